@@ -1,8 +1,11 @@
 CREATE EXTENSION IF NOT EXISTS "uuid-ossp";
 SET TIME ZONE 'UTC';
 
-CREATE TYPE ContractType AS ENUM ('perpetual', 'current_quarter', 'next_quarter');
+CREATE TYPE ContractType AS ENUM ('perpetual', 'current_quarter', 'next_quarter', 'spot');
 CREATE TYPE MarketRegime AS ENUM ('none', 'trending_up', 'trending_down', 'ranging', 'high_volatility', 'low_volatility');
+CREATE TYPE TradingSession AS ENUM ('asia', 'europe', 'us');
+CREATE TYPE LongShortRatioType AS ENUM ('global_account', 'top_trader_account', 'top_trader_position');
+CREATE TYPE DataIssueType AS ENUM ('invalid_ohlc', 'negative_volume', 'non_monotonic_time', 'duplicate_candle', 'bad_ingest');
 CREATE TYPE PricePattern AS ENUM (
     'none',
     'double_top',
@@ -24,6 +27,10 @@ CREATE TABLE Timeframes (
     interval_minutes INTEGER NOT NULL,
     created_at TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
 
+    -- Window start of the initial fetch and end of its last completed chunk
+    initialized_from TIMESTAMPTZ,
+    initialized_until TIMESTAMPTZ,
+
     UNIQUE (symbol, contract_type, interval_minutes)
 );
 
@@ -92,6 +99,45 @@ CREATE TABLE MarketData (
 
     created_at TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
 
+    -- Calendar features
+    trading_session TradingSession,
+    day_of_week SMALLINT, -- 0 for Monday through 6 for Sunday
+    hours_to_weekly_close DECIMAL(10,4),
+    hours_to_monthly_close DECIMAL(10,4),
+    is_holiday BOOLEAN,
+
+    -- Deviation from session and hour-of-week baselines
+    session_volume_ratio DECIMAL(10,4),
+    session_volatility_ratio DECIMAL(10,4),
+    hour_of_week_volume_ratio DECIMAL(10,4),
+    hour_of_week_volatility_ratio DECIMAL(10,4),
+
+    -- Derivatives
+    funding_rate DECIMAL(12,8), -- latest settled rate at the candle close
+    open_interest DECIMAL(30,8), -- contracts outstanding at the candle close
+    open_interest_change DECIMAL(20,8), -- % change from the candle open
+    long_short_ratio DECIMAL(20,8), -- all accounts, at the candle close
+    top_trader_account_ratio DECIMAL(20,8), -- top trader accounts, at the candle close
+    top_trader_position_ratio DECIMAL(20,8), -- top trader positions, at the candle close
+
+    -- Order flow
+    volume_delta DECIMAL(30,8), -- taker buy minus taker sell volume
+    cvd DECIMAL(30,8), -- cumulative volume delta since 00:00 UTC
+
+    -- Liquidations, lower bounds from the forceOrder stream
+    long_liquidation_volume DECIMAL(30,8), -- liquidated long quantity while the candle was open
+    short_liquidation_volume DECIMAL(30,8), -- liquidated short quantity while the candle was open
+
+    -- Mark and index prices
+    mark_close DECIMAL(20,8),
+    index_close DECIMAL(20,8),
+    basis DECIMAL(12,6), -- % of the close over the index close
+
+    -- Taker buys, from the exchange candle when it reports them
+    taker_buy_volume DECIMAL(20,8),
+    taker_buy_quote_volume DECIMAL(30,8),
+    taker_buy_ratio DECIMAL(10,8), -- share of the volume bought by takers
+
     UNIQUE (open_time, timeframe_id)
 );
 
@@ -113,19 +159,309 @@ CREATE TABLE Positions (
     created_at TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP
 );
 
+-- Registry of trained model artifacts
+CREATE TABLE Models (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    name VARCHAR(50) NOT NULL,
+    version VARCHAR(50) NOT NULL,
+    artifact BYTEA NOT NULL,
+    training_start TIMESTAMPTZ,
+    training_end TIMESTAMPTZ,
+    hyperparameters JSONB NOT NULL DEFAULT '{}',
+    metrics JSONB NOT NULL DEFAULT '{}',
+    created_at TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+
+    UNIQUE (name, version)
+);
+
 CREATE TABLE ModelPredictions (
     id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
-    market_data_id UUID REFERENCES MarketData(id),
-    timeframe_id UUID REFERENCES Timeframes(id),
+    market_data_id UUID NOT NULL REFERENCES MarketData(id),
+    timeframe_id UUID NOT NULL REFERENCES Timeframes(id),
+    model_id UUID REFERENCES Models(id),
     lstm_pred DECIMAL(10,4) NOT NULL,
     cnn_pred DECIMAL(10,4) NOT NULL,
     dnn_pred DECIMAL(10,4) NOT NULL,
     ensemble_pred DECIMAL(10,4) NOT NULL,
     confidence DECIMAL(5,4) NOT NULL,
     prediction_time TIMESTAMPTZ NOT NULL,
+    created_at TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+
+    -- Outcome once the horizon has elapsed, predictions > 0 are long and < 0 short
+    horizon_candles INTEGER,
+    realized_return DECIMAL(10,4), -- % close-to-close over the horizon
+    evaluated_at TIMESTAMPTZ
+);
+
+-- Rolling hit rate and expectancy of each model head over its latest evaluated predictions
+CREATE TABLE ModelScoreboard (
+    timeframe_id UUID NOT NULL REFERENCES Timeframes(id),
+    model VARCHAR(20) NOT NULL,
+    window_size INTEGER NOT NULL,
+    evaluated_count INTEGER NOT NULL,
+    accuracy DECIMAL(5,4) NOT NULL,
+    expectancy DECIMAL(10,4) NOT NULL,
+    updated_at TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+
+    PRIMARY KEY (timeframe_id, model)
+);
+
+-- Triple-barrier training targets of analyzed candles, label 1 long, -1 short, 0 none
+CREATE TABLE TripleBarrierLabels (
+    market_data_id UUID PRIMARY KEY REFERENCES MarketData(id) ON DELETE CASCADE,
+    timeframe_id UUID NOT NULL REFERENCES Timeframes(id),
+    open_time TIMESTAMPTZ NOT NULL,
+    label SMALLINT NOT NULL,
+    barrier VARCHAR(5) NOT NULL, -- upper, lower or time
+    realized_return DECIMAL(10,4) NOT NULL, -- % from the close to the barrier exit
+    holding_candles INTEGER NOT NULL,
+    profit_target DECIMAL(10,4) NOT NULL,
+    stop_loss DECIMAL(10,4) NOT NULL,
+    max_holding_candles INTEGER NOT NULL,
+    created_at TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Best levels of the order book, imbalance = (bid - ask) / (bid + ask) quantity
+CREATE TABLE OrderBookSnapshots (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    symbol VARCHAR(20) NOT NULL,
+    contract_type VARCHAR(10) NOT NULL,
+    snapshot_time TIMESTAMPTZ NOT NULL,
+    last_update_id BIGINT NOT NULL,
+    bid_prices DECIMAL(20,8)[] NOT NULL,
+    bid_quantities DECIMAL(20,8)[] NOT NULL,
+    ask_prices DECIMAL(20,8)[] NOT NULL,
+    ask_quantities DECIMAL(20,8)[] NOT NULL,
+    imbalance DECIMAL(10,8),
+    created_at TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+
+    UNIQUE (symbol, contract_type, snapshot_time)
+);
+
+-- Perpetual funding rates, unsettled rows hold the predicted rate of the next funding
+CREATE TABLE FundingRates (
+    symbol VARCHAR(20) NOT NULL,
+    funding_time TIMESTAMPTZ NOT NULL,
+    funding_rate DECIMAL(12,8) NOT NULL,
+    mark_price DECIMAL(20,8),
+    settled BOOLEAN NOT NULL,
+    updated_at TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+
+    PRIMARY KEY (symbol, funding_time)
+);
+
+-- Open interest history, one series per symbol and sampling period
+CREATE TABLE OpenInterest (
+    symbol VARCHAR(20) NOT NULL,
+    period_minutes INTEGER NOT NULL,
+    timestamp TIMESTAMPTZ NOT NULL,
+    sum_open_interest DECIMAL(30,8) NOT NULL,
+    sum_open_interest_value DECIMAL(30,8) NOT NULL,
+    created_at TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+
+    PRIMARY KEY (symbol, period_minutes, timestamp)
+);
+
+-- Long/short ratios of all accounts and of the top traders, one series per
+-- symbol, sampling period and ratio type
+CREATE TABLE LongShortRatios (
+    symbol VARCHAR(20) NOT NULL,
+    period_minutes INTEGER NOT NULL,
+    ratio_type LongShortRatioType NOT NULL,
+    timestamp TIMESTAMPTZ NOT NULL,
+    long_short_ratio DECIMAL(20,8) NOT NULL,
+    long_share DECIMAL(10,8) NOT NULL,
+    short_share DECIMAL(10,8) NOT NULL,
+    created_at TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+
+    PRIMARY KEY (symbol, period_minutes, ratio_type, timestamp)
+);
+
+-- Taker flow of each candle, aggregated from its aggTrades
+CREATE TABLE CandleTradeFlows (
+    market_data_id UUID PRIMARY KEY REFERENCES MarketData(id) ON DELETE CASCADE,
+    first_trade_id BIGINT,
+    last_trade_id BIGINT,
+    trade_count INTEGER NOT NULL,
+    taker_buy_volume DECIMAL(30,8) NOT NULL,
+    taker_sell_volume DECIMAL(30,8) NOT NULL,
     created_at TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP
 );
 
+-- Liquidation orders from the forceOrder stream, which pushes at most one
+-- per symbol and second
+CREATE TABLE Liquidations (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    symbol VARCHAR(20) NOT NULL,
+    side VARCHAR(4) NOT NULL, -- SELL liquidates a long, BUY a short
+    price DECIMAL(20,8) NOT NULL,
+    average_price DECIMAL(20,8) NOT NULL,
+    quantity DECIMAL(30,8) NOT NULL,
+    trade_time TIMESTAMPTZ NOT NULL,
+    created_at TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+
+    UNIQUE (symbol, trade_time)
+);
+
+-- Delta-encoded OHLCV chunks for candles past the archive horizon
+CREATE TABLE MarketDataArchive (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    timeframe_id UUID NOT NULL REFERENCES Timeframes(id),
+    symbol VARCHAR(20) NOT NULL,
+    contract_type VARCHAR(10) NOT NULL,
+    first_open_time TIMESTAMPTZ NOT NULL,
+    last_open_time TIMESTAMPTZ NOT NULL,
+    candle_count INTEGER NOT NULL,
+    format_version SMALLINT NOT NULL,
+    payload BYTEA NOT NULL,
+    created_at TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+
+    UNIQUE (timeframe_id, first_open_time)
+);
+
+-- Candles failing the integrity checks, with their OHLCV as they were found.
+-- Quarantined ones were removed from MarketData, the others are still
+-- referenced by a position or a prediction.
+CREATE TABLE DataIssues (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    market_data_id UUID NOT NULL,
+    timeframe_id UUID NOT NULL REFERENCES Timeframes(id),
+    issue_type DataIssueType NOT NULL,
+    open_time TIMESTAMPTZ NOT NULL,
+    open DECIMAL(20,8) NOT NULL,
+    high DECIMAL(20,8) NOT NULL,
+    low DECIMAL(20,8) NOT NULL,
+    close DECIMAL(20,8) NOT NULL,
+    volume DECIMAL(20,8) NOT NULL,
+    details TEXT NOT NULL,
+    quarantined BOOLEAN NOT NULL,
+    created_at TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+
+    UNIQUE (market_data_id, issue_type)
+);
+
+-- Trading rules of the Binance USD-M futures symbols, from exchangeInfo
+CREATE TABLE Symbols (
+    symbol VARCHAR(20) PRIMARY KEY,
+    pair VARCHAR(20) NOT NULL,
+    contract_type VARCHAR(20) NOT NULL,
+    status VARCHAR(20) NOT NULL,
+    base_asset VARCHAR(20) NOT NULL,
+    quote_asset VARCHAR(20) NOT NULL,
+    price_precision SMALLINT NOT NULL,
+    quantity_precision SMALLINT NOT NULL,
+    tick_size DECIMAL(20,8) NOT NULL, -- prices are multiples of it
+    step_size DECIMAL(20,8) NOT NULL, -- quantities are multiples of it
+    min_quantity DECIMAL(30,8) NOT NULL,
+    min_notional DECIMAL(20,8),
+    updated_at TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Notional tiers of each symbol, from the signed leverageBracket endpoint
+CREATE TABLE LeverageBrackets (
+    symbol VARCHAR(20) NOT NULL,
+    bracket SMALLINT NOT NULL,
+    initial_leverage SMALLINT NOT NULL, -- max leverage within the tier
+    notional_floor DECIMAL(30,8) NOT NULL,
+    notional_cap DECIMAL(30,8) NOT NULL,
+    maint_margin_ratio DECIMAL(10,6) NOT NULL,
+
+    PRIMARY KEY (symbol, bracket)
+);
+
+
+-- Flat model-ready row of each usable candle: its indicators next to the
+-- funding and open interest state at its close and its triple barrier label
+CREATE VIEW TrainingFeatureRows AS
+SELECT m.id AS market_data_id,
+       m.timeframe_id,
+       m.symbol,
+       m.contract_type,
+       m.open_time,
+       m.open,
+       m.high,
+       m.low,
+       m.close,
+       m.volume,
+       m.trades,
+       m.rsi_14,
+       m.macd_line,
+       m.macd_signal,
+       m.macd_histogram,
+       m.bb_upper,
+       m.bb_middle,
+       m.bb_lower,
+       m.atr_14,
+       m.adx,
+       m.dmi_plus,
+       m.dmi_minus,
+       m.trend_strength,
+       m.trend_direction,
+       m.market_regime::text AS market_regime,
+       m.pattern_strength,
+       m.nearest_support,
+       m.nearest_resistance,
+       m.depth_imbalance,
+       m.volatility_1h,
+       m.volatility_24h,
+       m.price_change_1h,
+       m.price_change_24h,
+       m.volume_change_1h,
+       m.volume_change_24h,
+       m.trading_session::text AS trading_session,
+       m.day_of_week,
+       m.hours_to_weekly_close,
+       m.hours_to_monthly_close,
+       m.is_holiday,
+       m.session_volume_ratio,
+       m.session_volatility_ratio,
+       m.hour_of_week_volume_ratio,
+       m.hour_of_week_volatility_ratio,
+       m.funding_rate,
+       EXTRACT(EPOCH FROM m.close_time - f.funding_time) / 3600 AS hours_since_funding,
+       m.open_interest,
+       o.sum_open_interest_value AS open_interest_value,
+       m.open_interest_change,
+       m.long_short_ratio,
+       m.top_trader_account_ratio,
+       m.top_trader_position_ratio,
+       m.volume_delta,
+       m.cvd,
+       m.long_liquidation_volume,
+       m.short_liquidation_volume,
+       m.basis,
+       m.taker_buy_ratio,
+       l.label,
+       l.realized_return AS barrier_return,
+       l.holding_candles
+FROM MarketData AS m
+LEFT JOIN LATERAL (
+    SELECT funding_time
+    FROM FundingRates
+    WHERE symbol = m.symbol
+      AND settled
+      AND funding_time <= m.close_time
+    ORDER BY funding_time DESC
+    LIMIT 1
+) AS f ON m.contract_type = 'PERPETUAL'
+LEFT JOIN LATERAL (
+    SELECT sum_open_interest_value
+    FROM OpenInterest
+    WHERE symbol = m.symbol
+      AND timestamp <= m.close_time
+    ORDER BY timestamp DESC, period_minutes ASC
+    LIMIT 1
+) AS o ON m.contract_type = 'PERPETUAL'
+LEFT JOIN TripleBarrierLabels AS l ON l.market_data_id = m.id
+WHERE m.usable_by_model;
+
+-- Generated from TrainingFeatureRows by the analyzer for the candles it
+-- analyzes, read by the training dataset loaders
+CREATE TABLE TrainingFeatures AS SELECT * FROM TrainingFeatureRows;
+ALTER TABLE TrainingFeatures
+    ADD PRIMARY KEY (market_data_id),
+    ADD FOREIGN KEY (market_data_id) REFERENCES MarketData(id) ON DELETE CASCADE;
+
 
 -- Create indexes with open_time as first column for hypertable compatibility
 CREATE UNIQUE INDEX idx_market_data_unique ON MarketData (open_time, symbol, contract_type, timeframe_id);
@@ -134,3 +470,9 @@ CREATE INDEX idx_market_data_timeframe ON MarketData (open_time DESC, timeframe_
 CREATE INDEX idx_market_data_analyzed ON MarketData (analyzed, timeframe_id);
 CREATE INDEX idx_positions_symbol ON Positions (symbol, contract_type, status);
 CREATE INDEX idx_model_predictions_market ON ModelPredictions (market_data_id, prediction_time DESC);
+CREATE INDEX idx_model_predictions_outcome ON ModelPredictions (timeframe_id, evaluated_at, prediction_time DESC);
+CREATE INDEX idx_triple_barrier_labels_timeframe ON TripleBarrierLabels (timeframe_id, open_time DESC);
+CREATE INDEX idx_order_book_snapshots_time ON OrderBookSnapshots (symbol, contract_type, snapshot_time DESC);
+CREATE INDEX idx_liquidations_time ON Liquidations (symbol, trade_time DESC);
+CREATE INDEX idx_market_data_archive_timeframe ON MarketDataArchive (timeframe_id, symbol, contract_type, first_open_time DESC);
+CREATE INDEX idx_training_features_timeframe ON TrainingFeatures (timeframe_id, open_time);
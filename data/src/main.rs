@@ -1,16 +1,54 @@
 use anyhow::Result;
+use chrono::{DateTime, NaiveDate, Utc};
 use clap::Parser;
 use dotenvy::dotenv;
-use models::timeframe::{ContractType, Interval};
+use futures_util::TryStreamExt;
+use models::indicator_filter::IndicatorFilter;
+use models::model::Model;
+use models::model_prediction::ModelPrediction;
+use models::position::Position;
+use models::timeframe::{ContractType, Interval, TimeFrame};
+use repositories::{
+    data_issue_repository::DataIssueRepository, market_data_repository::MarketDataRepository,
+    model_prediction_repository::ModelPredictionRepository, model_repository::ModelRepository,
+    position_repository::PositionRepository, storage_repository::StorageRepository,
+    symbol_repository::SymbolRepository, timeframe_repository::TimeFrameRepository,
+    training_feature_repository::TrainingFeatureRepository,
+};
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use serde_json::Value;
 use services::{
-    configuration_service::ConfigService, market_data_analyzer_service::MarketDataAnalyzer,
+    account_sync_service::AccountSyncService, binance_account_service::BinanceAccountClient,
+    binance_vision_loader_service::BinanceVisionLoader, clock_sync_service::ClockSync,
+    coinbase_fetcher_service::CoinbaseMarketDataFetcher, configuration_service::AlertConfig,
+    configuration_service::ConfigService, configuration_service::CsvImportConfig,
+    configuration_service::Exchange, configuration_service::LabelingConfig,
+    configuration_service::OrderBookConfig, configuration_service::StorageConfig,
+    configuration_service::TradingConfig, database_service::DatabaseService,
+    exchange_info_service::ExchangeInfoSync, exchange_service::Exchange as _,
+    liquidation_collector_service::LiquidationCollector,
+    market_data_aggregator_service::MarketDataAggregator,
+    market_data_analyzer_service::MarketDataAnalyzer,
+    market_data_archiver_service::MarketDataArchiver,
     market_data_fetcher_service::MarketDataFetcher,
+    market_data_importer_service::MarketDataImporter,
+    market_data_labeler_service::MarketDataLabeler,
+    market_data_spike_detector_service::MarketDataSpikeDetector,
+    market_data_streamer_service::MarketDataStreamer, okx_fetcher_service::OkxMarketDataFetcher,
+    order_book_collector_service::OrderBookCollector,
+    prediction_outcome_service::PredictionOutcomeTracker,
+    storage_policy_service::StoragePolicyService,
 };
+use std::collections::{BTreeMap, HashMap};
+use std::io::{BufRead, BufReader, Write};
 use std::{path::Path, str::FromStr, sync::Arc};
-use tokio::sync::broadcast;
-use tokio::sync::Semaphore;
+use tokio::sync::{broadcast, mpsc, Mutex};
+use tokio::sync::{AcquireError, Semaphore, SemaphorePermit};
 use tokio_cron_scheduler::{Job, JobScheduler};
-use utils::helper::WorkerError;
+use utils::evaluation::{ClassificationReport, LabeledPrediction};
+use utils::helper::{Helper, WorkerError};
+use uuid::Uuid;
 
 mod models;
 mod repositories;
@@ -28,6 +66,151 @@ struct Args {
 
     #[arg(short = 'i', long = "init", default_value_t = true, action = clap::ArgAction::Set)]
     initialize: bool,
+
+    /// Print the model scoreboard and exit
+    #[arg(long = "scoreboard", default_value_t = false)]
+    scoreboard: bool,
+
+    /// Backfill the missing candles of the fetched timeframes and exit
+    #[arg(long = "backfill-gaps", default_value_t = false)]
+    backfill_gaps: bool,
+
+    /// Fetch the recent candles of the configured timeframes, print them and
+    /// their count without writing to the database, and exit
+    #[arg(long = "dry-run", default_value_t = false)]
+    dry_run: bool,
+
+    /// Check the stored candles of the configured timeframes for OHLC,
+    /// volume, time ordering and duplicate issues, report them and exit
+    #[arg(long = "verify-data", default_value_t = false)]
+    verify_data: bool,
+
+    /// Move the candles failing --verify-data into the DataIssues table
+    #[arg(long = "quarantine", requires = "verify_data", default_value_t = false)]
+    quarantine: bool,
+
+    /// Remove the candles of the --symbol, --contract-type and --interval
+    /// timeframe opened from this date on, with their predictions, and exit
+    #[arg(long = "delete-from", requires_all = ["symbol", "contract_type", "interval"])]
+    delete_from: Option<NaiveDate>,
+
+    /// Day the --delete-from range stops at, excluded, every later candle by default
+    #[arg(long = "delete-to", requires = "delete_from")]
+    delete_to: Option<NaiveDate>,
+
+    /// Quarantine the --delete-from candles as bad_ingest issues with this
+    /// reason instead of deleting them
+    #[arg(long = "reason", requires = "delete_from")]
+    reason: Option<String>,
+
+    /// Print the retention state of the candle storage and exit
+    #[arg(long = "storage-policies", default_value_t = false)]
+    storage_policies: bool,
+
+    /// Apply the configured storage policies before --storage-policies prints them
+    #[arg(long = "apply", requires = "storage_policies", default_value_t = false)]
+    apply: bool,
+
+    /// Print the futures balances, open positions and last day of income and exit
+    #[arg(long = "account", default_value_t = false)]
+    account: bool,
+
+    /// Refresh the cached exchange rules and print those of a futures symbol and exit
+    #[arg(long = "symbol-info")]
+    symbol_info: Option<String>,
+
+    /// Register a trained model artifact in the model registry and exit
+    #[arg(long = "register-model", requires_all = ["model_name", "model_version"])]
+    register_model: Option<String>,
+
+    #[arg(long = "model-name")]
+    model_name: Option<String>,
+
+    #[arg(long = "model-version")]
+    model_version: Option<String>,
+
+    /// Start of the training window (RFC 3339)
+    #[arg(long = "training-start")]
+    training_start: Option<DateTime<Utc>>,
+
+    /// End of the training window (RFC 3339)
+    #[arg(long = "training-end")]
+    training_end: Option<DateTime<Utc>>,
+
+    /// Hyperparameters as a JSON object
+    #[arg(long = "hyperparameters")]
+    hyperparameters: Option<Value>,
+
+    /// Evaluation metrics as a JSON object
+    #[arg(long = "metrics")]
+    metrics: Option<Value>,
+
+    /// Persist model predictions from a JSON lines file and exit
+    #[arg(long = "import-predictions")]
+    import_predictions: Option<String>,
+
+    /// Load OHLCV candles from a CSV file into MarketData and exit
+    #[arg(long = "import-csv", requires_all = ["symbol", "contract_type", "interval"])]
+    import_csv: Option<String>,
+
+    /// Load the Binance Vision kline archives from this day (YYYY-MM-DD) on and exit
+    #[arg(long = "bulk-load", requires_all = ["symbol", "contract_type", "interval"])]
+    bulk_load: Option<NaiveDate>,
+
+    /// Last day of the archives to load, yesterday by default
+    #[arg(long = "bulk-load-to")]
+    bulk_load_to: Option<NaiveDate>,
+
+    #[arg(long = "symbol")]
+    symbol: Option<String>,
+
+    #[arg(long = "contract-type")]
+    contract_type: Option<ContractType>,
+
+    #[arg(long = "interval")]
+    interval: Option<Interval>,
+
+    /// Print the newest candles of a timeframe matching every filter, such as
+    /// "rsi_14 < 30", as JSON lines and exit
+    #[arg(long = "where", requires_all = ["symbol", "contract_type", "interval"])]
+    filters: Vec<IndicatorFilter>,
+
+    /// Print every stored candle of a timeframe opened from this date on as
+    /// JSON lines, oldest first, and exit
+    #[arg(long = "export", requires_all = ["symbol", "contract_type", "interval"])]
+    export: Option<NaiveDate>,
+
+    /// Print the training features of a timeframe opened from this date on as
+    /// CSV with a header line, oldest first, and exit
+    #[arg(long = "export-features", requires_all = ["symbol", "contract_type", "interval"])]
+    export_features: Option<NaiveDate>,
+
+    /// Record the positions of a JSON lines file and exit. A position already
+    /// stored takes the exit, pnl and status of its line.
+    #[arg(long = "import-positions")]
+    import_positions: Option<String>,
+
+    /// Print the open then the latest closed positions of a symbol as JSON
+    /// lines and exit, for the --contract-type pairs (PERPETUAL by default)
+    #[arg(long = "positions")]
+    positions: Option<String>,
+
+    /// Print the stored predictions of a market data row as JSON lines and exit
+    #[arg(long = "predictions")]
+    predictions: Option<Uuid>,
+
+    /// Print the predictions of a timeframe with their candle and realized
+    /// outcome as JSON lines and exit
+    #[arg(long = "outcomes")]
+    outcomes: Option<Uuid>,
+
+    /// First day of the --outcomes predictions, 30 days ago by default
+    #[arg(long = "outcomes-from", requires = "outcomes")]
+    outcomes_from: Option<NaiveDate>,
+
+    /// Print a classification report of the evaluated predictions of a timeframe and exit
+    #[arg(long = "evaluate")]
+    evaluate: Option<Uuid>,
 }
 
 fn setup_logging() {
@@ -36,7 +219,198 @@ fn setup_logging() {
         .init();
 }
 
-const MAX_CONCURRENT_TASKS: usize = 5;
+const DEFAULT_MAX_CONCURRENT_TASKS: usize = 5;
+const PREDICTION_IMPORT_CHUNK_SIZE: usize = 1000;
+const EVALUATION_WINDOW: i64 = 5000;
+const DEFAULT_OUTCOME_DAYS: i64 = 30;
+const FILTERED_CANDLES_LIMIT: i64 = 1000;
+const EXPORT_PAGE_SIZE: i64 = 5000;
+const POSITION_HISTORY_LIMIT: i64 = 100;
+const NEUTRAL_RETURN_BAND: f64 = 0.1; // % move counted as no position
+const GAP_SCAN_CRON: &str = "0 15 * * * *"; // Every hour at minute 15
+
+#[derive(Clone)]
+struct WorkerOptions {
+    exchange: Exchange,
+    lookback_days: u32,
+    fetch_limit: Option<i32>,
+    initialize: bool,
+    archive_after_days: Option<u32>,
+    raw_retention_days: Option<u32>, // Set for 1m timeframes only
+    alerts: Option<AlertConfig>,
+    aggregate_from: Option<String>,
+    prediction_horizon_candles: Option<u32>,
+    labeling: Option<LabelingConfig>,
+    candle_closed: Option<mpsc::Sender<()>>, // Set when klines are streamed
+    trade_flow: bool,
+    analyzer: Arc<MarketDataAnalyzer>,
+}
+
+// Fetch jobs hold a permit of the global limit and of their exchange one,
+// when set
+#[derive(Clone)]
+struct TaskLimits {
+    global: Arc<Semaphore>,
+    exchange: Option<Arc<Semaphore>>,
+}
+
+impl TaskLimits {
+    // The exchange permit comes first, so jobs queued behind a busy exchange
+    // leave the global slots to the other ones
+    async fn acquire(
+        &self,
+    ) -> Result<(Option<SemaphorePermit<'_>>, SemaphorePermit<'_>), AcquireError> {
+        let exchange = match &self.exchange {
+            Some(semaphore) => Some(semaphore.acquire().await?),
+            None => None,
+        };
+        Ok((exchange, self.global.acquire().await?))
+    }
+}
+
+// Where a worker gets its candles from: the Binance, OKX or Coinbase API, or
+// a lower timeframe of the same pair aggregated locally
+#[derive(Clone)]
+enum CandleSource {
+    Api(Arc<MarketDataFetcher>),
+    Okx(Arc<OkxMarketDataFetcher>),
+    Coinbase(Arc<CoinbaseMarketDataFetcher>),
+    Aggregated(Arc<MarketDataAggregator>),
+}
+
+impl CandleSource {
+    async fn new(
+        symbol: String,
+        contract_type: ContractType,
+        interval: String,
+        exchange: Exchange,
+        lookback_days: u32,
+        fetch_limit: Option<i32>,
+        aggregate_from: Option<String>,
+    ) -> Result<Self, WorkerError> {
+        Ok(match aggregate_from {
+            Some(source_interval) => CandleSource::Aggregated(Arc::new(
+                MarketDataAggregator::new(
+                    symbol,
+                    contract_type,
+                    interval,
+                    source_interval,
+                    lookback_days,
+                )
+                .await
+                .map_err(|e| WorkerError::MarketData(e.to_string()))?,
+            )),
+            None => {
+                Self::fetched(
+                    symbol,
+                    contract_type,
+                    interval,
+                    exchange,
+                    lookback_days,
+                    fetch_limit,
+                    false,
+                )
+                .await?
+            }
+        })
+    }
+
+    // Candles fetched from the exchange API, a dry run prints them in place
+    // of saving them
+    async fn fetched(
+        symbol: String,
+        contract_type: ContractType,
+        interval: String,
+        exchange: Exchange,
+        lookback_days: u32,
+        fetch_limit: Option<i32>,
+        dry_run: bool,
+    ) -> Result<Self, WorkerError> {
+        Ok(match exchange {
+            Exchange::Binance => CandleSource::Api(Arc::new(
+                MarketDataFetcher::new(
+                    symbol,
+                    contract_type,
+                    interval,
+                    lookback_days,
+                    fetch_limit,
+                    dry_run,
+                )
+                .await
+                .map_err(|e| WorkerError::MarketData(e.to_string()))?,
+            )),
+            Exchange::Okx => CandleSource::Okx(Arc::new(
+                OkxMarketDataFetcher::new(symbol, contract_type, interval, lookback_days, dry_run)
+                    .await
+                    .map_err(|e| WorkerError::MarketData(e.to_string()))?,
+            )),
+            Exchange::Coinbase => CandleSource::Coinbase(Arc::new(
+                CoinbaseMarketDataFetcher::new(
+                    symbol,
+                    contract_type,
+                    interval,
+                    lookback_days,
+                    dry_run,
+                )
+                .await
+                .map_err(|e| WorkerError::MarketData(e.to_string()))?,
+            )),
+        })
+    }
+
+    fn timeframe(&self) -> &TimeFrame {
+        match self {
+            Self::Api(fetcher) => &fetcher.timeframe,
+            Self::Okx(fetcher) => &fetcher.timeframe,
+            Self::Coinbase(fetcher) => &fetcher.timeframe,
+            Self::Aggregated(aggregator) => &aggregator.timeframe,
+        }
+    }
+
+    async fn initialize(&self) -> Result<usize> {
+        match self {
+            Self::Api(fetcher) => Ok(fetcher.initialize_market_data().await?),
+            Self::Okx(fetcher) => Ok(fetcher.initialize_market_data().await?),
+            Self::Coinbase(fetcher) => Ok(fetcher.initialize_market_data().await?),
+            Self::Aggregated(aggregator) => aggregator.aggregate_market_data().await,
+        }
+    }
+
+    async fn fetch_recent(&self) -> Result<usize> {
+        match self {
+            Self::Api(fetcher) => Ok(fetcher.fetch_recent_market_data().await?),
+            Self::Okx(fetcher) => Ok(fetcher.fetch_recent_market_data().await?),
+            Self::Coinbase(fetcher) => Ok(fetcher.fetch_recent_market_data().await?),
+            Self::Aggregated(aggregator) => aggregator.aggregate_market_data().await,
+        }
+    }
+
+    // Aggregated timeframes rebuild the buckets their source timeframe has
+    // been backfilled for
+    async fn backfill_gaps(&self, since: DateTime<Utc>) -> Result<usize> {
+        match self {
+            Self::Api(fetcher) => Ok(fetcher.backfill_gaps(since).await?),
+            Self::Okx(fetcher) => Ok(fetcher.backfill_gaps(since).await?),
+            Self::Coinbase(fetcher) => Ok(fetcher.backfill_gaps(since).await?),
+            Self::Aggregated(aggregator) => aggregator.backfill_gaps(since).await,
+        }
+    }
+}
+
+// Start of the stored window, candles past `archive_after_days`, and 1m
+// candles past `raw_retention_days`, only remain in MarketData when
+// referenced and would read as gaps
+fn gap_scan_since(
+    lookback_days: u32,
+    archive_after_days: Option<u32>,
+    raw_retention_days: Option<u32>,
+) -> DateTime<Utc> {
+    let days = [archive_after_days, raw_retention_days]
+        .into_iter()
+        .flatten()
+        .fold(lookback_days, u32::min);
+    Utc::now() - chrono::Duration::days(days.into())
+}
 
 fn get_cron_expression(interval: &str) -> String {
     match Interval::from_str(interval).unwrap() {
@@ -58,59 +432,158 @@ fn get_cron_expression(interval: &str) -> String {
     .to_string()
 }
 
+async fn fetch_derivatives_data(fetcher: &MarketDataFetcher) {
+    if let Err(e) = fetcher.fetch_funding_rates().await {
+        eprintln!("Error fetching funding rates: {}", e);
+    }
+    if let Err(e) = fetcher.fetch_open_interest().await {
+        eprintln!("Error fetching open interest: {}", e);
+    }
+    if let Err(e) = fetcher.fetch_long_short_ratios().await {
+        eprintln!("Error fetching long/short ratios: {}", e);
+    }
+    if let Err(e) = fetcher.fetch_price_closes().await {
+        eprintln!("Error fetching mark and index prices: {}", e);
+    }
+}
+
 async fn run_timeframe_worker(
     symbol: String,
     contract_type: ContractType,
     interval: String,
-    lookback_days: u32,
-    semaphore: Arc<Semaphore>,
-    initialize: bool,
+    options: WorkerOptions,
+    task_limits: TaskLimits,
     mut shutdown: broadcast::Receiver<()>,
 ) -> Result<(), WorkerError> {
     let mut scheduler = JobScheduler::new()
         .await
         .map_err(|e| WorkerError::Config(e.to_string()))?;
 
-    let market_data_fetcher = Arc::new(
-        MarketDataFetcher::new(
-            symbol.clone(),
-            contract_type.clone(),
-            interval.clone(),
-            lookback_days,
-        )
-        .await
-        .map_err(|e| WorkerError::MarketData(e.to_string()))?,
-    );
+    let candle_source = CandleSource::new(
+        symbol.clone(),
+        contract_type.clone(),
+        interval.clone(),
+        options.exchange,
+        options.lookback_days,
+        options.fetch_limit,
+        options.aggregate_from.clone(),
+    )
+    .await?;
 
-    if initialize {
+    if options.initialize {
         // Initial data fetch
-        market_data_fetcher
-            .initialize_market_data()
+        candle_source
+            .initialize()
             .await
             .map_err(|e| WorkerError::MarketData(e.to_string()))?;
     } else {
         // Fetch recent market data
-        if let Err(e) = market_data_fetcher.fetch_recent_market_data().await {
+        if let Err(e) = candle_source.fetch_recent().await {
             eprintln!("Error fetching market data: {}", e);
         }
     }
 
-    match MarketDataAnalyzer::new().await {
-        Ok(analyzer) => {
-            if let Err(e) = analyzer.analyze_market_data().await {
-                eprintln!("Error analyzing market data: {}", e);
-            }
+    // Funding and open interest only exist for perpetuals
+    let derivatives_fetcher = match (&candle_source, &contract_type) {
+        (CandleSource::Api(fetcher), ContractType::Perpetual) => Some(Arc::clone(fetcher)),
+        _ => None,
+    };
+    if let Some(fetcher) = &derivatives_fetcher {
+        fetch_derivatives_data(fetcher).await;
+    }
+
+    // Trades are fetched by perpetual symbol, like the derivatives data
+    let trade_flow_fetcher = derivatives_fetcher.clone().filter(|_| options.trade_flow);
+    if let Some(fetcher) = &trade_flow_fetcher {
+        if let Err(e) = fetcher.fetch_trade_flows(true).await {
+            eprintln!("Error fetching trade flows: {}", e);
+        }
+    }
+
+    if let Err(e) = options.analyzer.analyze_market_data().await {
+        eprintln!("Error analyzing market data: {}", e);
+    }
+
+    let labeler = match options.labeling {
+        Some(labeling) => Some(Arc::new(
+            MarketDataLabeler::new(candle_source.timeframe().id, labeling)
+                .await
+                .map_err(|e| WorkerError::Config(e.to_string()))?,
+        )),
+        None => None,
+    };
+    if let Some(labeler) = &labeler {
+        if let Err(e) = labeler.label_market_data().await {
+            eprintln!("Error labeling market data: {}", e);
         }
-        Err(e) => eprintln!("Error creating analyzer: {}", e),
     }
 
-    let cron_expression = get_cron_expression(&interval);
-    let sem = Arc::clone(&semaphore);
-    let fetcher = Arc::clone(&market_data_fetcher);
+    let archiver = match options.archive_after_days {
+        Some(days) => Some(Arc::new(
+            MarketDataArchiver::new(candle_source.timeframe().id, days)
+                .await
+                .map_err(|e| WorkerError::Config(e.to_string()))?,
+        )),
+        None => None,
+    };
+    if let Some(archiver) = &archiver {
+        if let Err(e) = archiver.archive_market_data().await {
+            eprintln!("Error archiving market data: {}", e);
+        }
+    }
+
+    let outcome_tracker = match options.prediction_horizon_candles {
+        Some(horizon) => Some(Arc::new(
+            PredictionOutcomeTracker::new(candle_source.timeframe().id, horizon)
+                .await
+                .map_err(|e| WorkerError::Config(e.to_string()))?,
+        )),
+        None => None,
+    };
+    if let Some(outcome_tracker) = &outcome_tracker {
+        if let Err(e) = outcome_tracker.track_outcomes().await {
+            eprintln!("Error tracking prediction outcomes: {}", e);
+        }
+    }
+
+    let spike_detector = match options.alerts {
+        Some(alerts) => Some(Arc::new(
+            MarketDataSpikeDetector::new(candle_source.timeframe().clone(), alerts)
+                .await
+                .map_err(|e| WorkerError::Config(e.to_string()))?,
+        )),
+        None => None,
+    };
+
+    // Only candles fetched from the exchange can be streamed
+    let streamer = match (&candle_source, &options.candle_closed) {
+        (CandleSource::Api(fetcher), Some(candle_closed)) => Some(
+            MarketDataStreamer::new(Arc::clone(fetcher), candle_closed.clone())
+                .await
+                .map_err(|e| WorkerError::Config(e.to_string()))?,
+        ),
+        _ => None,
+    };
+
+    let cron_expression = match &options.aggregate_from {
+        // Follow the source timeframe, 30 seconds later so its fetch has landed
+        Some(source_interval) => get_cron_expression(source_interval).replacen('0', "30", 1),
+        None => get_cron_expression(&interval),
+    };
+    let limits = task_limits.clone();
+    let gap_scan_source = candle_source.clone();
+    let analyzer = Arc::clone(&options.analyzer);
 
     let job = Job::new_async(cron_expression.as_str(), move |_uuid, _lock| {
-        let sem = Arc::clone(&sem);
-        let fetcher = Arc::clone(&fetcher);
+        let limits = limits.clone();
+        let candle_source = candle_source.clone();
+        let archiver = archiver.clone();
+        let spike_detector = spike_detector.clone();
+        let outcome_tracker = outcome_tracker.clone();
+        let labeler = labeler.clone();
+        let derivatives_fetcher = derivatives_fetcher.clone();
+        let trade_flow_fetcher = trade_flow_fetcher.clone();
+        let analyzer = Arc::clone(&analyzer);
 
         tracing::info!(
             "Running Job {} {} {}",
@@ -120,8 +593,8 @@ async fn run_timeframe_worker(
         );
 
         Box::pin(async move {
-            let _permit = match sem.acquire().await {
-                Ok(permit) => permit,
+            let _permits = match limits.acquire().await {
+                Ok(permits) => permits,
                 Err(e) => {
                     eprintln!("Error acquiring semaphore: {}", e);
                     return;
@@ -129,19 +602,48 @@ async fn run_timeframe_worker(
             };
 
             // Fetch recent market data
-            if let Err(e) = fetcher.fetch_recent_market_data().await {
+            if let Err(e) = candle_source.fetch_recent().await {
                 eprintln!("Error fetching market data: {}", e);
                 return;
             }
 
+            if let Some(fetcher) = derivatives_fetcher {
+                fetch_derivatives_data(&fetcher).await;
+            }
+
+            if let Some(fetcher) = trade_flow_fetcher {
+                if let Err(e) = fetcher.fetch_trade_flows(false).await {
+                    eprintln!("Error fetching trade flows: {}", e);
+                }
+            }
+
+            if let Some(spike_detector) = spike_detector {
+                if let Err(e) = spike_detector.detect_spikes().await {
+                    eprintln!("Error detecting spikes: {}", e);
+                }
+            }
+
             // Analyze MarketData
-            match MarketDataAnalyzer::new().await {
-                Ok(analyzer) => {
-                    if let Err(e) = analyzer.analyze_market_data().await {
-                        eprintln!("Error analyzing market data: {}", e);
-                    }
+            if let Err(e) = analyzer.analyze_market_data().await {
+                eprintln!("Error analyzing market data: {}", e);
+            }
+
+            if let Some(labeler) = labeler {
+                if let Err(e) = labeler.label_market_data().await {
+                    eprintln!("Error labeling market data: {}", e);
+                }
+            }
+
+            if let Some(outcome_tracker) = outcome_tracker {
+                if let Err(e) = outcome_tracker.track_outcomes().await {
+                    eprintln!("Error tracking prediction outcomes: {}", e);
+                }
+            }
+
+            if let Some(archiver) = archiver {
+                if let Err(e) = archiver.archive_market_data().await {
+                    eprintln!("Error archiving market data: {}", e);
                 }
-                Err(e) => eprintln!("Error creating analyzer: {}", e),
             }
         })
     })
@@ -152,11 +654,46 @@ async fn run_timeframe_worker(
         .await
         .map_err(|e| WorkerError::Config(e.to_string()))?;
 
+    let gap_scan_job = Job::new_async(GAP_SCAN_CRON, move |_uuid, _lock| {
+        let limits = task_limits.clone();
+        let gap_scan_source = gap_scan_source.clone();
+        let since = gap_scan_since(
+            options.lookback_days,
+            options.archive_after_days,
+            options.raw_retention_days,
+        );
+
+        Box::pin(async move {
+            let _permits = match limits.acquire().await {
+                Ok(permits) => permits,
+                Err(e) => {
+                    eprintln!("Error acquiring semaphore: {}", e);
+                    return;
+                }
+            };
+
+            if let Err(e) = gap_scan_source.backfill_gaps(since).await {
+                eprintln!("Error backfilling market data gaps: {}", e);
+            }
+        })
+    })
+    .map_err(|e| WorkerError::Config(e.to_string()))?;
+
+    scheduler
+        .add(gap_scan_job)
+        .await
+        .map_err(|e| WorkerError::Config(e.to_string()))?;
+
     scheduler
         .start()
         .await
         .map_err(|e| WorkerError::Config(e.to_string()))?;
 
+    if let Some(streamer) = streamer {
+        let shutdown = shutdown.resubscribe();
+        tokio::spawn(async move { streamer.run(shutdown).await });
+    }
+
     match shutdown.recv().await {
         Ok(_) | Err(_) => scheduler
             .shutdown()
@@ -166,6 +703,875 @@ async fn run_timeframe_worker(
     Ok(())
 }
 
+async fn run_order_book_collector(
+    symbol: String,
+    contract_type: ContractType,
+    config: OrderBookConfig,
+    shutdown: broadcast::Receiver<()>,
+) -> Result<(), WorkerError> {
+    let collector = OrderBookCollector::new(symbol, contract_type, config)
+        .await
+        .map_err(|e| WorkerError::Config(e.to_string()))?;
+    collector.run(shutdown).await;
+    Ok(())
+}
+
+async fn run_stream_analyzer(
+    analyzer: Arc<MarketDataAnalyzer>,
+    candle_closed: mpsc::Receiver<()>,
+    shutdown: broadcast::Receiver<()>,
+) -> Result<(), WorkerError> {
+    analyzer.run(candle_closed, shutdown).await;
+    Ok(())
+}
+
+async fn run_liquidation_collector(
+    symbol: String,
+    shutdown: broadcast::Receiver<()>,
+) -> Result<(), WorkerError> {
+    let collector = LiquidationCollector::new(symbol)
+        .await
+        .map_err(|e| WorkerError::Config(e.to_string()))?;
+    collector.run(shutdown).await;
+    Ok(())
+}
+
+async fn run_account_sync(
+    symbols: Vec<String>,
+    interval_seconds: u64,
+    shutdown: broadcast::Receiver<()>,
+) -> Result<(), WorkerError> {
+    let service = AccountSyncService::new(symbols)
+        .await
+        .map_err(|e| WorkerError::Config(e.to_string()))?;
+    service.run(interval_seconds, shutdown).await;
+    Ok(())
+}
+
+// Binance signed requests and candle close checks use the Binance clock, a
+// failed sync leaves the local clock in use
+async fn sync_clock(clock_sync: &ClockSync) {
+    match clock_sync.sync().await {
+        Ok(offset) => tracing::info!("Binance clock offset: {}ms", offset),
+        Err(e) => tracing::error!("Clock sync failed: {}", e),
+    }
+}
+
+async fn run_exchange_info_sync(shutdown: broadcast::Receiver<()>) -> Result<(), WorkerError> {
+    let mut exchange_info_sync = ExchangeInfoSync::new()
+        .await
+        .map_err(|e| WorkerError::Config(e.to_string()))?;
+    exchange_info_sync.run(shutdown).await;
+    Ok(())
+}
+
+async fn run_storage_policies(
+    config: StorageConfig,
+    shutdown: broadcast::Receiver<()>,
+) -> Result<(), WorkerError> {
+    let service = StoragePolicyService::new(config)
+        .await
+        .map_err(|e| WorkerError::Config(e.to_string()))?;
+    service.run(shutdown).await;
+    Ok(())
+}
+
+async fn run_clock_sync(
+    clock_sync: ClockSync,
+    shutdown: broadcast::Receiver<()>,
+) -> Result<(), WorkerError> {
+    clock_sync.run(shutdown).await;
+    Ok(())
+}
+
+// Only the smallest timeframe of each pair is fetched, the buckets of the
+// timeframes aggregated from it are rebuilt afterwards
+async fn backfill_gaps(config: &TradingConfig) -> Result<(), WorkerError> {
+    let raw_retention_days = config
+        .storage
+        .as_ref()
+        .and_then(|storage| storage.raw_retention_days);
+
+    for pair in &config.pairs {
+        let Some(source_minutes) = pair
+            .timeframes
+            .iter()
+            .filter_map(|t| Helper::interval_to_minutes(&t.interval.to_string()))
+            .min()
+        else {
+            continue;
+        };
+        let since = gap_scan_since(
+            config.lookback_days,
+            config.archive_after_days,
+            raw_retention_days.filter(|_| source_minutes == 1),
+        );
+
+        let candle_source = CandleSource::new(
+            pair.symbol.clone(),
+            pair.contract_type.clone(),
+            Helper::minutes_to_interval(source_minutes),
+            pair.exchange.unwrap_or_default(),
+            config.lookback_days,
+            config.fetch_limit,
+            None,
+        )
+        .await?;
+        let inserted = candle_source
+            .backfill_gaps(since)
+            .await
+            .map_err(|e| WorkerError::MarketData(e.to_string()))?;
+
+        tracing::info!(
+            "Backfilled {} candles for {} {} {}",
+            inserted,
+            pair.symbol,
+            Helper::minutes_to_interval(source_minutes),
+            pair.contract_type
+        );
+
+        for timeframe in &pair.timeframes {
+            let interval = timeframe.interval.to_string();
+            match Helper::interval_to_minutes(&interval) {
+                Some(minutes) if MarketDataAggregator::can_aggregate(minutes, source_minutes) => {}
+                _ => continue,
+            }
+
+            let candle_source = CandleSource::new(
+                pair.symbol.clone(),
+                pair.contract_type.clone(),
+                interval.clone(),
+                pair.exchange.unwrap_or_default(),
+                config.lookback_days,
+                config.fetch_limit,
+                Some(Helper::minutes_to_interval(source_minutes)),
+            )
+            .await?;
+            // Over the window of the source candles, the only ones buckets
+            // are rebuilt from
+            let rebuilt = candle_source
+                .backfill_gaps(since)
+                .await
+                .map_err(|e| WorkerError::MarketData(e.to_string()))?;
+
+            tracing::info!(
+                "Rebuilt {} {} candles for {} {}",
+                rebuilt,
+                interval,
+                pair.symbol,
+                pair.contract_type
+            );
+        }
+    }
+
+    Ok(())
+}
+
+// Fetches the recent candles of every timeframe that is not aggregated,
+// printing them and their count per timeframe. The database is read for the
+// latest stored candles but never written to.
+async fn dry_run(config: &TradingConfig) -> Result<(), WorkerError> {
+    let mut counts = Vec::new();
+
+    for pair in &config.pairs {
+        let source_minutes = pair
+            .timeframes
+            .iter()
+            .filter_map(|t| Helper::interval_to_minutes(&t.interval.to_string()))
+            .min();
+
+        for timeframe in &pair.timeframes {
+            let interval = timeframe.interval.to_string();
+            let label = format!("{} {} {}", pair.symbol, pair.contract_type, interval);
+            match (Helper::interval_to_minutes(&interval), source_minutes) {
+                (Some(minutes), Some(source))
+                    if MarketDataAggregator::can_aggregate(minutes, source) =>
+                {
+                    counts.push(format!(
+                        "{}: aggregated from {}",
+                        label,
+                        Helper::minutes_to_interval(source)
+                    ));
+                    continue;
+                }
+                _ => {}
+            }
+
+            let candle_source = CandleSource::fetched(
+                pair.symbol.clone(),
+                pair.contract_type.clone(),
+                interval,
+                pair.exchange.unwrap_or_default(),
+                config.lookback_days,
+                config.fetch_limit,
+                true,
+            )
+            .await;
+            let fetched = match candle_source {
+                Ok(candle_source) => candle_source.fetch_recent().await,
+                Err(e) => Err(e.into()),
+            };
+            counts.push(match fetched {
+                Ok(count) => format!("{}: {} candles", label, count),
+                Err(e) => format!("{}: {}", label, e),
+            });
+        }
+    }
+
+    println!();
+    for count in counts {
+        println!("{}", count);
+    }
+
+    Ok(())
+}
+
+async fn verify_data(config: &TradingConfig, quarantine: bool) -> Result<(), WorkerError> {
+    let database = DatabaseService::new()
+        .await
+        .map_err(|e| WorkerError::Config(e.to_string()))?;
+    let client = Arc::new(Mutex::new(database.client));
+    let timeframe_repository = TimeFrameRepository::from_shared(client.clone());
+    let data_issue_repository = DataIssueRepository::from_shared(client);
+
+    for pair in &config.pairs {
+        for timeframe in &pair.timeframes {
+            let interval = timeframe.interval.to_string();
+            let Some(interval_minutes) = Helper::interval_to_minutes(&interval) else {
+                continue;
+            };
+            let Some(timeframe) = timeframe_repository
+                .find(&pair.symbol, &pair.contract_type, interval_minutes)
+                .await
+                .map_err(|e| WorkerError::MarketData(e.to_string()))?
+            else {
+                continue;
+            };
+
+            let issues = data_issue_repository
+                .find_by_timeframe(&timeframe.id)
+                .await
+                .map_err(|e| WorkerError::MarketData(e.to_string()))?;
+            println!(
+                "{} {} {}: {} issues",
+                pair.symbol,
+                pair.contract_type,
+                interval,
+                issues.len()
+            );
+            for issue in &issues {
+                println!("  {}", issue);
+            }
+
+            if quarantine && !issues.is_empty() {
+                let removed = data_issue_repository
+                    .quarantine(&issues)
+                    .await
+                    .map_err(|e| WorkerError::MarketData(e.to_string()))?;
+                println!("  {} candles quarantined", removed);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+async fn remove_range(args: &Args, from: NaiveDate) -> Result<(), WorkerError> {
+    let timeframe = find_timeframe(args).await?;
+    let from = from.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc();
+    let to = match args.delete_to {
+        Some(day) => day.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc(),
+        None => DateTime::<Utc>::MAX_UTC,
+    };
+
+    let database = DatabaseService::new()
+        .await
+        .map_err(|e| WorkerError::Config(e.to_string()))?;
+    let repository = DataIssueRepository::new(database.client);
+    let cleanup = match &args.reason {
+        Some(reason) => {
+            repository
+                .quarantine_range(&timeframe.id, from, to, reason)
+                .await
+        }
+        None => repository.delete_range(&timeframe.id, from, to).await,
+    }
+    .map_err(|e| WorkerError::MarketData(e.to_string()))?;
+
+    println!(
+        "{} candles {}, {} predictions deleted, {} positions detached",
+        cleanup.candles,
+        if args.reason.is_some() {
+            "quarantined"
+        } else {
+            "deleted"
+        },
+        cleanup.predictions,
+        cleanup.positions
+    );
+
+    Ok(())
+}
+
+async fn print_scoreboard() -> Result<(), WorkerError> {
+    let database = DatabaseService::new()
+        .await
+        .map_err(|e| WorkerError::Config(e.to_string()))?;
+    let scores = ModelPredictionRepository::new(database.client)
+        .find_scoreboard()
+        .await
+        .map_err(|e| WorkerError::MarketData(e.to_string()))?;
+
+    println!(
+        "{:<12} {:<16} {:>8} {:<10} {:>9} {:>9} {:>11}  updated",
+        "symbol", "contract", "interval", "model", "evaluated", "accuracy", "expectancy"
+    );
+    for score in scores {
+        println!(
+            "{:<12} {:<16} {:>8} {:<10} {:>9} {:>8.2}% {:>10.4}%  {}",
+            score.symbol,
+            score.contract_type.to_string(),
+            Helper::minutes_to_interval(score.interval_minutes),
+            score.model,
+            format!("{}/{}", score.evaluated_count, score.window_size),
+            score.accuracy * Decimal::ONE_HUNDRED,
+            score.expectancy,
+            score.updated_at
+        );
+    }
+
+    Ok(())
+}
+
+async fn register_model(args: &Args, path: &str) -> Result<(), WorkerError> {
+    let artifact = std::fs::read(path).map_err(|e| WorkerError::Config(e.to_string()))?;
+
+    let mut model = Model::new(
+        args.model_name.clone().unwrap_or_default(),
+        args.model_version.clone().unwrap_or_default(),
+        artifact,
+    );
+    model.training_start = args.training_start;
+    model.training_end = args.training_end;
+    if let Some(hyperparameters) = &args.hyperparameters {
+        model.hyperparameters = hyperparameters.clone();
+    }
+    if let Some(metrics) = &args.metrics {
+        model.metrics = metrics.clone();
+    }
+
+    let database = DatabaseService::new()
+        .await
+        .map_err(|e| WorkerError::Config(e.to_string()))?;
+    let id = ModelRepository::new(database.client)
+        .create(&model)
+        .await
+        .map_err(|e| WorkerError::Config(e.to_string()))?;
+
+    tracing::info!(
+        "Registered model {} {} ({} bytes) as {}",
+        model.name,
+        model.version,
+        model.artifact.len(),
+        id
+    );
+
+    Ok(())
+}
+
+async fn import_predictions(path: &str) -> Result<(), WorkerError> {
+    let file = std::fs::File::open(path).map_err(|e| WorkerError::Config(e.to_string()))?;
+    let database = DatabaseService::new()
+        .await
+        .map_err(|e| WorkerError::Config(e.to_string()))?;
+    let mut repository = ModelPredictionRepository::new(database.client);
+
+    let mut imported = 0;
+    let mut chunk = Vec::with_capacity(PREDICTION_IMPORT_CHUNK_SIZE);
+    for line in BufReader::new(file).lines() {
+        let line = line.map_err(|e| WorkerError::Config(e.to_string()))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let prediction: ModelPrediction =
+            serde_json::from_str(&line).map_err(|e| WorkerError::Config(e.to_string()))?;
+        chunk.push(prediction);
+
+        if chunk.len() == PREDICTION_IMPORT_CHUNK_SIZE {
+            imported += repository
+                .create_batch(&chunk)
+                .await
+                .map_err(|e| WorkerError::MarketData(e.to_string()))?
+                .len();
+            chunk.clear();
+        }
+    }
+    if !chunk.is_empty() {
+        imported += repository
+            .create_batch(&chunk)
+            .await
+            .map_err(|e| WorkerError::MarketData(e.to_string()))?
+            .len();
+    }
+
+    tracing::info!("Imported {} predictions from {}", imported, path);
+
+    Ok(())
+}
+
+async fn import_csv(args: &Args, path: &str, config: CsvImportConfig) -> Result<(), WorkerError> {
+    let interval = args
+        .interval
+        .clone()
+        .unwrap_or(Interval::Minute1)
+        .to_string();
+    let importer = MarketDataImporter::new(
+        args.symbol.clone().unwrap_or_default(),
+        args.contract_type
+            .clone()
+            .unwrap_or(ContractType::Perpetual),
+        interval.clone(),
+        config,
+    )
+    .await
+    .map_err(|e| WorkerError::Config(e.to_string()))?;
+    // With the causes, such as the invalid value after the line number
+    let imported = importer
+        .import(path)
+        .await
+        .map_err(|e| WorkerError::MarketData(format!("{:#}", e)))?;
+
+    tracing::info!("Imported {} {} candles from {}", imported, interval, path);
+
+    Ok(())
+}
+
+async fn bulk_load(args: &Args, from: NaiveDate) -> Result<(), WorkerError> {
+    let interval = args
+        .interval
+        .clone()
+        .unwrap_or(Interval::Minute1)
+        .to_string();
+    let loader = BinanceVisionLoader::new(
+        args.symbol.clone().unwrap_or_default(),
+        args.contract_type
+            .clone()
+            .unwrap_or(ContractType::Perpetual),
+        interval.clone(),
+    )
+    .await
+    .map_err(|e| WorkerError::Config(e.to_string()))?;
+    let loaded = loader
+        .load(from, args.bulk_load_to.unwrap_or(NaiveDate::MAX))
+        .await
+        .map_err(|e| WorkerError::MarketData(e.to_string()))?;
+
+    tracing::info!("Loaded {} {} candles from Binance Vision", loaded, interval);
+
+    Ok(())
+}
+
+// Stored timeframe of the --symbol, --contract-type and --interval arguments
+async fn find_timeframe(args: &Args) -> Result<TimeFrame, WorkerError> {
+    let symbol = args.symbol.clone().unwrap_or_default();
+    let contract_type = args
+        .contract_type
+        .clone()
+        .unwrap_or(ContractType::Perpetual);
+    let interval = args
+        .interval
+        .clone()
+        .unwrap_or(Interval::Minute1)
+        .to_string();
+    let interval_minutes = Helper::interval_to_minutes(&interval)
+        .ok_or_else(|| WorkerError::Config(format!("Invalid interval {}", interval)))?;
+
+    let database = DatabaseService::new()
+        .await
+        .map_err(|e| WorkerError::Config(e.to_string()))?;
+    TimeFrameRepository::new(database.client)
+        .find(&symbol, &contract_type, interval_minutes)
+        .await
+        .map_err(|e| WorkerError::MarketData(e.to_string()))?
+        .ok_or_else(|| {
+            WorkerError::Config(format!(
+                "No {} {} {} timeframe",
+                symbol, contract_type, interval
+            ))
+        })
+}
+
+async fn print_filtered_candles(args: &Args) -> Result<(), WorkerError> {
+    let timeframe = find_timeframe(args).await?;
+    let database = DatabaseService::new()
+        .await
+        .map_err(|e| WorkerError::Config(e.to_string()))?;
+    let candles = MarketDataRepository::new(database.client)
+        .find_where(&timeframe.id, &args.filters, FILTERED_CANDLES_LIMIT)
+        .await
+        .map_err(|e| WorkerError::MarketData(e.to_string()))?;
+
+    for candle in candles {
+        let line =
+            serde_json::to_string(&candle).map_err(|e| WorkerError::Config(e.to_string()))?;
+        println!("{}", line);
+    }
+
+    Ok(())
+}
+
+// Streams the candles page by page, so whole histories are exported without
+// being loaded at once
+async fn export_candles(args: &Args, from: NaiveDate) -> Result<(), WorkerError> {
+    let timeframe = find_timeframe(args).await?;
+    let database = DatabaseService::new()
+        .await
+        .map_err(|e| WorkerError::Config(e.to_string()))?;
+    let market_data_repository = MarketDataRepository::new(database.client);
+    let candles = market_data_repository.stream_historical_data(
+        timeframe.id,
+        from.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc(),
+        EXPORT_PAGE_SIZE,
+    );
+    tokio::pin!(candles);
+
+    let mut stdout = std::io::stdout().lock();
+    while let Some(candle) = candles
+        .try_next()
+        .await
+        .map_err(|e| WorkerError::MarketData(e.to_string()))?
+    {
+        serde_json::to_writer(&mut stdout, &candle)
+            .map_err(|e| WorkerError::Config(e.to_string()))?;
+        writeln!(stdout).map_err(|e| WorkerError::Config(e.to_string()))?;
+    }
+
+    Ok(())
+}
+
+async fn export_features(args: &Args, from: NaiveDate) -> Result<(), WorkerError> {
+    let timeframe = find_timeframe(args).await?;
+    let database = DatabaseService::new()
+        .await
+        .map_err(|e| WorkerError::Config(e.to_string()))?;
+    let rows = TrainingFeatureRepository::new(database.client)
+        .copy_csv(
+            &timeframe.id,
+            from.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc(),
+            Utc::now(),
+        )
+        .await
+        .map_err(|e| WorkerError::MarketData(e.to_string()))?;
+    tokio::pin!(rows);
+
+    let mut stdout = std::io::stdout().lock();
+    while let Some(chunk) = rows
+        .try_next()
+        .await
+        .map_err(|e| WorkerError::MarketData(e.to_string()))?
+    {
+        stdout
+            .write_all(&chunk)
+            .map_err(|e| WorkerError::Config(e.to_string()))?;
+    }
+
+    Ok(())
+}
+
+async fn import_positions(path: &str) -> Result<(), WorkerError> {
+    let file = std::fs::File::open(path).map_err(|e| WorkerError::Config(e.to_string()))?;
+    let database = DatabaseService::new()
+        .await
+        .map_err(|e| WorkerError::Config(e.to_string()))?;
+    let repository = PositionRepository::new(database.client);
+
+    let (mut created, mut updated) = (0, 0);
+    for line in BufReader::new(file).lines() {
+        let line = line.map_err(|e| WorkerError::Config(e.to_string()))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let position: Position =
+            serde_json::from_str(&line).map_err(|e| WorkerError::Config(e.to_string()))?;
+
+        if repository
+            .create(&position)
+            .await
+            .map_err(|e| WorkerError::MarketData(e.to_string()))?
+        {
+            created += 1;
+        } else if repository
+            .update_exit(&position)
+            .await
+            .map_err(|e| WorkerError::MarketData(e.to_string()))?
+        {
+            updated += 1;
+        }
+    }
+
+    tracing::info!(
+        "Recorded {} new positions and {} updates from {}",
+        created,
+        updated,
+        path
+    );
+
+    Ok(())
+}
+
+async fn print_positions(args: &Args, symbol: &str) -> Result<(), WorkerError> {
+    let contract_type = args
+        .contract_type
+        .clone()
+        .unwrap_or(ContractType::Perpetual)
+        .to_string();
+    let database = DatabaseService::new()
+        .await
+        .map_err(|e| WorkerError::Config(e.to_string()))?;
+    let repository = PositionRepository::new(database.client);
+
+    let mut positions = repository
+        .find_open_by_symbol(symbol, &contract_type)
+        .await
+        .map_err(|e| WorkerError::MarketData(e.to_string()))?;
+    positions.extend(
+        repository
+            .find_history(symbol, &contract_type, POSITION_HISTORY_LIMIT)
+            .await
+            .map_err(|e| WorkerError::MarketData(e.to_string()))?,
+    );
+
+    for position in positions {
+        let line =
+            serde_json::to_string(&position).map_err(|e| WorkerError::Config(e.to_string()))?;
+        println!("{}", line);
+    }
+
+    Ok(())
+}
+
+async fn print_predictions(market_data_id: &Uuid) -> Result<(), WorkerError> {
+    let database = DatabaseService::new()
+        .await
+        .map_err(|e| WorkerError::Config(e.to_string()))?;
+    let predictions = ModelPredictionRepository::new(database.client)
+        .find_by_market_data(market_data_id)
+        .await
+        .map_err(|e| WorkerError::MarketData(e.to_string()))?;
+
+    for prediction in predictions {
+        let line =
+            serde_json::to_string(&prediction).map_err(|e| WorkerError::Config(e.to_string()))?;
+        println!("{}", line);
+    }
+
+    Ok(())
+}
+
+async fn print_outcomes(timeframe_id: &Uuid, from: Option<NaiveDate>) -> Result<(), WorkerError> {
+    let from = match from {
+        Some(day) => day.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc(),
+        None => Utc::now() - chrono::Duration::days(DEFAULT_OUTCOME_DAYS),
+    };
+    let database = DatabaseService::new()
+        .await
+        .map_err(|e| WorkerError::Config(e.to_string()))?;
+    let outcomes = ModelPredictionRepository::new(database.client)
+        .find_outcomes(timeframe_id, from, Utc::now())
+        .await
+        .map_err(|e| WorkerError::MarketData(e.to_string()))?;
+
+    for outcome in outcomes {
+        let line =
+            serde_json::to_string(&outcome).map_err(|e| WorkerError::Config(e.to_string()))?;
+        println!("{}", line);
+    }
+
+    Ok(())
+}
+
+async fn evaluate_predictions(timeframe_id: &Uuid) -> Result<(), WorkerError> {
+    let database = DatabaseService::new()
+        .await
+        .map_err(|e| WorkerError::Config(e.to_string()))?;
+    let predictions = ModelPredictionRepository::new(database.client)
+        .find_evaluated(timeframe_id, EVALUATION_WINDOW)
+        .await
+        .map_err(|e| WorkerError::MarketData(e.to_string()))?;
+
+    let samples: Vec<LabeledPrediction> = predictions
+        .iter()
+        .filter_map(|p| {
+            Some(LabeledPrediction::new(
+                p.ensemble_pred.to_f64()?,
+                p.realized_return?.to_f64()?,
+                NEUTRAL_RETURN_BAND,
+            ))
+        })
+        .collect();
+
+    println!("{}", ClassificationReport::evaluate(&samples));
+
+    Ok(())
+}
+
+async fn print_account() -> Result<(), WorkerError> {
+    sync_clock(&ClockSync::new()).await;
+    let client =
+        BinanceAccountClient::from_env().map_err(|e| WorkerError::Config(e.to_string()))?;
+    let balances = client
+        .fetch_balances()
+        .await
+        .map_err(|e| WorkerError::MarketData(e.to_string()))?;
+    let positions = client
+        .fetch_positions()
+        .await
+        .map_err(|e| WorkerError::MarketData(e.to_string()))?;
+    let income = client
+        .fetch_income_history(Utc::now() - chrono::Duration::days(1))
+        .await
+        .map_err(|e| WorkerError::MarketData(e.to_string()))?;
+
+    println!(
+        "{:<8} {:>18} {:>18} {:>18}",
+        "asset", "wallet", "unrealized", "available"
+    );
+    for balance in balances {
+        println!(
+            "{:<8} {:>18} {:>18} {:>18}",
+            balance.asset,
+            balance.wallet_balance,
+            balance.unrealized_profit,
+            balance.available_balance
+        );
+    }
+
+    println!();
+    println!(
+        "{:<12} {:<6} {:>14} {:>14} {:>14} {:>14} {:>4}",
+        "symbol", "side", "amount", "entry", "mark", "unrealized", "lev"
+    );
+    for position in positions {
+        println!(
+            "{:<12} {:<6} {:>14} {:>14} {:>14} {:>14} {:>4}",
+            position.symbol,
+            position.position_side,
+            position.position_amount,
+            position.entry_price,
+            position.mark_price,
+            position.unrealized_profit,
+            position.leverage
+        );
+    }
+
+    // Realized PnL, funding and commissions of the last 24 hours per asset and type
+    let mut totals: BTreeMap<(String, String), Decimal> = BTreeMap::new();
+    for record in income {
+        *totals
+            .entry((record.asset, record.income_type))
+            .or_default() += record.income;
+    }
+    println!();
+    println!("{:<8} {:<24} {:>18}", "asset", "income (24h)", "total");
+    for ((asset, income_type), total) in totals {
+        println!("{:<8} {:<24} {:>18}", asset, income_type, total);
+    }
+
+    Ok(())
+}
+
+async fn print_storage_policies(config: &TradingConfig, apply: bool) -> Result<(), WorkerError> {
+    if apply {
+        let service = StoragePolicyService::new(config.storage.clone().unwrap_or_default())
+            .await
+            .map_err(|e| WorkerError::Config(e.to_string()))?;
+        let deleted = service
+            .enforce_retention()
+            .await
+            .map_err(|e| WorkerError::MarketData(e.to_string()))?;
+        println!("Deleted {} 1m candles past the retention", deleted);
+        println!();
+    }
+
+    let database = DatabaseService::new()
+        .await
+        .map_err(|e| WorkerError::Config(e.to_string()))?;
+    let repository = StorageRepository::new(database.client);
+    let storage = config.storage.clone().unwrap_or_default();
+
+    let (raw_candles, oldest) = repository
+        .raw_candle_summary()
+        .await
+        .map_err(|e| WorkerError::MarketData(e.to_string()))?;
+    println!(
+        "1m candles: {} stored, oldest {}, retention {}",
+        raw_candles,
+        oldest.map_or_else(|| "-".to_string(), |time| time.to_string()),
+        storage
+            .raw_retention_days
+            .map_or_else(|| "none".to_string(), |days| format!("{} days", days))
+    );
+
+    Ok(())
+}
+
+async fn print_symbol_info(symbol: &str) -> Result<(), WorkerError> {
+    sync_clock(&ClockSync::new()).await;
+    ExchangeInfoSync::new()
+        .await
+        .map_err(|e| WorkerError::Config(e.to_string()))?
+        .sync()
+        .await
+        .map_err(|e| WorkerError::MarketData(e.to_string()))?;
+
+    let database = DatabaseService::new()
+        .await
+        .map_err(|e| WorkerError::Config(e.to_string()))?;
+    let repository = SymbolRepository::new(database.client);
+    let info = repository
+        .find(symbol)
+        .await
+        .map_err(|e| WorkerError::MarketData(e.to_string()))?
+        .ok_or_else(|| WorkerError::Config(format!("Unknown futures symbol {}", symbol)))?;
+    let brackets = repository
+        .find_leverage_brackets(symbol)
+        .await
+        .map_err(|e| WorkerError::MarketData(e.to_string()))?;
+
+    println!(
+        "{} {} ({}/{}, {})",
+        info.symbol, info.contract_type, info.base_asset, info.quote_asset, info.status
+    );
+    println!(
+        "tick size {}, step size {}, min quantity {}, min notional {}",
+        info.tick_size,
+        info.step_size,
+        info.min_quantity,
+        info.min_notional
+            .map_or_else(|| "-".to_string(), |n| n.to_string())
+    );
+
+    if !brackets.is_empty() {
+        println!();
+        println!(
+            "{:>7} {:>4} {:>18} {:>18} {:>10}",
+            "bracket", "lev", "notional floor", "notional cap", "maint"
+        );
+    }
+    for bracket in brackets {
+        println!(
+            "{:>7} {:>4} {:>18} {:>18} {:>10}",
+            bracket.bracket,
+            bracket.initial_leverage,
+            bracket.notional_floor,
+            bracket.notional_cap,
+            bracket.maint_margin_ratio
+        );
+    }
+
+    Ok(())
+}
+
 #[tokio::main]
 async fn main() -> Result<(), WorkerError> {
     setup_logging();
@@ -181,22 +1587,235 @@ async fn main() -> Result<(), WorkerError> {
     let config = ConfigService::load_config(&config_str)
         .map_err(|e| WorkerError::Config(e.to_string()))?
         .data;
+    utils::http::init(config.http.clone().unwrap_or_default());
+    utils::rate_limit::init(config.limits.clone().unwrap_or_default());
 
-    let semaphore = Arc::new(Semaphore::new(MAX_CONCURRENT_TASKS));
+    if args.scoreboard {
+        return print_scoreboard().await;
+    }
+    if args.account {
+        return print_account().await;
+    }
+    if args.storage_policies {
+        return print_storage_policies(&config, args.apply).await;
+    }
+    if args.backfill_gaps {
+        return backfill_gaps(&config).await;
+    }
+    if args.dry_run {
+        return dry_run(&config).await;
+    }
+    if args.verify_data {
+        return verify_data(&config, args.quarantine).await;
+    }
+    if let Some(from) = args.delete_from {
+        return remove_range(&args, from).await;
+    }
+    if let Some(symbol) = &args.symbol_info {
+        return print_symbol_info(symbol).await;
+    }
+    if let Some(path) = &args.register_model {
+        return register_model(&args, path).await;
+    }
+    if let Some(path) = &args.import_predictions {
+        return import_predictions(path).await;
+    }
+    if let Some(from) = args.bulk_load {
+        return bulk_load(&args, from).await;
+    }
+    if let Some(path) = &args.import_csv {
+        return import_csv(&args, path, config.csv_import.unwrap_or_default()).await;
+    }
+    if !args.filters.is_empty() {
+        return print_filtered_candles(&args).await;
+    }
+    if let Some(from) = args.export {
+        return export_candles(&args, from).await;
+    }
+    if let Some(from) = args.export_features {
+        return export_features(&args, from).await;
+    }
+    if let Some(path) = &args.import_positions {
+        return import_positions(path).await;
+    }
+    if let Some(symbol) = &args.positions {
+        return print_positions(&args, symbol).await;
+    }
+    if let Some(market_data_id) = &args.predictions {
+        return print_predictions(market_data_id).await;
+    }
+    if let Some(timeframe_id) = &args.outcomes {
+        return print_outcomes(timeframe_id, args.outcomes_from).await;
+    }
+    if let Some(timeframe_id) = &args.evaluate {
+        return evaluate_predictions(timeframe_id).await;
+    }
+
+    let limits = config.limits.clone().unwrap_or_default();
+    let semaphore = Arc::new(Semaphore::new(
+        limits
+            .max_concurrent_tasks
+            .unwrap_or(DEFAULT_MAX_CONCURRENT_TASKS),
+    ));
+    let exchange_semaphores: HashMap<Exchange, Arc<Semaphore>> = limits
+        .exchanges
+        .iter()
+        .filter_map(|(exchange, limits)| {
+            let permits = limits.max_concurrent_tasks?;
+            Some((*exchange, Arc::new(Semaphore::new(permits))))
+        })
+        .collect();
     let mut handles = vec![];
 
+    let clock_sync = ClockSync::new();
+    sync_clock(&clock_sync).await;
+    handles.push(tokio::spawn(run_clock_sync(
+        clock_sync,
+        shutdown_sender.subscribe(),
+    )));
+
+    if let Some(storage) = config.storage.clone() {
+        handles.push(tokio::spawn(run_storage_policies(
+            storage,
+            shutdown_sender.subscribe(),
+        )));
+    }
+
+    // Trading rules for the order sizing, Binance USD-M futures only
+    if config.pairs.iter().any(|pair| {
+        pair.exchange.unwrap_or_default() == Exchange::Binance
+            && pair.contract_type != ContractType::Spot
+    }) {
+        handles.push(tokio::spawn(run_exchange_info_sync(
+            shutdown_sender.subscribe(),
+        )));
+    }
+
+    // Only Binance perpetual positions can be read back from the account
+    if let Some(interval_seconds) = config.account_sync_interval_seconds {
+        let symbols = config
+            .pairs
+            .iter()
+            .filter(|pair| {
+                pair.exchange.unwrap_or_default() == Exchange::Binance
+                    && pair.contract_type == ContractType::Perpetual
+            })
+            .map(|pair| pair.symbol.clone())
+            .collect();
+        handles.push(tokio::spawn(run_account_sync(
+            symbols,
+            interval_seconds,
+            shutdown_sender.subscribe(),
+        )));
+    }
+
+    // A pass covers the unanalyzed candles of every timeframe, so a single
+    // analyzer and connection serve all the workers
+    let analyzer = Arc::new(
+        MarketDataAnalyzer::new()
+            .await
+            .map_err(|e| WorkerError::Config(e.to_string()))?,
+    );
+
+    // The same analyzer serves every streamed timeframe, a single pending
+    // signal is enough
+    let candle_closed = if config.stream_klines.unwrap_or(false) {
+        let (sender, receiver) = mpsc::channel(1);
+        handles.push(tokio::spawn(run_stream_analyzer(
+            Arc::clone(&analyzer),
+            receiver,
+            shutdown_sender.subscribe(),
+        )));
+        Some(sender)
+    } else {
+        None
+    };
+
     for pair in config.pairs {
+        // Only the smallest interval of a pair is fetched, higher ones are built from it
+        let source_minutes = pair
+            .timeframes
+            .iter()
+            .filter_map(|t| Helper::interval_to_minutes(&t.interval.to_string()))
+            .min();
+
+        // The order book and liquidation streams are Binance perpetual ones
+        let exchange = pair.exchange.unwrap_or_default();
+        let binance_perpetual =
+            exchange == Exchange::Binance && pair.contract_type == ContractType::Perpetual;
+
+        match (&pair.order_book, binance_perpetual) {
+            (Some(order_book), true) => {
+                handles.push(tokio::spawn(run_order_book_collector(
+                    pair.symbol.clone(),
+                    pair.contract_type.clone(),
+                    order_book.clone(),
+                    shutdown_sender.subscribe(),
+                )));
+            }
+            (Some(_), false) => tracing::warn!(
+                "Order book collection is only supported for Binance perpetuals, skipping {} {}",
+                pair.symbol,
+                pair.contract_type
+            ),
+            (None, _) => {}
+        }
+
+        match (pair.liquidations.unwrap_or(false), binance_perpetual) {
+            (true, true) => {
+                handles.push(tokio::spawn(run_liquidation_collector(
+                    pair.symbol.clone(),
+                    shutdown_sender.subscribe(),
+                )));
+            }
+            (true, false) => tracing::warn!(
+                "Liquidation collection is only supported for Binance perpetuals, skipping {} {}",
+                pair.symbol,
+                pair.contract_type
+            ),
+            (false, _) => {}
+        }
+
         for timeframe in pair.timeframes {
-            let sem = Arc::clone(&semaphore);
+            let task_limits = TaskLimits {
+                global: Arc::clone(&semaphore),
+                exchange: exchange_semaphores.get(&exchange).cloned(),
+            };
             let shutdown_rx = shutdown_sender.subscribe();
+            let minutes = Helper::interval_to_minutes(&timeframe.interval.to_string());
+            let aggregate_from = match (minutes, source_minutes) {
+                (Some(minutes), Some(source))
+                    if MarketDataAggregator::can_aggregate(minutes, source) =>
+                {
+                    Some(Helper::minutes_to_interval(source))
+                }
+                _ => None,
+            };
 
             let handle = tokio::spawn(run_timeframe_worker(
                 pair.symbol.clone(),
                 pair.contract_type.clone(),
                 timeframe.interval.to_string(),
-                config.lookback_days,
-                sem,
-                args.initialize,
+                WorkerOptions {
+                    exchange,
+                    lookback_days: config.lookback_days,
+                    fetch_limit: config.fetch_limit,
+                    initialize: args.initialize,
+                    archive_after_days: config.archive_after_days,
+                    raw_retention_days: config
+                        .storage
+                        .as_ref()
+                        .and_then(|storage| storage.raw_retention_days)
+                        .filter(|_| timeframe.interval == Interval::Minute1),
+                    alerts: timeframe.alerts.clone(),
+                    aggregate_from,
+                    prediction_horizon_candles: config.prediction_horizon_candles,
+                    labeling: timeframe.labeling.clone(),
+                    candle_closed: candle_closed.clone(),
+                    trade_flow: pair.trade_flow.unwrap_or(false),
+                    analyzer: Arc::clone(&analyzer),
+                },
+                task_limits,
                 shutdown_rx,
             ));
             handles.push(handle);
@@ -0,0 +1,34 @@
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+// Futures wallet balance of one asset
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AccountBalance {
+    pub asset: String,
+    pub wallet_balance: Decimal,
+    pub unrealized_profit: Decimal,
+    pub available_balance: Decimal,
+}
+
+// Open position as reported by the exchange, negative amounts are shorts
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ExchangePosition {
+    pub symbol: String,
+    pub position_side: String, // BOTH in one-way mode, LONG or SHORT in hedge mode
+    pub position_amount: Decimal,
+    pub entry_price: Decimal,
+    pub mark_price: Decimal,
+    pub unrealized_profit: Decimal,
+    pub leverage: Decimal,
+}
+
+// One entry of the income history: realized PnL, funding fee, commission...
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct IncomeRecord {
+    pub symbol: String,
+    pub income_type: String,
+    pub income: Decimal,
+    pub asset: String,
+    pub time: DateTime<Utc>,
+}
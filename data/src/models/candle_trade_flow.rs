@@ -0,0 +1,23 @@
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+// Taker flow of one candle, aggregated from its aggTrades
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CandleTradeFlow {
+    pub market_data_id: Uuid,
+    pub first_trade_id: Option<i64>,
+    pub last_trade_id: Option<i64>,
+    pub trade_count: i32,
+    pub taker_buy_volume: Decimal,
+    pub taker_sell_volume: Decimal,
+}
+
+// A closed candle whose trades are not aggregated yet
+#[derive(Debug, Clone)]
+pub struct TradeFlowCandle {
+    pub market_data_id: Uuid,
+    pub open_time: DateTime<Utc>,
+    pub close_time: DateTime<Utc>,
+}
@@ -0,0 +1,67 @@
+use chrono::{DateTime, Utc};
+use postgres_types::{FromSql, ToSql};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use uuid::Uuid;
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, FromSql, ToSql, Clone)]
+#[postgres(name = "dataissuetype")]
+pub enum DataIssueType {
+    // High below the open or close, or low above them
+    #[postgres(name = "invalid_ohlc")]
+    #[serde(rename = "INVALID_OHLC")]
+    InvalidOhlc,
+    #[postgres(name = "negative_volume")]
+    #[serde(rename = "NEGATIVE_VOLUME")]
+    NegativeVolume,
+    // Closes before it opens, or opens before the previous candle closes
+    #[postgres(name = "non_monotonic_time")]
+    #[serde(rename = "NON_MONOTONIC_TIME")]
+    NonMonotonicTime,
+    // Another candle of the timeframe has the same open time, the first one
+    // stored is kept
+    #[postgres(name = "duplicate_candle")]
+    #[serde(rename = "DUPLICATE_CANDLE")]
+    DuplicateCandle,
+    // Quarantined by hand with the rest of a time range, the details tell why
+    #[postgres(name = "bad_ingest")]
+    #[serde(rename = "BAD_INGEST")]
+    BadIngest,
+}
+
+// Stored candle failing an integrity check
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DataIssue {
+    pub market_data_id: Uuid,
+    pub timeframe_id: Uuid,
+    pub issue_type: DataIssueType,
+    pub open_time: DateTime<Utc>,
+    pub open: Decimal,
+    pub high: Decimal,
+    pub low: Decimal,
+    pub close: Decimal,
+    pub volume: Decimal,
+    pub details: String,
+}
+
+// Rows affected by the removal of the candles of a time range
+#[derive(Debug, Default)]
+pub struct RangeCleanup {
+    pub candles: u64,
+    pub predictions: u64, // Deleted along with their candle
+    pub positions: u64,   // Kept, no longer pointing to a candle
+}
+
+impl fmt::Display for DataIssue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} {:?} {} ({})",
+            self.open_time.format("%Y-%m-%d %H:%M"),
+            self.issue_type,
+            self.details,
+            self.market_data_id
+        )
+    }
+}
@@ -0,0 +1,12 @@
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FundingRate {
+    pub symbol: String,
+    pub funding_time: DateTime<Utc>,
+    pub funding_rate: Decimal,
+    pub mark_price: Option<Decimal>,
+    pub settled: bool, // False while it is the predicted rate of an upcoming funding
+}
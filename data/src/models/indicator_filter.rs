@@ -0,0 +1,122 @@
+use std::str::FromStr;
+
+use crate::services::configuration_service::ConfigError;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Comparison {
+    LessThan,
+    LessOrEqual,
+    Equal,
+    NotEqual,
+    GreaterOrEqual,
+    GreaterThan,
+}
+
+impl Comparison {
+    const OPERATORS: [(&'static str, Comparison); 7] = [
+        ("<=", Comparison::LessOrEqual),
+        (">=", Comparison::GreaterOrEqual),
+        ("!=", Comparison::NotEqual),
+        ("<>", Comparison::NotEqual),
+        ("<", Comparison::LessThan),
+        (">", Comparison::GreaterThan),
+        ("=", Comparison::Equal),
+    ];
+
+    pub fn sql(self) -> &'static str {
+        match self {
+            Comparison::LessThan => "<",
+            Comparison::LessOrEqual => "<=",
+            Comparison::Equal => "=",
+            Comparison::NotEqual => "<>",
+            Comparison::GreaterOrEqual => ">=",
+            Comparison::GreaterThan => ">",
+        }
+    }
+}
+
+// Condition on an indicator column, such as `rsi_14 < 30` or
+// `market_regime = trending_up`. The value is cast to the column type by
+// the database, candles without the indicator never match.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndicatorFilter {
+    pub column: String,
+    pub comparison: Comparison,
+    pub value: String,
+}
+
+impl FromStr for IndicatorFilter {
+    type Err = ConfigError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // The first operator in the filter, `<=` rather than `<` at the same position
+        let (position, operator, comparison) = Comparison::OPERATORS
+            .iter()
+            .filter_map(|(operator, comparison)| {
+                s.find(operator)
+                    .map(|position| (position, *operator, *comparison))
+            })
+            .min_by_key(|(position, operator, _)| (*position, usize::MAX - operator.len()))
+            .ok_or_else(|| ConfigError::InvalidFilter(s.to_string()))?;
+
+        let column = s[..position].trim().to_lowercase();
+        let value = s[position + operator.len()..]
+            .trim()
+            .trim_matches(|c| c == '\'' || c == '"')
+            .to_string();
+        if column.is_empty() || value.is_empty() {
+            return Err(ConfigError::InvalidFilter(s.to_string()));
+        }
+
+        Ok(IndicatorFilter {
+            column,
+            comparison,
+            value,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter(column: &str, comparison: Comparison, value: &str) -> IndicatorFilter {
+        IndicatorFilter {
+            column: column.to_string(),
+            comparison,
+            value: value.to_string(),
+        }
+    }
+
+    #[test]
+    fn operators_are_parsed_longest_first() {
+        assert_eq!(
+            "RSI_14 <= 30".parse::<IndicatorFilter>().unwrap(),
+            filter("rsi_14", Comparison::LessOrEqual, "30")
+        );
+        assert_eq!(
+            "volume_ratio<>1.5".parse::<IndicatorFilter>().unwrap(),
+            filter("volume_ratio", Comparison::NotEqual, "1.5")
+        );
+        assert_eq!(
+            "market_regime = 'trending_up'"
+                .parse::<IndicatorFilter>()
+                .unwrap(),
+            filter("market_regime", Comparison::Equal, "trending_up")
+        );
+    }
+
+    #[test]
+    fn filters_without_an_operator_column_or_value_are_rejected() {
+        for filter in ["rsi_14 ~ 30", "rsi_14 LIKE 3%", "rsi_14 <", "< 30", ""] {
+            assert!(
+                matches!(
+                    filter.parse::<IndicatorFilter>(),
+                    Err(ConfigError::InvalidFilter(_))
+                ),
+                "{}",
+                filter
+            );
+        }
+    }
+}
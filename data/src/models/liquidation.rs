@@ -0,0 +1,14 @@
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+// Forced order of a liquidated position, a SELL closes a long and a BUY a short
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Liquidation {
+    pub symbol: String,
+    pub side: String,
+    pub price: Decimal,
+    pub average_price: Decimal,
+    pub quantity: Decimal, // Filled quantity
+    pub trade_time: DateTime<Utc>,
+}
@@ -0,0 +1,33 @@
+use chrono::{DateTime, Utc};
+use postgres_types::{FromSql, ToSql};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, FromSql, ToSql, Clone)]
+#[postgres(name = "longshortratiotype")]
+pub enum LongShortRatioType {
+    // Share of all accounts holding a long or short position
+    #[postgres(name = "global_account")]
+    #[serde(rename = "GLOBAL_ACCOUNT")]
+    GlobalAccount,
+    // Share of the top 20% traders by margin holding a net long or short position
+    #[postgres(name = "top_trader_account")]
+    #[serde(rename = "TOP_TRADER_ACCOUNT")]
+    TopTraderAccount,
+    // Share of the top 20% traders' positions that is long or short
+    #[postgres(name = "top_trader_position")]
+    #[serde(rename = "TOP_TRADER_POSITION")]
+    TopTraderPosition,
+}
+
+// Long/short ratio of a symbol at the end of a sampling period
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LongShortRatio {
+    pub symbol: String,
+    pub period_minutes: i32,
+    pub ratio_type: LongShortRatioType,
+    pub timestamp: DateTime<Utc>,
+    pub long_short_ratio: Decimal,
+    pub long_share: Decimal,  // Between 0 and 1
+    pub short_share: Decimal, // Between 0 and 1
+}
@@ -2,6 +2,7 @@ use chrono::{DateTime, Utc};
 use postgres_types::{FromSql, ToSql};
 use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
+use std::fmt;
 use uuid::Uuid;
 use validator::Validate;
 
@@ -31,6 +32,9 @@ pub enum MarketRegime {
 #[derive(Debug, Serialize, Deserialize, PartialEq, FromSql, ToSql, Clone)]
 #[postgres(name = "pricepattern")]
 pub enum PricePattern {
+    #[postgres(name = "none")]
+    #[serde(rename = "NONE")]
+    None,
     #[postgres(name = "double_top")]
     #[serde(rename = "DOUBLE_TOP")]
     DoubleTop,
@@ -60,6 +64,20 @@ pub enum PricePattern {
     EveningStar,
 }
 
+#[derive(Debug, Serialize, Deserialize, PartialEq, FromSql, ToSql, Clone)]
+#[postgres(name = "tradingsession")]
+pub enum TradingSession {
+    #[postgres(name = "asia")]
+    #[serde(rename = "ASIA")]
+    Asia,
+    #[postgres(name = "europe")]
+    #[serde(rename = "EUROPE")]
+    Europe,
+    #[postgres(name = "us")]
+    #[serde(rename = "US")]
+    Us,
+}
+
 #[derive(Debug, Serialize, Deserialize, Validate, Clone)]
 pub struct MarketData {
     pub id: Uuid,
@@ -132,6 +150,46 @@ pub struct MarketData {
     pub usable_by_model: bool,
 
     pub created_at: DateTime<Utc>,
+
+    // Calendar features
+    pub trading_session: Option<TradingSession>,
+    pub day_of_week: Option<i16>, // 0 for Monday through 6 for Sunday
+    pub hours_to_weekly_close: Option<Decimal>,
+    pub hours_to_monthly_close: Option<Decimal>,
+    pub is_holiday: Option<bool>,
+
+    // Deviation from the session and hour-of-week baselines, as a ratio of
+    // the candle's volume/range to the baseline average
+    pub session_volume_ratio: Option<Decimal>,
+    pub session_volatility_ratio: Option<Decimal>,
+    pub hour_of_week_volume_ratio: Option<Decimal>,
+    pub hour_of_week_volatility_ratio: Option<Decimal>,
+
+    // Derivatives
+    pub funding_rate: Option<Decimal>, // Latest settled rate at the candle close
+    pub open_interest: Option<Decimal>, // Contracts outstanding at the candle close
+    pub open_interest_change: Option<Decimal>, // % change from the candle open
+    pub long_short_ratio: Option<Decimal>, // All accounts, at the candle close
+    pub top_trader_account_ratio: Option<Decimal>, // Top trader accounts, at the candle close
+    pub top_trader_position_ratio: Option<Decimal>, // Top trader positions, at the candle close
+
+    // Order flow, from the aggregated trades
+    pub volume_delta: Option<Decimal>, // Taker buy minus taker sell volume
+    pub cvd: Option<Decimal>,          // Cumulative volume delta since 00:00 UTC
+
+    // Liquidations, lower bounds from the forceOrder stream
+    pub long_liquidation_volume: Option<Decimal>, // Liquidated long quantity
+    pub short_liquidation_volume: Option<Decimal>, // Liquidated short quantity
+
+    // Mark and index prices
+    pub mark_close: Option<Decimal>,
+    pub index_close: Option<Decimal>,
+    pub basis: Option<Decimal>, // % of the close over the index close
+
+    // Taker buys, reported by Binance candles only
+    pub taker_buy_volume: Option<Decimal>,
+    pub taker_buy_quote_volume: Option<Decimal>,
+    pub taker_buy_ratio: Option<Decimal>, // Share of the volume bought by takers
 }
 
 impl MarketData {
@@ -191,8 +249,57 @@ impl MarketData {
             analyzed: false,
             usable_by_model: false,
             created_at: Utc::now(),
+            trading_session: None,
+            day_of_week: None,
+            hours_to_weekly_close: None,
+            hours_to_monthly_close: None,
+            is_holiday: None,
+            session_volume_ratio: None,
+            session_volatility_ratio: None,
+            hour_of_week_volume_ratio: None,
+            hour_of_week_volatility_ratio: None,
+            funding_rate: None,
+            open_interest: None,
+            open_interest_change: None,
+            long_short_ratio: None,
+            top_trader_account_ratio: None,
+            top_trader_position_ratio: None,
+            volume_delta: None,
+            cvd: None,
+            long_liquidation_volume: None,
+            short_liquidation_volume: None,
+            mark_close: None,
+            index_close: None,
+            basis: None,
+            taker_buy_volume: None,
+            taker_buy_quote_volume: None,
+            taker_buy_ratio: None,
         }
     }
+
+    pub fn with_taker_buy_volumes(mut self, volume: Decimal, quote_volume: Decimal) -> Self {
+        self.taker_buy_volume = Some(volume);
+        self.taker_buy_quote_volume = Some(quote_volume);
+        self
+    }
+}
+
+impl fmt::Display for MarketData {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} {} {} O {} H {} L {} C {} V {} trades {}",
+            self.symbol,
+            self.contract_type,
+            self.open_time.format("%Y-%m-%d %H:%M"),
+            self.open,
+            self.high,
+            self.low,
+            self.close,
+            self.volume,
+            self.trades
+        )
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -227,4 +334,36 @@ pub struct MarketDataIndicatorUpdate {
     pub volume_change_24h: Option<Decimal>,
     pub analyzed: bool,
     pub usable_by_model: bool,
+    pub trading_session: Option<TradingSession>,
+    pub day_of_week: Option<i16>,
+    pub hours_to_weekly_close: Option<Decimal>,
+    pub hours_to_monthly_close: Option<Decimal>,
+    pub is_holiday: Option<bool>,
+    pub session_volume_ratio: Option<Decimal>,
+    pub session_volatility_ratio: Option<Decimal>,
+    pub hour_of_week_volume_ratio: Option<Decimal>,
+    pub hour_of_week_volatility_ratio: Option<Decimal>,
+    pub funding_rate: Option<Decimal>,
+    pub open_interest: Option<Decimal>,
+    pub open_interest_change: Option<Decimal>,
+    pub long_short_ratio: Option<Decimal>,
+    pub top_trader_account_ratio: Option<Decimal>,
+    pub top_trader_position_ratio: Option<Decimal>,
+    pub volume_delta: Option<Decimal>,
+    pub cvd: Option<Decimal>,
+    pub long_liquidation_volume: Option<Decimal>,
+    pub short_liquidation_volume: Option<Decimal>,
+    pub basis: Option<Decimal>,
+    pub taker_buy_ratio: Option<Decimal>,
+}
+
+// Totals of the candles of a timeframe opened in one UTC hour
+#[derive(Debug, Clone)]
+pub struct HourlyActivity {
+    pub hour: DateTime<Utc>,
+    pub candles: i64,
+    pub volume: f64,
+    // Candles with a non-zero open, the only ones with a range
+    pub priced_candles: i64,
+    pub range: f64, // Sum of (high - low) / open
 }
@@ -1,2 +1,16 @@
 pub mod market_data;
 pub mod timeframe;
+pub mod model_prediction;
+pub mod model;
+pub mod triple_barrier_label;
+pub mod order_book_snapshot;
+pub mod funding_rate;
+pub mod open_interest;
+pub mod long_short_ratio;
+pub mod candle_trade_flow;
+pub mod liquidation;
+pub mod account;
+pub mod position;
+pub mod data_issue;
+pub mod symbol_info;
+pub mod indicator_filter;
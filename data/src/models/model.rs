@@ -0,0 +1,34 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use uuid::Uuid;
+
+// A trained model artifact as stored in the registry
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Model {
+    pub id: Uuid,
+    pub name: String,
+    pub version: String,
+    pub artifact: Vec<u8>,
+    pub training_start: Option<DateTime<Utc>>,
+    pub training_end: Option<DateTime<Utc>>,
+    pub hyperparameters: Value,
+    pub metrics: Value,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Model {
+    pub fn new(name: String, version: String, artifact: Vec<u8>) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            name,
+            version,
+            artifact,
+            training_start: None,
+            training_end: None,
+            hyperparameters: Value::Object(Default::default()),
+            metrics: Value::Object(Default::default()),
+            created_at: Utc::now(),
+        }
+    }
+}
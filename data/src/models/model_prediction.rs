@@ -0,0 +1,56 @@
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::timeframe::ContractType;
+
+// One inference result; predictions > 0 are long and < 0 short
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ModelPrediction {
+    #[serde(default)]
+    pub id: Uuid,
+    pub market_data_id: Uuid,
+    pub timeframe_id: Uuid,
+    pub model_id: Option<Uuid>,
+    pub lstm_pred: Decimal,
+    pub cnn_pred: Decimal,
+    pub dnn_pred: Decimal,
+    pub ensemble_pred: Decimal,
+    pub confidence: Decimal,
+    pub prediction_time: DateTime<Utc>,
+    #[serde(default)]
+    pub created_at: DateTime<Utc>,
+
+    // Outcome, filled by the prediction outcome tracker
+    pub horizon_candles: Option<i32>,
+    pub realized_return: Option<Decimal>,
+    pub evaluated_at: Option<DateTime<Utc>>,
+}
+
+// A prediction next to what its candle went on to do: the close it was made
+// on, the return over its horizon once evaluated and the triple barrier
+// label once labeled
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PredictionOutcome {
+    #[serde(flatten)]
+    pub prediction: ModelPrediction,
+    pub open_time: DateTime<Utc>,
+    pub close: Decimal,
+    pub label: Option<i16>,
+    pub barrier_return: Option<Decimal>, // % from the close to the barrier exit
+}
+
+// One model head's rolling performance on a timeframe
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ModelScore {
+    pub symbol: String,
+    pub contract_type: ContractType,
+    pub interval_minutes: i32,
+    pub model: String,
+    pub window_size: i32,
+    pub evaluated_count: i32,
+    pub accuracy: Decimal,   // Share of predictions with the realized direction
+    pub expectancy: Decimal, // Mean % return when following the prediction
+    pub updated_at: DateTime<Utc>,
+}
@@ -0,0 +1,13 @@
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+// Open interest of a symbol at the end of a sampling period
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct OpenInterest {
+    pub symbol: String,
+    pub period_minutes: i32,
+    pub timestamp: DateTime<Utc>,
+    pub sum_open_interest: Decimal,       // In contracts
+    pub sum_open_interest_value: Decimal, // In quote asset
+}
@@ -0,0 +1,19 @@
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+// Best levels of both sides of the book, bids highest and asks lowest first
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct OrderBookSnapshot {
+    pub id: Uuid,
+    pub symbol: String,
+    pub contract_type: String,
+    pub snapshot_time: DateTime<Utc>,
+    pub last_update_id: i64,
+    pub bid_prices: Vec<Decimal>,
+    pub bid_quantities: Vec<Decimal>,
+    pub ask_prices: Vec<Decimal>,
+    pub ask_quantities: Vec<Decimal>,
+    pub imbalance: Option<Decimal>,
+}
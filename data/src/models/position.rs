@@ -0,0 +1,35 @@
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+// Position taken by the bot, `size` is always positive and `side` is LONG or
+// SHORT. Open until `exit_time` is set and `status` leaves 'open'.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Position {
+    pub id: Uuid,
+    pub market_data_id: Option<Uuid>, // Candle the entry was decided on
+    pub symbol: String,
+    pub contract_type: String,
+    pub side: String,
+    pub size: Decimal,
+    pub entry_price: Decimal,
+    pub take_profit: Option<Decimal>,
+    pub stop_loss: Option<Decimal>,
+    pub entry_time: DateTime<Utc>,
+    pub exit_time: Option<DateTime<Utc>>,
+    pub exit_price: Option<Decimal>,
+    pub pnl: Option<Decimal>,
+    pub status: String,
+}
+
+impl Position {
+    // Size with the sign of the side, as exchanges report position amounts
+    pub fn signed_size(&self) -> Decimal {
+        if self.side.eq_ignore_ascii_case("SHORT") {
+            -self.size
+        } else {
+            self.size
+        }
+    }
+}
@@ -0,0 +1,31 @@
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+// Trading rules of a futures symbol
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SymbolInfo {
+    pub symbol: String,
+    pub pair: String,
+    pub contract_type: String, // PERPETUAL, CURRENT_QUARTER...
+    pub status: String,        // TRADING, SETTLING...
+    pub base_asset: String,
+    pub quote_asset: String,
+    pub price_precision: i16,
+    pub quantity_precision: i16,
+    pub tick_size: Decimal,
+    pub step_size: Decimal,
+    pub min_quantity: Decimal,
+    pub min_notional: Option<Decimal>,
+}
+
+// Notional tier of a symbol, the leverage and margin ratio apply to positions
+// between the floor and the cap
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LeverageBracket {
+    pub symbol: String,
+    pub bracket: i16,
+    pub initial_leverage: i16,
+    pub notional_floor: Decimal,
+    pub notional_cap: Decimal,
+    pub maint_margin_ratio: Decimal,
+}
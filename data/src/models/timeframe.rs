@@ -22,6 +22,10 @@ pub enum ContractType {
     #[postgres(name = "next_quarter")]
     #[serde(rename = "NEXT_QUARTER")]
     NextQuarter,
+
+    #[postgres(name = "spot")]
+    #[serde(rename = "SPOT")]
+    Spot,
 }
 
 impl fmt::Display for ContractType {
@@ -30,6 +34,20 @@ impl fmt::Display for ContractType {
             Self::Perpetual => write!(f, "PERPETUAL"),
             Self::CurrentQuarter => write!(f, "CURRENT_QUARTER"),
             Self::NextQuarter => write!(f, "NEXT_QUARTER"),
+            Self::Spot => write!(f, "SPOT"),
+        }
+    }
+}
+
+impl FromStr for ContractType {
+    type Err = ConfigError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_uppercase().as_str() {
+            "PERPETUAL" => Ok(Self::Perpetual),
+            "CURRENT_QUARTER" => Ok(Self::CurrentQuarter),
+            "NEXT_QUARTER" => Ok(Self::NextQuarter),
+            "SPOT" => Ok(Self::Spot),
+            _ => Err(ConfigError::InvalidContractType(s.to_string())),
         }
     }
 }
@@ -97,7 +115,7 @@ impl fmt::Display for Interval {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, Validate)]
+#[derive(Debug, Serialize, Deserialize, Validate, Clone)]
 pub struct TimeFrame {
     pub id: Uuid,
     pub symbol: String,
@@ -0,0 +1,30 @@
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+// Training target of one candle, label 1 long, -1 short and 0 none
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TripleBarrierLabel {
+    pub market_data_id: Uuid,
+    pub timeframe_id: Uuid,
+    pub open_time: DateTime<Utc>,
+    pub label: i16,
+    pub barrier: String,
+    pub realized_return: Decimal,
+    pub holding_candles: i32,
+    pub profit_target: Decimal,
+    pub stop_loss: Decimal,
+    pub max_holding_candles: i32,
+}
+
+// A candle as seen by the labeler, pending when analyzed but not labeled yet
+#[derive(Debug, Clone)]
+pub struct LabelingCandle {
+    pub market_data_id: Uuid,
+    pub open_time: DateTime<Utc>,
+    pub high: Decimal,
+    pub low: Decimal,
+    pub close: Decimal,
+    pub pending: bool,
+}
@@ -0,0 +1,160 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use tokio::sync::Mutex;
+use tokio_postgres::Client;
+use uuid::Uuid;
+
+use crate::models::candle_trade_flow::{CandleTradeFlow, TradeFlowCandle};
+
+pub struct CandleTradeFlowRepository {
+    client: Arc<Mutex<Client>>,
+}
+
+impl CandleTradeFlowRepository {
+    pub fn from_shared(client: Arc<Mutex<Client>>) -> Self {
+        Self { client }
+    }
+
+    // Latest `limit` closed candles of a timeframe opened since `since`
+    // without a trade flow, oldest first
+    pub async fn find_pending_candles(
+        &self,
+        timeframe_id: &Uuid,
+        since: DateTime<Utc>,
+        limit: i64,
+    ) -> Result<Vec<TradeFlowCandle>> {
+        let rows = self
+            .client
+            .lock()
+            .await
+            .query(
+                "SELECT m.id, m.open_time, m.close_time
+                 FROM MarketData AS m
+                 WHERE m.timeframe_id = $1
+                   AND m.open_time >= $2
+                   AND m.close_time < CURRENT_TIMESTAMP
+                   AND NOT EXISTS (
+                       SELECT 1 FROM CandleTradeFlows AS f WHERE f.market_data_id = m.id
+                   )
+                 ORDER BY m.open_time DESC
+                 LIMIT $3",
+                &[timeframe_id, &since, &limit],
+            )
+            .await?;
+
+        Ok(rows
+            .iter()
+            .rev()
+            .map(|row| TradeFlowCandle {
+                market_data_id: row.get(0),
+                open_time: row.get(1),
+                close_time: row.get(2),
+            })
+            .collect())
+    }
+
+    // Open time of the latest candle of a timeframe with a trade flow
+    pub async fn find_latest_flow_time(
+        &self,
+        timeframe_id: &Uuid,
+    ) -> Result<Option<DateTime<Utc>>> {
+        let row = self
+            .client
+            .lock()
+            .await
+            .query_one(
+                "SELECT MAX(m.open_time)
+                 FROM MarketData AS m
+                 JOIN CandleTradeFlows AS f ON f.market_data_id = m.id
+                 WHERE m.timeframe_id = $1",
+                &[timeframe_id],
+            )
+            .await?;
+
+        Ok(row.get(0))
+    }
+
+    pub async fn create_batch(&self, flows: &[CandleTradeFlow]) -> Result<u64> {
+        let market_data_ids: Vec<Uuid> = flows.iter().map(|f| f.market_data_id).collect();
+        let first_trade_ids: Vec<Option<i64>> = flows.iter().map(|f| f.first_trade_id).collect();
+        let last_trade_ids: Vec<Option<i64>> = flows.iter().map(|f| f.last_trade_id).collect();
+        let trade_counts: Vec<i32> = flows.iter().map(|f| f.trade_count).collect();
+        let buy_volumes: Vec<Decimal> = flows.iter().map(|f| f.taker_buy_volume).collect();
+        let sell_volumes: Vec<Decimal> = flows.iter().map(|f| f.taker_sell_volume).collect();
+
+        let created = self
+            .client
+            .lock()
+            .await
+            .execute(
+                "INSERT INTO CandleTradeFlows (
+                    market_data_id,
+                    first_trade_id,
+                    last_trade_id,
+                    trade_count,
+                    taker_buy_volume,
+                    taker_sell_volume
+                 )
+                 SELECT * FROM UNNEST(
+                    $1::uuid[],
+                    $2::bigint[],
+                    $3::bigint[],
+                    $4::integer[],
+                    $5::numeric[],
+                    $6::numeric[]
+                 )
+                 ON CONFLICT (market_data_id) DO NOTHING",
+                &[
+                    &market_data_ids,
+                    &first_trade_ids,
+                    &last_trade_ids,
+                    &trade_counts,
+                    &buy_volumes,
+                    &sell_volumes,
+                ],
+            )
+            .await?;
+
+        Ok(created)
+    }
+
+    // Taker buy minus sell volume of each candle with a trade flow, and its
+    // cumulative sum since 00:00 UTC. The cumulative delta stays NULL while
+    // a candle of the day lacks a trade flow.
+    pub async fn find_candle_deltas(
+        &self,
+        market_data_ids: &[Uuid],
+    ) -> Result<HashMap<Uuid, (Decimal, Option<Decimal>)>> {
+        let rows = self
+            .client
+            .lock()
+            .await
+            .query(
+                "SELECT m.id, f.taker_buy_volume - f.taker_sell_volume, day.cvd
+                 FROM MarketData AS m
+                 JOIN CandleTradeFlows AS f ON f.market_data_id = m.id
+                 CROSS JOIN LATERAL (
+                     SELECT CASE WHEN COUNT(pf.market_data_id) = COUNT(*)
+                                 THEN SUM(pf.taker_buy_volume - pf.taker_sell_volume)
+                            END AS cvd
+                     FROM MarketData AS p
+                     LEFT JOIN CandleTradeFlows AS pf ON pf.market_data_id = p.id
+                     WHERE p.timeframe_id = m.timeframe_id
+                       AND p.open_time >= date_trunc('day', m.open_time AT TIME ZONE 'UTC') AT TIME ZONE 'UTC'
+                       AND p.open_time <= m.open_time
+                 ) AS day
+                 WHERE m.id = ANY($1)",
+                &[&market_data_ids],
+            )
+            .await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| (row.get(0), (row.get(1), row.get(2))))
+            .collect())
+    }
+}
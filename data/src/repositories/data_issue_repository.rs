@@ -0,0 +1,280 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use tokio::sync::Mutex;
+use tokio_postgres::Client;
+use uuid::Uuid;
+
+use crate::models::data_issue::{DataIssue, DataIssueType, RangeCleanup};
+
+pub struct DataIssueRepository {
+    client: Arc<Mutex<Client>>,
+}
+
+impl DataIssueRepository {
+    pub fn new(client: Client) -> Self {
+        Self::from_shared(Arc::new(Mutex::new(client)))
+    }
+
+    pub fn from_shared(client: Arc<Mutex<Client>>) -> Self {
+        Self { client }
+    }
+
+    // Runs every integrity check over the stored candles of a timeframe. A
+    // duplicate is only reported once, not as a time overlap as well.
+    pub async fn find_by_timeframe(&self, timeframe_id: &Uuid) -> Result<Vec<DataIssue>> {
+        let rows = self
+            .client
+            .lock()
+            .await
+            .query(
+                "WITH candles AS (
+                    SELECT id,
+                           open_time,
+                           close_time,
+                           open,
+                           high,
+                           low,
+                           close,
+                           volume,
+                           LAG(close_time) OVER (ORDER BY open_time, created_at) AS previous_close_time,
+                           ROW_NUMBER() OVER (PARTITION BY open_time ORDER BY created_at) AS occurrence
+                    FROM MarketData
+                    WHERE timeframe_id = $1
+                 )
+                 SELECT id, open_time, open, high, low, close, volume, issue_type, details
+                 FROM (
+                    SELECT *,
+                           'invalid_ohlc'::DataIssueType AS issue_type,
+                           format('open %s high %s low %s close %s', open, high, low, close) AS details
+                    FROM candles
+                    WHERE high < GREATEST(open, close) OR low > LEAST(open, close)
+                    UNION ALL
+                    SELECT *,
+                           'negative_volume'::DataIssueType,
+                           format('volume %s', volume)
+                    FROM candles
+                    WHERE volume < 0
+                    UNION ALL
+                    SELECT *,
+                           'non_monotonic_time'::DataIssueType,
+                           format('closes at %s, previous candle closes at %s', close_time, previous_close_time)
+                    FROM candles
+                    WHERE occurrence = 1
+                      AND (close_time <= open_time OR previous_close_time >= open_time)
+                    UNION ALL
+                    SELECT *,
+                           'duplicate_candle'::DataIssueType,
+                           format('occurrence %s of this open time', occurrence)
+                    FROM candles
+                    WHERE occurrence > 1
+                 ) issues
+                 ORDER BY open_time",
+                &[timeframe_id],
+            )
+            .await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| DataIssue {
+                market_data_id: row.get(0),
+                timeframe_id: *timeframe_id,
+                open_time: row.get(1),
+                open: row.get(2),
+                high: row.get(3),
+                low: row.get(4),
+                close: row.get(5),
+                volume: row.get(6),
+                issue_type: row.get(7),
+                details: row.get(8),
+            })
+            .collect())
+    }
+
+    // Records the issues and removes their candles from MarketData, except
+    // the ones a position or a prediction still points to. Returns the number
+    // of candles removed.
+    pub async fn quarantine(&self, issues: &[DataIssue]) -> Result<u64> {
+        let market_data_ids: Vec<Uuid> = issues.iter().map(|i| i.market_data_id).collect();
+        let timeframe_ids: Vec<Uuid> = issues.iter().map(|i| i.timeframe_id).collect();
+        let issue_types: Vec<&DataIssueType> = issues.iter().map(|i| &i.issue_type).collect();
+        let open_times: Vec<DateTime<Utc>> = issues.iter().map(|i| i.open_time).collect();
+        let opens: Vec<Decimal> = issues.iter().map(|i| i.open).collect();
+        let highs: Vec<Decimal> = issues.iter().map(|i| i.high).collect();
+        let lows: Vec<Decimal> = issues.iter().map(|i| i.low).collect();
+        let closes: Vec<Decimal> = issues.iter().map(|i| i.close).collect();
+        let volumes: Vec<Decimal> = issues.iter().map(|i| i.volume).collect();
+        let details: Vec<&str> = issues.iter().map(|i| i.details.as_str()).collect();
+
+        let row = self
+            .client
+            .lock()
+            .await
+            .query_one(
+                "WITH removed AS (
+                    DELETE FROM MarketData m
+                    WHERE m.id = ANY($1)
+                      AND NOT EXISTS (SELECT 1 FROM Positions p WHERE p.market_data_id = m.id)
+                      AND NOT EXISTS (SELECT 1 FROM ModelPredictions mp WHERE mp.market_data_id = m.id)
+                    RETURNING m.id
+                 ),
+                 recorded AS (
+                    INSERT INTO DataIssues (
+                        market_data_id,
+                        timeframe_id,
+                        issue_type,
+                        open_time,
+                        open,
+                        high,
+                        low,
+                        close,
+                        volume,
+                        details,
+                        quarantined
+                    )
+                    SELECT i.*, i.market_data_id IN (SELECT id FROM removed)
+                    FROM UNNEST(
+                        $1::uuid[],
+                        $2::uuid[],
+                        $3::dataissuetype[],
+                        $4::timestamptz[],
+                        $5::numeric[],
+                        $6::numeric[],
+                        $7::numeric[],
+                        $8::numeric[],
+                        $9::numeric[],
+                        $10::text[]
+                    ) AS i(market_data_id, timeframe_id, issue_type, open_time, open, high, low, close, volume, details)
+                    ON CONFLICT (market_data_id, issue_type) DO NOTHING
+                 )
+                 SELECT COUNT(*) FROM removed",
+                &[
+                    &market_data_ids,
+                    &timeframe_ids,
+                    &issue_types,
+                    &open_times,
+                    &opens,
+                    &highs,
+                    &lows,
+                    &closes,
+                    &volumes,
+                    &details,
+                ],
+            )
+            .await?;
+
+        Ok(row.get::<_, i64>(0) as u64)
+    }
+
+    // Deletes the candles of a timeframe opened from `from` to `to` excluded,
+    // to recover from a bad ingest
+    pub async fn delete_range(
+        &self,
+        timeframe_id: &Uuid,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<RangeCleanup> {
+        self.remove_range(timeframe_id, from, to, None).await
+    }
+
+    // Same as delete_range, but every candle is recorded as a quarantined
+    // bad_ingest issue with `reason` as details
+    pub async fn quarantine_range(
+        &self,
+        timeframe_id: &Uuid,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        reason: &str,
+    ) -> Result<RangeCleanup> {
+        self.remove_range(timeframe_id, from, to, Some(reason))
+            .await
+    }
+
+    // Predictions of the candles are deleted with them and positions lose
+    // their candle, labels and trade flows cascade. Archived candles are
+    // left as they are.
+    async fn remove_range(
+        &self,
+        timeframe_id: &Uuid,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        reason: Option<&str>,
+    ) -> Result<RangeCleanup> {
+        let mut client = self.client.lock().await;
+        let transaction = client.transaction().await?;
+        let candles = "SELECT id FROM MarketData
+                       WHERE timeframe_id = $1
+                         AND open_time >= $2
+                         AND open_time < $3";
+
+        let predictions = transaction
+            .execute(
+                &format!(
+                    "DELETE FROM ModelPredictions WHERE market_data_id IN ({})",
+                    candles
+                ),
+                &[timeframe_id, &from, &to],
+            )
+            .await?;
+        let positions = transaction
+            .execute(
+                &format!(
+                    "UPDATE Positions SET market_data_id = NULL WHERE market_data_id IN ({})",
+                    candles
+                ),
+                &[timeframe_id, &from, &to],
+            )
+            .await?;
+        let row = transaction
+            .query_one(
+                "WITH removed AS (
+                    DELETE FROM MarketData
+                    WHERE timeframe_id = $1
+                      AND open_time >= $2
+                      AND open_time < $3
+                    RETURNING *
+                 ),
+                 recorded AS (
+                    INSERT INTO DataIssues (
+                        market_data_id,
+                        timeframe_id,
+                        issue_type,
+                        open_time,
+                        open,
+                        high,
+                        low,
+                        close,
+                        volume,
+                        details,
+                        quarantined
+                    )
+                    SELECT id,
+                           timeframe_id,
+                           'bad_ingest',
+                           open_time,
+                           open,
+                           high,
+                           low,
+                           close,
+                           volume,
+                           $4::text,
+                           true
+                    FROM removed
+                    WHERE $4::text IS NOT NULL
+                    ON CONFLICT (market_data_id, issue_type) DO NOTHING
+                 )
+                 SELECT COUNT(*) FROM removed",
+                &[timeframe_id, &from, &to, &reason],
+            )
+            .await?;
+        transaction.commit().await?;
+
+        Ok(RangeCleanup {
+            candles: row.get::<_, i64>(0) as u64,
+            predictions,
+            positions,
+        })
+    }
+}
@@ -0,0 +1,112 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use tokio::sync::Mutex;
+use tokio_postgres::Client;
+use uuid::Uuid;
+
+use crate::models::funding_rate::FundingRate;
+
+pub struct FundingRateRepository {
+    client: Arc<Mutex<Client>>,
+}
+
+impl FundingRateRepository {
+    pub fn from_shared(client: Arc<Mutex<Client>>) -> Self {
+        Self { client }
+    }
+
+    // Settled rates are final, only predicted ones get overwritten
+    pub async fn upsert_batch(&self, rates: &[FundingRate]) -> Result<u64> {
+        let symbols: Vec<&str> = rates.iter().map(|r| r.symbol.as_str()).collect();
+        let funding_times: Vec<DateTime<Utc>> = rates.iter().map(|r| r.funding_time).collect();
+        let funding_rates: Vec<Decimal> = rates.iter().map(|r| r.funding_rate).collect();
+        let mark_prices: Vec<Option<Decimal>> = rates.iter().map(|r| r.mark_price).collect();
+        let settled: Vec<bool> = rates.iter().map(|r| r.settled).collect();
+
+        let upserted = self
+            .client
+            .lock()
+            .await
+            .execute(
+                "INSERT INTO FundingRates (
+                    symbol,
+                    funding_time,
+                    funding_rate,
+                    mark_price,
+                    settled
+                 )
+                 SELECT * FROM UNNEST(
+                    $1::varchar[],
+                    $2::timestamptz[],
+                    $3::numeric[],
+                    $4::numeric[],
+                    $5::boolean[]
+                 )
+                 ON CONFLICT (symbol, funding_time) DO UPDATE SET
+                    funding_rate = EXCLUDED.funding_rate,
+                    mark_price = COALESCE(EXCLUDED.mark_price, FundingRates.mark_price),
+                    settled = EXCLUDED.settled,
+                    updated_at = CURRENT_TIMESTAMP
+                 WHERE NOT FundingRates.settled",
+                &[
+                    &symbols,
+                    &funding_times,
+                    &funding_rates,
+                    &mark_prices,
+                    &settled,
+                ],
+            )
+            .await?;
+
+        Ok(upserted)
+    }
+
+    pub async fn find_latest_settled_time(&self, symbol: &str) -> Result<Option<DateTime<Utc>>> {
+        let row = self
+            .client
+            .lock()
+            .await
+            .query_one(
+                "SELECT MAX(funding_time) FROM FundingRates WHERE symbol = $1 AND settled",
+                &[&symbol],
+            )
+            .await?;
+
+        Ok(row.get(0))
+    }
+
+    // Latest rate settled at or before each perpetual candle's close, so
+    // no candle sees a rate that was not known yet
+    pub async fn find_candle_funding_rates(
+        &self,
+        market_data_ids: &[Uuid],
+    ) -> Result<HashMap<Uuid, Decimal>> {
+        let rows = self
+            .client
+            .lock()
+            .await
+            .query(
+                "SELECT m.id, f.funding_rate
+                 FROM MarketData AS m
+                 CROSS JOIN LATERAL (
+                     SELECT funding_rate
+                     FROM FundingRates
+                     WHERE symbol = m.symbol
+                       AND settled
+                       AND funding_time <= m.close_time
+                     ORDER BY funding_time DESC
+                     LIMIT 1
+                 ) AS f
+                 WHERE m.id = ANY($1)
+                   AND m.contract_type = 'PERPETUAL'",
+                &[&market_data_ids],
+            )
+            .await?;
+
+        Ok(rows.iter().map(|row| (row.get(0), row.get(1))).collect())
+    }
+}
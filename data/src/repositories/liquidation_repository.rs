@@ -0,0 +1,90 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use anyhow::Result;
+use rust_decimal::Decimal;
+use tokio::sync::Mutex;
+use tokio_postgres::Client;
+use uuid::Uuid;
+
+use crate::models::liquidation::Liquidation;
+
+pub struct LiquidationRepository {
+    client: Arc<Mutex<Client>>,
+}
+
+impl LiquidationRepository {
+    pub fn new(client: Client) -> Self {
+        Self::from_shared(Arc::new(Mutex::new(client)))
+    }
+
+    pub fn from_shared(client: Arc<Mutex<Client>>) -> Self {
+        Self { client }
+    }
+
+    // An order already stored, such as one received by another collector,
+    // is skipped
+    pub async fn create(&self, liquidation: &Liquidation) -> Result<u64> {
+        let created = self
+            .client
+            .lock()
+            .await
+            .execute(
+                "INSERT INTO Liquidations (
+                    symbol,
+                    side,
+                    price,
+                    average_price,
+                    quantity,
+                    trade_time
+                 )
+                 VALUES ($1, $2, $3, $4, $5, $6)
+                 ON CONFLICT (symbol, trade_time) DO NOTHING",
+                &[
+                    &liquidation.symbol,
+                    &liquidation.side,
+                    &liquidation.price,
+                    &liquidation.average_price,
+                    &liquidation.quantity,
+                    &liquidation.trade_time,
+                ],
+            )
+            .await?;
+
+        Ok(created)
+    }
+
+    // Liquidated long and short quantity while each perpetual candle was
+    // open, candles before the first collected liquidation are left out
+    pub async fn find_candle_volumes(
+        &self,
+        market_data_ids: &[Uuid],
+    ) -> Result<HashMap<Uuid, (Decimal, Decimal)>> {
+        let rows = self
+            .client
+            .lock()
+            .await
+            .query(
+                "SELECT m.id,
+                        COALESCE(SUM(l.quantity) FILTER (WHERE l.side = 'SELL'), 0),
+                        COALESCE(SUM(l.quantity) FILTER (WHERE l.side = 'BUY'), 0)
+                 FROM MarketData AS m
+                 LEFT JOIN Liquidations AS l
+                   ON l.symbol = m.symbol
+                  AND l.trade_time BETWEEN m.open_time AND m.close_time
+                 WHERE m.id = ANY($1)
+                   AND m.contract_type = 'PERPETUAL'
+                   AND m.open_time >= (
+                       SELECT MIN(trade_time) FROM Liquidations WHERE symbol = m.symbol
+                   )
+                 GROUP BY m.id",
+                &[&market_data_ids],
+            )
+            .await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| (row.get(0), (row.get(1), row.get(2))))
+            .collect())
+    }
+}
@@ -0,0 +1,133 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use tokio::sync::Mutex;
+use tokio_postgres::Client;
+use uuid::Uuid;
+
+use crate::models::long_short_ratio::{LongShortRatio, LongShortRatioType};
+
+pub struct LongShortRatioRepository {
+    client: Arc<Mutex<Client>>,
+}
+
+impl LongShortRatioRepository {
+    pub fn from_shared(client: Arc<Mutex<Client>>) -> Self {
+        Self { client }
+    }
+
+    pub async fn create_batch(&self, ratios: &[LongShortRatio]) -> Result<u64> {
+        let symbols: Vec<&str> = ratios.iter().map(|r| r.symbol.as_str()).collect();
+        let periods: Vec<i32> = ratios.iter().map(|r| r.period_minutes).collect();
+        let ratio_types: Vec<&LongShortRatioType> = ratios.iter().map(|r| &r.ratio_type).collect();
+        let timestamps: Vec<DateTime<Utc>> = ratios.iter().map(|r| r.timestamp).collect();
+        let long_short_ratios: Vec<Decimal> = ratios.iter().map(|r| r.long_short_ratio).collect();
+        let long_shares: Vec<Decimal> = ratios.iter().map(|r| r.long_share).collect();
+        let short_shares: Vec<Decimal> = ratios.iter().map(|r| r.short_share).collect();
+
+        let created = self
+            .client
+            .lock()
+            .await
+            .execute(
+                "INSERT INTO LongShortRatios (
+                    symbol,
+                    period_minutes,
+                    ratio_type,
+                    timestamp,
+                    long_short_ratio,
+                    long_share,
+                    short_share
+                 )
+                 SELECT * FROM UNNEST(
+                    $1::varchar[],
+                    $2::integer[],
+                    $3::longshortratiotype[],
+                    $4::timestamptz[],
+                    $5::numeric[],
+                    $6::numeric[],
+                    $7::numeric[]
+                 )
+                 ON CONFLICT (symbol, period_minutes, ratio_type, timestamp) DO NOTHING",
+                &[
+                    &symbols,
+                    &periods,
+                    &ratio_types,
+                    &timestamps,
+                    &long_short_ratios,
+                    &long_shares,
+                    &short_shares,
+                ],
+            )
+            .await?;
+
+        Ok(created)
+    }
+
+    pub async fn find_latest_time(
+        &self,
+        symbol: &str,
+        period_minutes: i32,
+        ratio_type: &LongShortRatioType,
+    ) -> Result<Option<DateTime<Utc>>> {
+        let row = self
+            .client
+            .lock()
+            .await
+            .query_one(
+                "SELECT MAX(timestamp) FROM LongShortRatios
+                 WHERE symbol = $1 AND period_minutes = $2 AND ratio_type = $3",
+                &[&symbol, &period_minutes, ratio_type],
+            )
+            .await?;
+
+        Ok(row.get(0))
+    }
+
+    // Latest global account, top trader account and top trader position
+    // ratios known at each perpetual candle's close, from the finest period
+    // available
+    pub async fn find_candle_ratios(
+        &self,
+        market_data_ids: &[Uuid],
+    ) -> Result<HashMap<Uuid, (Option<Decimal>, Option<Decimal>, Option<Decimal>)>> {
+        let rows = self
+            .client
+            .lock()
+            .await
+            .query(
+                "SELECT m.id,
+                        (SELECT long_short_ratio FROM LongShortRatios
+                         WHERE symbol = m.symbol
+                           AND ratio_type = 'global_account'
+                           AND timestamp <= m.close_time
+                         ORDER BY timestamp DESC, period_minutes ASC
+                         LIMIT 1),
+                        (SELECT long_short_ratio FROM LongShortRatios
+                         WHERE symbol = m.symbol
+                           AND ratio_type = 'top_trader_account'
+                           AND timestamp <= m.close_time
+                         ORDER BY timestamp DESC, period_minutes ASC
+                         LIMIT 1),
+                        (SELECT long_short_ratio FROM LongShortRatios
+                         WHERE symbol = m.symbol
+                           AND ratio_type = 'top_trader_position'
+                           AND timestamp <= m.close_time
+                         ORDER BY timestamp DESC, period_minutes ASC
+                         LIMIT 1)
+                 FROM MarketData AS m
+                 WHERE m.id = ANY($1)
+                   AND m.contract_type = 'PERPETUAL'",
+                &[&market_data_ids],
+            )
+            .await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| (row.get(0), (row.get(1), row.get(2), row.get(3))))
+            .collect())
+    }
+}
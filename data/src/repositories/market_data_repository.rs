@@ -1,44 +1,414 @@
+use std::collections::HashMap;
 use std::sync::Arc;
 
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Duration, Utc};
+use futures_util::{stream, Stream, TryStreamExt};
 use log::error;
-use tokio::sync::Mutex;
+use rust_decimal::Decimal;
+use tokio::sync::{Mutex, OnceCell};
 use tokio_postgres::error::Error as PgError;
-use tokio_postgres::Client;
+use tokio_postgres::types::{FromSql, ToSql};
+use tokio_postgres::{Client, Row, Statement};
 use uuid::Uuid;
 
-use crate::models::market_data::{MarketData, MarketDataIndicatorUpdate};
+use crate::models::indicator_filter::IndicatorFilter;
+use crate::models::market_data::{HourlyActivity, MarketData, MarketDataIndicatorUpdate};
+use crate::utils::candle_codec::{CandleCodec, CandleCodecError, ARCHIVE_FORMAT_VERSION};
 
 #[derive(Debug, thiserror::Error)]
 pub enum MarketDataRepositoryError {
     #[error("Database error: {0}")]
     Database(#[from] PgError),
+    #[error("Archive error: {0}")]
+    Archive(#[from] CandleCodecError),
+    #[error("Cannot filter on column {0}")]
+    FilterColumn(String),
+    #[error("Column {column}: {source}")]
+    Column {
+        column: &'static str,
+        source: PgError,
+    },
 }
 
 type Result<T> = std::result::Result<T, MarketDataRepositoryError>;
 
+// Every MarketData column read by market_data_from_row. The cached statements
+// list them rather than `*`: a column added to the table would otherwise
+// change their result type and fail them until the connection is reopened.
+const MARKET_DATA_COLUMNS: &str = "
+    id, timeframe_id, symbol, contract_type, open_time, close_time, open, close,
+    high, low, volume, trades, rsi_14, macd_line, macd_signal, macd_histogram,
+    bb_upper, bb_middle, bb_lower, atr_14, market_regime, adx, dmi_plus,
+    dmi_minus, trend_strength, trend_direction, support_levels,
+    resistance_levels, nearest_support, nearest_resistance, detected_patterns,
+    pattern_strength, depth_imbalance, volatility_1h, volatility_24h,
+    price_change_1h, price_change_24h, volume_change_1h, volume_change_24h,
+    analyzed, usable_by_model, created_at, trading_session, day_of_week,
+    hours_to_weekly_close, hours_to_monthly_close, is_holiday,
+    session_volume_ratio, session_volatility_ratio, hour_of_week_volume_ratio,
+    hour_of_week_volatility_ratio, funding_rate, open_interest,
+    open_interest_change, long_short_ratio, top_trader_account_ratio,
+    top_trader_position_ratio, volume_delta, cvd, long_liquidation_volume,
+    short_liquidation_volume, mark_close, index_close, basis, taker_buy_volume,
+    taker_buy_quote_volume, taker_buy_ratio";
+
+const HISTORICAL_DATA_CLAUSES: &str = "WHERE timeframe_id = $1
+            AND symbol = $2
+            AND contract_type = $3
+            AND open_time <= $4
+            ORDER BY open_time DESC
+            LIMIT $5";
+
+// Keyset page: the (timeframe_id, open_time) unique index serves every page
+// directly, however deep the cursor
+const HISTORICAL_PAGE_CLAUSES: &str = "WHERE timeframe_id = $1
+            AND open_time > $2
+            ORDER BY open_time ASC
+            LIMIT $3";
+
+const LATEST_BY_TIMEFRAME_CLAUSES: &str = "WHERE timeframe_id = $1
+                ORDER BY open_time DESC
+                LIMIT 1";
+
+// Columns written by an indicator update, with the SQL type of each placeholder
+const INDICATOR_UPDATE_COLUMNS: [(&str, &str); 51] = [
+    ("id", "uuid"),
+    ("rsi_14", "numeric"),
+    ("macd_line", "numeric"),
+    ("macd_signal", "numeric"),
+    ("macd_histogram", "numeric"),
+    ("bb_upper", "numeric"),
+    ("bb_middle", "numeric"),
+    ("bb_lower", "numeric"),
+    ("atr_14", "numeric"),
+    ("market_regime", "marketregime"),
+    ("adx", "numeric"),
+    ("dmi_plus", "numeric"),
+    ("dmi_minus", "numeric"),
+    ("trend_strength", "numeric"),
+    ("trend_direction", "integer"),
+    ("support_levels", "numeric[]"),
+    ("resistance_levels", "numeric[]"),
+    ("nearest_support", "numeric"),
+    ("nearest_resistance", "numeric"),
+    ("detected_patterns", "pricepattern[]"),
+    ("pattern_strength", "numeric"),
+    ("depth_imbalance", "numeric"),
+    ("volatility_1h", "numeric"),
+    ("volatility_24h", "numeric"),
+    ("price_change_1h", "numeric"),
+    ("price_change_24h", "numeric"),
+    ("volume_change_1h", "numeric"),
+    ("volume_change_24h", "numeric"),
+    ("analyzed", "boolean"),
+    ("usable_by_model", "boolean"),
+    ("trading_session", "tradingsession"),
+    ("day_of_week", "smallint"),
+    ("hours_to_weekly_close", "numeric"),
+    ("hours_to_monthly_close", "numeric"),
+    ("is_holiday", "boolean"),
+    ("session_volume_ratio", "numeric"),
+    ("session_volatility_ratio", "numeric"),
+    ("hour_of_week_volume_ratio", "numeric"),
+    ("hour_of_week_volatility_ratio", "numeric"),
+    ("funding_rate", "numeric"),
+    ("open_interest", "numeric"),
+    ("open_interest_change", "numeric"),
+    ("long_short_ratio", "numeric"),
+    ("top_trader_account_ratio", "numeric"),
+    ("top_trader_position_ratio", "numeric"),
+    ("volume_delta", "numeric"),
+    ("cvd", "numeric"),
+    ("long_liquidation_volume", "numeric"),
+    ("short_liquidation_volume", "numeric"),
+    ("basis", "numeric"),
+    ("taker_buy_ratio", "numeric"),
+];
+
+// Keeps a single statement well under the 65535 bind parameter limit
+const INDICATOR_UPDATE_CHUNK_SIZE: usize = 500;
+
+// Reads a column by name, a missing column or a type mismatch names it
+fn column<'a, T: FromSql<'a>>(row: &'a Row, name: &'static str) -> Result<T> {
+    row.try_get(name)
+        .map_err(|source| MarketDataRepositoryError::Column {
+            column: name,
+            source,
+        })
+}
+
+// Maps a row holding every MarketData column, in any order
+fn market_data_from_row(row: &Row) -> Result<MarketData> {
+    Ok(MarketData {
+        id: column(row, "id")?,
+        timeframe_id: column(row, "timeframe_id")?,
+        symbol: column(row, "symbol")?,
+        contract_type: column(row, "contract_type")?,
+        open_time: column(row, "open_time")?,
+        close_time: column(row, "close_time")?,
+        open: column(row, "open")?,
+        close: column(row, "close")?,
+        high: column(row, "high")?,
+        low: column(row, "low")?,
+        volume: column(row, "volume")?,
+        trades: column(row, "trades")?,
+        rsi_14: column(row, "rsi_14")?,
+        macd_line: column(row, "macd_line")?,
+        macd_signal: column(row, "macd_signal")?,
+        macd_histogram: column(row, "macd_histogram")?,
+        bb_upper: column(row, "bb_upper")?,
+        bb_middle: column(row, "bb_middle")?,
+        bb_lower: column(row, "bb_lower")?,
+        atr_14: column(row, "atr_14")?,
+        market_regime: column(row, "market_regime")?,
+        adx: column(row, "adx")?,
+        dmi_plus: column(row, "dmi_plus")?,
+        dmi_minus: column(row, "dmi_minus")?,
+        trend_strength: column(row, "trend_strength")?,
+        trend_direction: column(row, "trend_direction")?,
+        support_levels: column(row, "support_levels")?,
+        resistance_levels: column(row, "resistance_levels")?,
+        nearest_support: column(row, "nearest_support")?,
+        nearest_resistance: column(row, "nearest_resistance")?,
+        detected_patterns: column(row, "detected_patterns")?,
+        pattern_strength: column(row, "pattern_strength")?,
+        depth_imbalance: column(row, "depth_imbalance")?,
+        volatility_1h: column(row, "volatility_1h")?,
+        volatility_24h: column(row, "volatility_24h")?,
+        price_change_1h: column(row, "price_change_1h")?,
+        price_change_24h: column(row, "price_change_24h")?,
+        volume_change_1h: column(row, "volume_change_1h")?,
+        volume_change_24h: column(row, "volume_change_24h")?,
+        analyzed: column(row, "analyzed")?,
+        usable_by_model: column(row, "usable_by_model")?,
+        created_at: column(row, "created_at")?,
+        trading_session: column(row, "trading_session")?,
+        day_of_week: column(row, "day_of_week")?,
+        hours_to_weekly_close: column(row, "hours_to_weekly_close")?,
+        hours_to_monthly_close: column(row, "hours_to_monthly_close")?,
+        is_holiday: column(row, "is_holiday")?,
+        session_volume_ratio: column(row, "session_volume_ratio")?,
+        session_volatility_ratio: column(row, "session_volatility_ratio")?,
+        hour_of_week_volume_ratio: column(row, "hour_of_week_volume_ratio")?,
+        hour_of_week_volatility_ratio: column(row, "hour_of_week_volatility_ratio")?,
+        funding_rate: column(row, "funding_rate")?,
+        open_interest: column(row, "open_interest")?,
+        open_interest_change: column(row, "open_interest_change")?,
+        long_short_ratio: column(row, "long_short_ratio")?,
+        top_trader_account_ratio: column(row, "top_trader_account_ratio")?,
+        top_trader_position_ratio: column(row, "top_trader_position_ratio")?,
+        volume_delta: column(row, "volume_delta")?,
+        cvd: column(row, "cvd")?,
+        long_liquidation_volume: column(row, "long_liquidation_volume")?,
+        short_liquidation_volume: column(row, "short_liquidation_volume")?,
+        mark_close: column(row, "mark_close")?,
+        index_close: column(row, "index_close")?,
+        basis: column(row, "basis")?,
+        taker_buy_volume: column(row, "taker_buy_volume")?,
+        taker_buy_quote_volume: column(row, "taker_buy_quote_volume")?,
+        taker_buy_ratio: column(row, "taker_buy_ratio")?,
+    })
+}
+
+// Maps the OHLCV columns of a row, for the queries selecting only those
+fn candle_from_row(
+    row: &Row,
+    timeframe_id: Uuid,
+    symbol: String,
+    contract_type: String,
+) -> Result<MarketData> {
+    Ok(MarketData::new(
+        timeframe_id,
+        symbol,
+        contract_type,
+        column(row, "open_time")?,
+        column(row, "close_time")?,
+        column(row, "open")?,
+        column(row, "close")?,
+        column(row, "high")?,
+        column(row, "low")?,
+        column(row, "volume")?,
+        column(row, "trades")?,
+    ))
+}
+
+fn select_market_data_query(clauses: &str) -> String {
+    format!("SELECT {} FROM MarketData {}", MARKET_DATA_COLUMNS, clauses)
+}
+
+fn update_indicators_batch_query(rows: usize) -> String {
+    let column_count = INDICATOR_UPDATE_COLUMNS.len();
+    let values = (0..rows)
+        .map(|row| {
+            let placeholders = INDICATOR_UPDATE_COLUMNS
+                .iter()
+                .enumerate()
+                .map(|(column, (_, sql_type))| {
+                    format!("${}::{}", row * column_count + column + 1, sql_type)
+                })
+                .collect::<Vec<String>>()
+                .join(", ");
+            format!("({})", placeholders)
+        })
+        .collect::<Vec<String>>()
+        .join(",\n");
+    let assignments = INDICATOR_UPDATE_COLUMNS[1..]
+        .iter()
+        .map(|(column, _)| format!("{} = v.{}", column, column))
+        .collect::<Vec<String>>()
+        .join(",\n");
+    let columns = INDICATOR_UPDATE_COLUMNS
+        .iter()
+        .map(|(column, _)| *column)
+        .collect::<Vec<&str>>()
+        .join(", ");
+
+    format!(
+        "UPDATE MarketData AS m SET
+            {}
+        FROM (VALUES {}) AS v({})
+        WHERE m.id = v.id",
+        assignments, values, columns
+    )
+}
+
+// Statements used on every analyzed candle, prepared once per connection
+struct PreparedStatements {
+    historical_data: Statement,
+    historical_page: Statement,
+    latest_by_timeframe: Statement,
+}
+
 pub struct MarketDataRepository {
     client: Arc<Mutex<Client>>,
+    statements: OnceCell<PreparedStatements>,
+    // Batched update statements keyed by row count
+    update_statements: Mutex<HashMap<usize, Statement>>,
 }
 
 impl MarketDataRepository {
     pub fn new(client: Client) -> Self {
+        Self::from_shared(Arc::new(Mutex::new(client)))
+    }
+
+    // Used by services whose repositories all share one connection
+    pub fn from_shared(client: Arc<Mutex<Client>>) -> Self {
         Self {
-            client: Arc::new(Mutex::new(client)),
+            client,
+            statements: OnceCell::new(),
+            update_statements: Mutex::new(HashMap::new()),
         }
     }
 
+    async fn statements(&self, client: &Client) -> Result<&PreparedStatements> {
+        let statements = self
+            .statements
+            .get_or_try_init(|| async {
+                Ok::<_, PgError>(PreparedStatements {
+                    historical_data: client
+                        .prepare(&select_market_data_query(HISTORICAL_DATA_CLAUSES))
+                        .await?,
+                    historical_page: client
+                        .prepare(&select_market_data_query(HISTORICAL_PAGE_CLAUSES))
+                        .await?,
+                    latest_by_timeframe: client
+                        .prepare(&select_market_data_query(LATEST_BY_TIMEFRAME_CLAUSES))
+                        .await?,
+                })
+            })
+            .await?;
+        Ok(statements)
+    }
+
+    async fn update_statement(&self, client: &Client, rows: usize) -> Result<Statement> {
+        let mut update_statements = self.update_statements.lock().await;
+        if let Some(statement) = update_statements.get(&rows) {
+            return Ok(statement.clone());
+        }
+
+        let statement = client.prepare(&update_indicators_batch_query(rows)).await?;
+        update_statements.insert(rows, statement.clone());
+        Ok(statement)
+    }
+
+    // One multi-row insert for the whole batch. Returns the ids of the
+    // inserted candles, those already stored are skipped.
     pub async fn create_batch(&self, data: &[MarketData]) -> Result<Vec<Uuid>> {
-        let mut ids = Vec::with_capacity(data.len());
-        let mut client = self.client.lock().await;
-        let transaction = client.transaction().await?;
+        self.write_batch(data, "DO NOTHING").await
+    }
 
-        for record in data {
-            if record.close_time > Utc::now() {
-                continue;
-            }
-            let row = transaction
-                .query_one(
+    // Same as create_batch, but stored candles the exchange has revised since
+    // take the new OHLCV values and are analyzed again. Returns the ids of the
+    // inserted and revised candles, unchanged ones are left untouched.
+    pub async fn upsert_batch(&self, data: &[MarketData]) -> Result<Vec<Uuid>> {
+        self.write_batch(
+            data,
+            "DO UPDATE SET
+                close_time = EXCLUDED.close_time,
+                open = EXCLUDED.open,
+                high = EXCLUDED.high,
+                low = EXCLUDED.low,
+                close = EXCLUDED.close,
+                volume = EXCLUDED.volume,
+                trades = EXCLUDED.trades,
+                taker_buy_volume = EXCLUDED.taker_buy_volume,
+                taker_buy_quote_volume = EXCLUDED.taker_buy_quote_volume,
+                analyzed = false
+            WHERE (
+                MarketData.open,
+                MarketData.high,
+                MarketData.low,
+                MarketData.close,
+                MarketData.volume,
+                MarketData.trades,
+                MarketData.taker_buy_volume,
+                MarketData.taker_buy_quote_volume
+            ) IS DISTINCT FROM (
+                EXCLUDED.open,
+                EXCLUDED.high,
+                EXCLUDED.low,
+                EXCLUDED.close,
+                EXCLUDED.volume,
+                EXCLUDED.trades,
+                EXCLUDED.taker_buy_volume,
+                EXCLUDED.taker_buy_quote_volume
+            )",
+        )
+        .await
+    }
+
+    // `conflict` is the action taken on candles already stored. Candles still
+    // open on the local clock are skipped, the fetchers drop those still open
+    // on their exchange clock beforehand.
+    async fn write_batch(&self, data: &[MarketData], conflict: &str) -> Result<Vec<Uuid>> {
+        let now = Utc::now();
+        let closed: Vec<&MarketData> = data.iter().filter(|r| r.close_time <= now).collect();
+        if closed.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let timeframe_ids: Vec<Uuid> = closed.iter().map(|r| r.timeframe_id).collect();
+        let symbols: Vec<&str> = closed.iter().map(|r| r.symbol.as_str()).collect();
+        let contract_types: Vec<&str> = closed.iter().map(|r| r.contract_type.as_str()).collect();
+        let open_times: Vec<DateTime<Utc>> = closed.iter().map(|r| r.open_time).collect();
+        let close_times: Vec<DateTime<Utc>> = closed.iter().map(|r| r.close_time).collect();
+        let opens: Vec<Decimal> = closed.iter().map(|r| r.open).collect();
+        let highs: Vec<Decimal> = closed.iter().map(|r| r.high).collect();
+        let lows: Vec<Decimal> = closed.iter().map(|r| r.low).collect();
+        let closes: Vec<Decimal> = closed.iter().map(|r| r.close).collect();
+        let volumes: Vec<Decimal> = closed.iter().map(|r| r.volume).collect();
+        let trades: Vec<i64> = closed.iter().map(|r| r.trades).collect();
+        let taker_buy_volumes: Vec<Option<Decimal>> =
+            closed.iter().map(|r| r.taker_buy_volume).collect();
+        let taker_buy_quote_volumes: Vec<Option<Decimal>> =
+            closed.iter().map(|r| r.taker_buy_quote_volume).collect();
+
+        let rows = self
+            .client
+            .lock()
+            .await
+            .query(
+                &format!(
                     "INSERT INTO MarketData (
                         timeframe_id,
                         symbol,
@@ -50,45 +420,48 @@ impl MarketDataRepository {
                         low,
                         close,
                         volume,
-                        trades
+                        trades,
+                        taker_buy_volume,
+                        taker_buy_quote_volume
+                    )
+                    SELECT * FROM UNNEST(
+                        $1::uuid[],
+                        $2::varchar[],
+                        $3::varchar[],
+                        $4::timestamptz[],
+                        $5::timestamptz[],
+                        $6::numeric[],
+                        $7::numeric[],
+                        $8::numeric[],
+                        $9::numeric[],
+                        $10::numeric[],
+                        $11::bigint[],
+                        $12::numeric[],
+                        $13::numeric[]
                     )
-                    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
-                    ON CONFLICT (open_time, timeframe_id) DO NOTHING
+                    ON CONFLICT (open_time, timeframe_id) {}
                     RETURNING id",
-                    &[
-                        &record.timeframe_id,
-                        &record.symbol,
-                        &record.contract_type,
-                        &record.open_time,
-                        &record.close_time,
-                        &record.open,
-                        &record.high,
-                        &record.low,
-                        &record.close,
-                        &record.volume,
-                        &record.trades,
-                    ],
-                )
-                .await;
-
-            match row {
-                Ok(row) => {
-                    ids.push(row.get(0));
-                    continue;
-                }
-                Err(e) if e.as_db_error().is_none() => {
-                    error!("{:?}", e);
-                    continue;
-                }
-                Err(e) => {
-                    error!("{:?}", e);
-                    continue;
-                }
-            }
-        }
+                    conflict
+                ),
+                &[
+                    &timeframe_ids,
+                    &symbols,
+                    &contract_types,
+                    &open_times,
+                    &close_times,
+                    &opens,
+                    &highs,
+                    &lows,
+                    &closes,
+                    &volumes,
+                    &trades,
+                    &taker_buy_volumes,
+                    &taker_buy_quote_volumes,
+                ],
+            )
+            .await?;
 
-        transaction.commit().await?;
-        Ok(ids)
+        Ok(rows.iter().map(|row| row.get(0)).collect())
     }
 
     pub async fn find_market_data_for_analysis(
@@ -120,53 +493,7 @@ impl MarketDataRepository {
             .await;
 
         match rows {
-            Ok(row) => Ok(row
-                .iter()
-                .map(|r| MarketData {
-                    id: r.get(0),
-                    timeframe_id: r.get(1),
-                    symbol: r.get(2),
-                    contract_type: r.get(3),
-                    open_time: r.get(4),
-                    close_time: r.get(5),
-                    open: r.get(6),
-                    high: r.get(7),
-                    low: r.get(8),
-                    close: r.get(9),
-                    volume: r.get(10),
-                    trades: r.get(11),
-                    rsi_14: r.get(12),
-                    macd_line: r.get(13),
-                    macd_signal: r.get(14),
-                    macd_histogram: r.get(15),
-                    bb_upper: r.get(16),
-                    bb_middle: r.get(17),
-                    bb_lower: r.get(18),
-                    atr_14: r.get(19),
-                    market_regime: r.get(20),
-                    adx: r.get(21),
-                    dmi_plus: r.get(22),
-                    dmi_minus: r.get(23),
-                    trend_strength: r.get(24),
-                    trend_direction: r.get(25),
-                    support_levels: r.get(26),
-                    resistance_levels: r.get(27),
-                    nearest_support: r.get(28),
-                    nearest_resistance: r.get(29),
-                    detected_patterns: r.get(30),
-                    pattern_strength: r.get(31),
-                    depth_imbalance: r.get(32),
-                    volatility_1h: r.get(33),
-                    volatility_24h: r.get(34),
-                    price_change_1h: r.get(35),
-                    price_change_24h: r.get(36),
-                    volume_change_1h: r.get(37),
-                    volume_change_24h: r.get(38),
-                    analyzed: r.get(39),
-                    usable_by_model: r.get(40),
-                    created_at: r.get(41),
-                })
-                .collect()),
+            Ok(row) => row.iter().map(market_data_from_row).collect(),
             Err(error) => {
                 error!("Error: {:?}", error);
                 Err(MarketDataRepositoryError::Database(error))
@@ -182,18 +509,11 @@ impl MarketDataRepository {
         from_time: DateTime<Utc>,
         record_count: i32,
     ) -> Result<Vec<MarketData>> {
-        let rows = self
-            .client
-            .lock()
-            .await
+        let client = self.client.lock().await;
+        let statements = self.statements(&client).await?;
+        let rows = client
             .query(
-                "SELECT * FROM MarketData
-            WHERE timeframe_id = $1
-            AND symbol = $2
-            AND contract_type = $3
-            AND open_time <= $4
-            ORDER BY open_time DESC
-            LIMIT $5",
+                &statements.historical_data,
                 &[
                     &timeframe_id,
                     &symbol,
@@ -204,97 +524,231 @@ impl MarketDataRepository {
             )
             .await;
 
-        match rows {
-            Ok(row) => Ok(row
+        let mut historical_data = match rows {
+            Ok(row) => row
                 .iter()
-                .map(|r| MarketData {
-                    id: r.get(0),
-                    timeframe_id: r.get(1),
-                    symbol: r.get(2),
-                    contract_type: r.get(3),
-                    open_time: r.get(4),
-                    close_time: r.get(5),
-                    open: r.get(6),
-                    high: r.get(7),
-                    low: r.get(8),
-                    close: r.get(9),
-                    volume: r.get(10),
-                    trades: r.get(11),
-                    rsi_14: r.get(12),
-                    macd_line: r.get(13),
-                    macd_signal: r.get(14),
-                    macd_histogram: r.get(15),
-                    bb_upper: r.get(16),
-                    bb_middle: r.get(17),
-                    bb_lower: r.get(18),
-                    atr_14: r.get(19),
-                    market_regime: r.get(20),
-                    adx: r.get(21),
-                    dmi_plus: r.get(22),
-                    dmi_minus: r.get(23),
-                    trend_strength: r.get(24),
-                    trend_direction: r.get(25),
-                    support_levels: r.get(26),
-                    resistance_levels: r.get(27),
-                    nearest_support: r.get(28),
-                    nearest_resistance: r.get(29),
-                    detected_patterns: r.get(30),
-                    pattern_strength: r.get(31),
-                    depth_imbalance: r.get(32),
-                    volatility_1h: r.get(33),
-                    volatility_24h: r.get(34),
-                    price_change_1h: r.get(35),
-                    price_change_24h: r.get(36),
-                    volume_change_1h: r.get(37),
-                    volume_change_24h: r.get(38),
-                    analyzed: r.get(39),
-                    usable_by_model: r.get(40),
-                    created_at: r.get(41),
-                })
-                .collect()),
+                .map(market_data_from_row)
+                .collect::<Result<Vec<MarketData>>>()?,
             Err(error) => {
                 error!("Error: {:?}", error);
-                Err(MarketDataRepositoryError::Database(error))
+                return Err(MarketDataRepositoryError::Database(error));
             }
+        };
+
+        // Older candles may have been moved to the archive, fill the window from there
+        let missing = (record_count as usize).saturating_sub(historical_data.len());
+        if missing > 0 {
+            let before = historical_data
+                .last()
+                .map(|d| d.open_time)
+                .unwrap_or(from_time + Duration::milliseconds(1));
+            let archived = self
+                .find_archived_before(
+                    &client,
+                    timeframe_id,
+                    symbol,
+                    contract_type,
+                    before,
+                    missing,
+                )
+                .await?;
+            historical_data.extend(archived);
         }
+
+        Ok(historical_data)
     }
 
-    pub async fn update_indicators(&self, update: MarketDataIndicatorUpdate) -> Result<()> {
+    // Up to `page_size` candles opened strictly after `after`, oldest first.
+    // The open time of the last one is the cursor of the next page.
+    pub async fn get_historical_page(
+        &self,
+        timeframe_id: Uuid,
+        after: DateTime<Utc>,
+        page_size: i64,
+    ) -> Result<Vec<MarketData>> {
         let client = self.client.lock().await;
+        let statements = self.statements(&client).await?;
         let rows = client
-            .execute(
-                "UPDATE MarketData SET
-               rsi_14 = $2,
-               macd_line = $3,
-               macd_signal = $4,
-               macd_histogram = $5,
-               bb_upper = $6,
-               bb_middle = $7,
-               bb_lower = $8,
-               atr_14 = $9,
-               market_regime = $10,
-               adx = $11,
-               dmi_plus = $12,
-               dmi_minus = $13,
-               trend_strength = $14,
-               trend_direction = $15,
-               support_levels = $16,
-               resistance_levels = $17,
-               nearest_support = $18,
-               nearest_resistance = $19,
-               detected_patterns = $20,
-               pattern_strength = $21,
-               depth_imbalance = $22,
-               volatility_1h = $23,
-               volatility_24h = $24,
-               price_change_1h = $25,
-               price_change_24h = $26,
-               volume_change_1h = $27,
-               volume_change_24h = $28,
-               analyzed = $29,
-               usable_by_model = $30
-            WHERE id = $1",
-                &[
+            .query(
+                &statements.historical_page,
+                &[&timeframe_id, &after, &page_size],
+            )
+            .await?;
+
+        rows.iter().map(market_data_from_row).collect()
+    }
+
+    // Every candle of a timeframe opened from `from` on, oldest first, read
+    // page by page so only one page is held in memory. The connection is
+    // released between pages. Archived candles are not included.
+    pub fn stream_historical_data(
+        &self,
+        timeframe_id: Uuid,
+        from: DateTime<Utc>,
+        page_size: i64,
+    ) -> impl Stream<Item = Result<MarketData>> + '_ {
+        // One microsecond, the resolution of timestamptz, so `from` is included
+        let cursor = Some(from - Duration::microseconds(1));
+        stream::try_unfold(cursor, move |cursor| async move {
+            let Some(after) = cursor else {
+                return Ok::<_, MarketDataRepositoryError>(None);
+            };
+            let page = self
+                .get_historical_page(timeframe_id, after, page_size)
+                .await?;
+            let next = match page.last() {
+                Some(last) if page.len() as i64 == page_size => Some(last.open_time),
+                Some(_) => None,
+                None => return Ok(None),
+            };
+            Ok(Some((stream::iter(page.into_iter().map(Ok)), next)))
+        })
+        .try_flatten()
+    }
+
+    // Decoded archived candles strictly older than `before`, newest first
+    async fn find_archived_before(
+        &self,
+        client: &Client,
+        timeframe_id: Uuid,
+        symbol: &str,
+        contract_type: &str,
+        before: DateTime<Utc>,
+        limit: usize,
+    ) -> Result<Vec<MarketData>> {
+        let rows = client
+            .query(
+                "SELECT payload FROM MarketDataArchive
+                WHERE timeframe_id = $1
+                AND symbol = $2
+                AND contract_type = $3
+                AND first_open_time < $4
+                ORDER BY first_open_time DESC",
+                &[&timeframe_id, &symbol, &contract_type, &before],
+            )
+            .await?;
+
+        let mut archived = Vec::with_capacity(limit);
+        for row in rows {
+            let payload: Vec<u8> = row.get(0);
+            let mut candles = CandleCodec::decode(&payload, timeframe_id, symbol, contract_type)?;
+            candles.retain(|c| c.open_time < before);
+            candles.reverse();
+            archived.extend(candles);
+            if archived.len() >= limit {
+                break;
+            }
+        }
+        archived.truncate(limit);
+
+        Ok(archived)
+    }
+
+    /// Moves candles older than `cutoff` into delta-encoded archive chunks of
+    /// `chunk_size` candles, oldest first and one transaction per chunk.
+    /// Candles referenced by positions or predictions stay in MarketData, and
+    /// only full chunks are archived.
+    pub async fn archive_before(
+        &self,
+        timeframe_id: &Uuid,
+        cutoff: DateTime<Utc>,
+        chunk_size: usize,
+    ) -> Result<usize> {
+        let chunk_size = chunk_size.max(1);
+        let mut archived = 0;
+
+        loop {
+            let mut client = self.client.lock().await;
+            let transaction = client.transaction().await?;
+
+            // Archived candles are deleted, the next chunk starts after them
+            let rows = transaction
+                .query(
+                    "SELECT id, symbol, contract_type, open_time, close_time,
+                            open, close, high, low, volume, trades,
+                            taker_buy_volume, taker_buy_quote_volume
+                    FROM MarketData m
+                    WHERE timeframe_id = $1
+                    AND open_time < $2
+                    AND NOT EXISTS (SELECT 1 FROM Positions p WHERE p.market_data_id = m.id)
+                    AND NOT EXISTS (SELECT 1 FROM ModelPredictions mp WHERE mp.market_data_id = m.id)
+                    ORDER BY open_time ASC
+                    LIMIT $3",
+                    &[timeframe_id, &cutoff, &(chunk_size as i64)],
+                )
+                .await?;
+            if rows.len() < chunk_size {
+                break;
+            }
+
+            let chunk = rows
+                .iter()
+                .map(|r| {
+                    let mut candle = candle_from_row(
+                        r,
+                        *timeframe_id,
+                        column(r, "symbol")?,
+                        column(r, "contract_type")?,
+                    )?;
+                    candle.id = column(r, "id")?;
+                    candle.taker_buy_volume = column(r, "taker_buy_volume")?;
+                    candle.taker_buy_quote_volume = column(r, "taker_buy_quote_volume")?;
+                    Ok(candle)
+                })
+                .collect::<Result<Vec<MarketData>>>()?;
+
+            let (first, last) = (&chunk[0], &chunk[chunk.len() - 1]);
+            let payload = CandleCodec::encode(&chunk)?;
+            transaction
+                .execute(
+                    "INSERT INTO MarketDataArchive (
+                        timeframe_id,
+                        symbol,
+                        contract_type,
+                        first_open_time,
+                        last_open_time,
+                        candle_count,
+                        format_version,
+                        payload
+                    )
+                    VALUES ($1, $2, $3, $4, $5, $6, $7, $8)",
+                    &[
+                        timeframe_id,
+                        &first.symbol,
+                        &first.contract_type,
+                        &first.open_time,
+                        &last.open_time,
+                        &(chunk.len() as i32),
+                        &(ARCHIVE_FORMAT_VERSION as i16),
+                        &payload,
+                    ],
+                )
+                .await?;
+
+            let ids = chunk.iter().map(|c| c.id).collect::<Vec<Uuid>>();
+            transaction
+                .execute("DELETE FROM MarketData WHERE id = ANY($1)", &[&ids])
+                .await?;
+
+            transaction.commit().await?;
+            archived += chunk.len();
+        }
+
+        Ok(archived)
+    }
+
+    pub async fn update_indicators_batch(
+        &self,
+        updates: &[MarketDataIndicatorUpdate],
+    ) -> Result<()> {
+        let client = self.client.lock().await;
+
+        for chunk in updates.chunks(INDICATOR_UPDATE_CHUNK_SIZE) {
+            let statement = self.update_statement(&client, chunk.len()).await?;
+            let mut params: Vec<&(dyn ToSql + Sync)> =
+                Vec::with_capacity(chunk.len() * INDICATOR_UPDATE_COLUMNS.len());
+            for update in chunk {
+                params.extend_from_slice(&[
                     &update.id,
                     &update.rsi_14,
                     &update.macd_line,
@@ -325,79 +779,573 @@ impl MarketDataRepository {
                     &update.volume_change_24h,
                     &update.analyzed,
                     &update.usable_by_model,
-                ],
-            )
-            .await;
+                    &update.trading_session,
+                    &update.day_of_week,
+                    &update.hours_to_weekly_close,
+                    &update.hours_to_monthly_close,
+                    &update.is_holiday,
+                    &update.session_volume_ratio,
+                    &update.session_volatility_ratio,
+                    &update.hour_of_week_volume_ratio,
+                    &update.hour_of_week_volatility_ratio,
+                    &update.funding_rate,
+                    &update.open_interest,
+                    &update.open_interest_change,
+                    &update.long_short_ratio,
+                    &update.top_trader_account_ratio,
+                    &update.top_trader_position_ratio,
+                    &update.volume_delta,
+                    &update.cvd,
+                    &update.long_liquidation_volume,
+                    &update.short_liquidation_volume,
+                    &update.basis,
+                    &update.taker_buy_ratio,
+                ]);
+            }
 
-        match rows {
-            Ok(_rows) => Ok(()),
-            Err(error) => {
+            if let Err(error) = client.execute(&statement, &params).await {
                 error!("Error updating indicators: {:?}", error);
-                Err(MarketDataRepositoryError::Database(error))
+                return Err(MarketDataRepositoryError::Database(error));
             }
         }
+
+        Ok(())
+    }
+
+    // Open time of the oldest closed candle since `since` missing its mark or
+    // index close
+    pub async fn find_oldest_without_price_closes(
+        &self,
+        timeframe_id: &Uuid,
+        since: DateTime<Utc>,
+    ) -> Result<Option<DateTime<Utc>>> {
+        let client = self.client.lock().await;
+        let row = client
+            .query_one(
+                "SELECT MIN(open_time)
+                 FROM MarketData
+                 WHERE timeframe_id = $1
+                   AND open_time >= $2
+                   AND close_time < CURRENT_TIMESTAMP
+                   AND (mark_close IS NULL OR index_close IS NULL)",
+                &[timeframe_id, &since],
+            )
+            .await?;
+
+        Ok(row.get(0))
+    }
+
+    // Sets the mark and index closes of closed candles by open time, a
+    // missing close keeps the stored one
+    pub async fn update_price_closes(
+        &self,
+        timeframe_id: &Uuid,
+        open_times: &[DateTime<Utc>],
+        mark_closes: &[Option<Decimal>],
+        index_closes: &[Option<Decimal>],
+    ) -> Result<u64> {
+        let client = self.client.lock().await;
+        let updated = client
+            .execute(
+                "UPDATE MarketData AS m
+                 SET mark_close = COALESCE(u.mark_close, m.mark_close),
+                     index_close = COALESCE(u.index_close, m.index_close)
+                 FROM UNNEST($2::timestamptz[], $3::numeric[], $4::numeric[])
+                      AS u(open_time, mark_close, index_close)
+                 WHERE m.timeframe_id = $1
+                   AND m.open_time = u.open_time
+                   AND m.close_time < CURRENT_TIMESTAMP",
+                &[timeframe_id, &open_times, &mark_closes, &index_closes],
+            )
+            .await?;
+
+        Ok(updated)
+    }
+
+    // Missing stretches between stored candles opened since `since`, as the
+    // open time of the first missing candle and of the next stored one
+    pub async fn find_gaps(
+        &self,
+        timeframe_id: &Uuid,
+        interval_minutes: i32,
+        since: DateTime<Utc>,
+    ) -> Result<Vec<(DateTime<Utc>, DateTime<Utc>)>> {
+        let client = self.client.lock().await;
+        let rows = client
+            .query(
+                "SELECT gap_start, gap_end
+                 FROM (
+                     SELECT open_time + make_interval(mins => $2) AS gap_start,
+                            LEAD(open_time) OVER (ORDER BY open_time) AS gap_end
+                     FROM MarketData
+                     WHERE timeframe_id = $1
+                       AND open_time >= $3
+                 ) AS g
+                 WHERE gap_end > gap_start
+                 ORDER BY gap_start",
+                &[timeframe_id, &interval_minutes, &since],
+            )
+            .await?;
+
+        Ok(rows.iter().map(|row| (row.get(0), row.get(1))).collect())
+    }
+
+    // Hourly totals of the candles of a timeframe opened in [from, to),
+    // oldest first
+    pub async fn find_hourly_activity(
+        &self,
+        timeframe_id: &Uuid,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<HourlyActivity>> {
+        let client = self.client.lock().await;
+        let rows = client
+            .query(
+                "SELECT date_trunc('hour', open_time AT TIME ZONE 'UTC') AT TIME ZONE 'UTC' AS hour,
+                        COUNT(*) AS candles,
+                        SUM(volume)::float8 AS volume,
+                        COUNT(NULLIF(open, 0)) AS priced_candles,
+                        COALESCE(SUM((high - low) / NULLIF(open, 0)), 0)::float8 AS range
+                 FROM MarketData
+                 WHERE timeframe_id = $1
+                   AND open_time >= $2
+                   AND open_time < $3
+                 GROUP BY 1
+                 ORDER BY 1",
+                &[timeframe_id, &from, &to],
+            )
+            .await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| HourlyActivity {
+                hour: row.get("hour"),
+                candles: row.get("candles"),
+                volume: row.get("volume"),
+                priced_candles: row.get("priced_candles"),
+                range: row.get("range"),
+            })
+            .collect())
+    }
+
+    // Raw OHLCV candles with open_time in [from, to), oldest first
+    pub async fn find_candles_between(
+        &self,
+        timeframe_id: &Uuid,
+        symbol: &str,
+        contract_type: &str,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<MarketData>> {
+        let client = self.client.lock().await;
+        let rows = client
+            .query(
+                "SELECT open_time, close_time, open, high, low, close, volume, trades,
+                        taker_buy_volume, taker_buy_quote_volume
+                 FROM MarketData
+                 WHERE timeframe_id = $1
+                   AND open_time >= $2
+                   AND open_time < $3
+                 ORDER BY open_time ASC",
+                &[timeframe_id, &from, &to],
+            )
+            .await?;
+
+        rows.iter()
+            .map(|r| {
+                let mut candle = candle_from_row(
+                    r,
+                    *timeframe_id,
+                    symbol.to_string(),
+                    contract_type.to_string(),
+                )?;
+                candle.taker_buy_volume = column(r, "taker_buy_volume")?;
+                candle.taker_buy_quote_volume = column(r, "taker_buy_quote_volume")?;
+                Ok(candle)
+            })
+            .collect()
+    }
+
+    // Newest candles of a timeframe matching every filter. Filters apply to
+    // the single-valued indicator columns, each value is sent as text and
+    // cast to the column type.
+    pub async fn find_where(
+        &self,
+        timeframe_id: &Uuid,
+        filters: &[IndicatorFilter],
+        limit: i64,
+    ) -> Result<Vec<MarketData>> {
+        let query = filter_query(filters)?;
+        let mut params: Vec<&(dyn ToSql + Sync)> = vec![timeframe_id];
+        for filter in filters {
+            params.push(&filter.value);
+        }
+        params.push(&limit);
+
+        let rows = self.client.lock().await.query(&query, &params).await?;
+        rows.iter().map(market_data_from_row).collect()
     }
 
     pub async fn find_latest_by_timeframe(
         &self,
         timeframe_id: &Uuid,
     ) -> Result<Option<MarketData>> {
-        let row = self
-            .client
-            .lock()
+        let client = self.client.lock().await;
+        let statements = self.statements(&client).await?;
+        let row = client
+            .query_opt(&statements.latest_by_timeframe, &[timeframe_id])
+            .await?;
+
+        row.as_ref().map(market_data_from_row).transpose()
+    }
+}
+
+// Query of find_where, binding the timeframe id, then the value of each
+// filter and the limit. Columns outside the indicator columns are rejected
+// before the query is built.
+fn filter_query(filters: &[IndicatorFilter]) -> Result<String> {
+    let mut query = String::from("SELECT * FROM MarketData WHERE timeframe_id = $1");
+    for (i, filter) in filters.iter().enumerate() {
+        let sql_type = INDICATOR_UPDATE_COLUMNS
+            .iter()
+            .find(|(name, sql_type)| *name == filter.column && !sql_type.ends_with("[]"))
+            .map(|(_, sql_type)| *sql_type)
+            .ok_or_else(|| MarketDataRepositoryError::FilterColumn(filter.column.clone()))?;
+        query.push_str(&format!(
+            " AND {} {} ${}::text::{}",
+            filter.column,
+            filter.comparison.sql(),
+            i + 2,
+            sql_type
+        ));
+    }
+    query.push_str(&format!(
+        " ORDER BY open_time DESC LIMIT ${}",
+        filters.len() + 2
+    ));
+    Ok(query)
+}
+
+// The ignored tests need a PostgreSQL database with init_schema.sql,
+// reached through the DB_* variables like the service:
+// cargo test -- --ignored
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::database_service::DatabaseService;
+    use chrono::TimeZone;
+
+    // The candle insert before create_batch sent a single UNNEST statement
+    const PER_ROW_INSERT: &str = "INSERT INTO MarketData (
+            timeframe_id,
+            symbol,
+            contract_type,
+            open_time,
+            close_time,
+            open,
+            high,
+            low,
+            close,
+            volume,
+            trades,
+            taker_buy_volume,
+            taker_buy_quote_volume
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)
+        ON CONFLICT (open_time, timeframe_id) DO NOTHING
+        RETURNING id";
+
+    // Every inserted column of the candles of a timeframe, one text per row
+    const STORED_CANDLES: &str = "SELECT ROW(
+            symbol,
+            contract_type,
+            open_time,
+            close_time,
+            open,
+            high,
+            low,
+            close,
+            volume,
+            trades,
+            taker_buy_volume,
+            taker_buy_quote_volume,
+            analyzed
+        )::text
+        FROM MarketData
+        WHERE timeframe_id = $1
+        ORDER BY open_time";
+
+    async fn connect() -> Arc<Mutex<Client>> {
+        Arc::new(Mutex::new(DatabaseService::new().await.unwrap().client))
+    }
+
+    async fn create_timeframe(client: &Client) -> Uuid {
+        client
+            .query_one(
+                "INSERT INTO Timeframes (symbol, contract_type, interval_minutes)
+                 VALUES ($1, 'perpetual', 1)
+                 RETURNING id",
+                &[&format!("T{}", &Uuid::new_v4().simple().to_string()[..12])],
+            )
             .await
-            .query_opt(
-                "SELECT * FROM MarketData
-                WHERE timeframe_id = $1
-                ORDER BY open_time DESC
-                LIMIT 1",
+            .unwrap()
+            .get(0)
+    }
+
+    async fn drop_timeframe(client: &Client, timeframe_id: &Uuid) {
+        client
+            .execute(
+                "DELETE FROM MarketData WHERE timeframe_id = $1",
                 &[timeframe_id],
             )
-            .await?;
+            .await
+            .unwrap();
+        client
+            .execute("DELETE FROM Timeframes WHERE id = $1", &[timeframe_id])
+            .await
+            .unwrap();
+    }
+
+    // 1m candles of 2024-01-01, every third one without taker volumes
+    fn candles(timeframe_id: Uuid, count: i64) -> Vec<MarketData> {
+        let start = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        (0..count)
+            .map(|i| {
+                let open_time = start + Duration::minutes(i);
+                let price = Decimal::new(4_200_000 + i * 137, 2);
+                let mut candle = MarketData::new(
+                    timeframe_id,
+                    "BTCUSDT".to_string(),
+                    "perpetual".to_string(),
+                    open_time,
+                    open_time + Duration::milliseconds(59_999),
+                    price,
+                    price + Decimal::new(25, 1),
+                    price + Decimal::new(5, 0),
+                    price - Decimal::new(5, 0),
+                    Decimal::new(123_456 + i, 3),
+                    100 + i,
+                );
+                if i % 3 != 0 {
+                    candle.taker_buy_volume = Some(Decimal::new(61_000 + i, 3));
+                    candle.taker_buy_quote_volume = Some(Decimal::new(2_500_000 + i, 1));
+                }
+                candle
+            })
+            .collect()
+    }
+
+    async fn insert_per_row(client: &Client, data: &[MarketData]) -> usize {
+        let mut inserted = 0;
+        for record in data {
+            inserted += client
+                .query_opt(
+                    PER_ROW_INSERT,
+                    &[
+                        &record.timeframe_id,
+                        &record.symbol,
+                        &record.contract_type,
+                        &record.open_time,
+                        &record.close_time,
+                        &record.open,
+                        &record.high,
+                        &record.low,
+                        &record.close,
+                        &record.volume,
+                        &record.trades,
+                        &record.taker_buy_volume,
+                        &record.taker_buy_quote_volume,
+                    ],
+                )
+                .await
+                .unwrap()
+                .is_some() as usize;
+        }
+        inserted
+    }
+
+    async fn stored(client: &Client, timeframe_id: &Uuid) -> Vec<String> {
+        client
+            .query(STORED_CANDLES, &[timeframe_id])
+            .await
+            .unwrap()
+            .iter()
+            .map(|row| row.get(0))
+            .collect()
+    }
+
+    fn filters(filters: &[&str]) -> Vec<IndicatorFilter> {
+        filters
+            .iter()
+            .map(|filter| filter.parse().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn filters_bind_their_values() {
+        let filters = filters(&["rsi_14 < 30", "market_regime = 'trending_up'"]);
+
+        assert_eq!(
+            filter_query(&filters).unwrap(),
+            "SELECT * FROM MarketData WHERE timeframe_id = $1 \
+             AND rsi_14 < $2::text::numeric \
+             AND market_regime = $3::text::marketregime \
+             ORDER BY open_time DESC LIMIT $4"
+        );
+    }
+
+    #[test]
+    fn unknown_and_array_columns_are_rejected() {
+        for filter in [
+            "close < 30",
+            "rsi_14; DROP TABLE MarketData; -- < 1",
+            "support_levels > 1",
+        ] {
+            let error = filter_query(&filters(&["rsi_14 < 30", filter])).unwrap_err();
+            assert!(
+                matches!(error, MarketDataRepositoryError::FilterColumn(_)),
+                "{}",
+                filter
+            );
+        }
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a PostgreSQL database"]
+    async fn create_batch_stores_the_rows_of_the_per_row_insert() {
+        let client = connect().await;
+        let repository = MarketDataRepository::from_shared(client.clone());
+        let (per_row_id, batch_id) = {
+            let client = client.lock().await;
+            (
+                create_timeframe(&client).await,
+                create_timeframe(&client).await,
+            )
+        };
+
+        let per_row = candles(per_row_id, 500);
+        let batch = candles(batch_id, 500);
+        // Half of the candles are stored already
+        {
+            let client = client.lock().await;
+            assert_eq!(insert_per_row(&client, &per_row[..250]).await, 250);
+            assert_eq!(insert_per_row(&client, &batch[..250]).await, 250);
+            assert_eq!(insert_per_row(&client, &per_row).await, 250);
+        }
+        let created = repository.create_batch(&batch).await.unwrap();
+
+        let client = client.lock().await;
+        assert_eq!(created.len(), 250);
+        let per_row_rows = stored(&client, &per_row_id).await;
+        assert_eq!(per_row_rows.len(), 500);
+        assert_eq!(stored(&client, &batch_id).await, per_row_rows);
+
+        drop_timeframe(&client, &per_row_id).await;
+        drop_timeframe(&client, &batch_id).await;
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a PostgreSQL database"]
+    async fn upsert_batch_rewrites_only_the_changed_candles() {
+        let client = connect().await;
+        let repository = MarketDataRepository::from_shared(client.clone());
+        let (timeframe_id, fresh_id) = {
+            let client = client.lock().await;
+            (
+                create_timeframe(&client).await,
+                create_timeframe(&client).await,
+            )
+        };
+        let set_analyzed = |timeframe_id: Uuid| {
+            let client = client.clone();
+            async move {
+                client
+                    .lock()
+                    .await
+                    .execute(
+                        "UPDATE MarketData SET analyzed = true WHERE timeframe_id = $1",
+                        &[&timeframe_id],
+                    )
+                    .await
+                    .unwrap();
+            }
+        };
+
+        let mut data = candles(timeframe_id, 30);
+        repository.create_batch(&data).await.unwrap();
+        set_analyzed(timeframe_id).await;
+        assert!(repository.upsert_batch(&data).await.unwrap().is_empty());
+
+        // A revised close, a taker volume that went missing and one that
+        // appeared
+        data[4].close += Decimal::new(1, 2);
+        data[7].taker_buy_volume = None;
+        data[9].taker_buy_quote_volume = Some(Decimal::new(1, 0));
+        let revised = repository.upsert_batch(&data).await.unwrap();
+        assert_eq!(revised.len(), 3);
+
+        let unanalyzed: Vec<DateTime<Utc>> = client
+            .lock()
+            .await
+            .query(
+                "SELECT open_time FROM MarketData
+                 WHERE timeframe_id = $1 AND NOT analyzed
+                 ORDER BY open_time",
+                &[&timeframe_id],
+            )
+            .await
+            .unwrap()
+            .iter()
+            .map(|row| row.get(0))
+            .collect();
+        assert_eq!(
+            unanalyzed,
+            vec![data[4].open_time, data[7].open_time, data[9].open_time]
+        );
+
+        // The stored candles match the revised ones inserted from scratch
+        let fresh: Vec<MarketData> = data
+            .iter()
+            .cloned()
+            .map(|candle| MarketData {
+                timeframe_id: fresh_id,
+                ..candle
+            })
+            .collect();
+        repository.create_batch(&fresh).await.unwrap();
+        set_analyzed(timeframe_id).await;
+        set_analyzed(fresh_id).await;
+
+        let client = client.lock().await;
+        assert_eq!(
+            stored(&client, &timeframe_id).await,
+            stored(&client, &fresh_id).await
+        );
+
+        drop_timeframe(&client, &timeframe_id).await;
+        drop_timeframe(&client, &fresh_id).await;
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a PostgreSQL database"]
+    async fn cached_statements_survive_a_new_column() {
+        let client = connect().await;
+        let repository = MarketDataRepository::from_shared(client.clone());
+        let timeframe_id = create_timeframe(&*client.lock().await).await;
+        let data = candles(timeframe_id, 3);
+        repository.create_batch(&data).await.unwrap();
+        let latest = repository.find_latest_by_timeframe(&timeframe_id).await;
+        assert_eq!(latest.unwrap().unwrap().open_time, data[2].open_time);
+
+        let alter = |statement: &'static str| {
+            let client = client.clone();
+            async move { client.lock().await.batch_execute(statement).await.unwrap() }
+        };
+        alter("ALTER TABLE MarketData ADD COLUMN cached_statement_probe INTEGER").await;
+        let latest = repository.find_latest_by_timeframe(&timeframe_id).await;
+        alter("ALTER TABLE MarketData DROP COLUMN cached_statement_probe").await;
+        assert_eq!(latest.unwrap().unwrap().open_time, data[2].open_time);
 
-        Ok(row.map(|r| MarketData {
-            id: r.get(0),
-            timeframe_id: r.get(1),
-            symbol: r.get(2),
-            contract_type: r.get(3),
-            open_time: r.get(4),
-            close_time: r.get(5),
-            open: r.get(6),
-            high: r.get(7),
-            low: r.get(8),
-            close: r.get(9),
-            volume: r.get(10),
-            trades: r.get(11),
-            rsi_14: r.get(12),
-            macd_line: r.get(13),
-            macd_signal: r.get(14),
-            macd_histogram: r.get(15),
-            bb_upper: r.get(16),
-            bb_middle: r.get(17),
-            bb_lower: r.get(18),
-            atr_14: r.get(19),
-            market_regime: r.get(20),
-            adx: r.get(21),
-            dmi_plus: r.get(22),
-            dmi_minus: r.get(23),
-            trend_strength: r.get(24),
-            trend_direction: r.get(25),
-            support_levels: r.get(26),
-            resistance_levels: r.get(27),
-            nearest_support: r.get(28),
-            nearest_resistance: r.get(29),
-            detected_patterns: r.get(30),
-            pattern_strength: r.get(31),
-            depth_imbalance: r.get(32),
-            volatility_1h: r.get(33),
-            volatility_24h: r.get(34),
-            price_change_1h: r.get(35),
-            price_change_24h: r.get(36),
-            volume_change_1h: r.get(37),
-            volume_change_24h: r.get(38),
-            analyzed: r.get(39),
-            usable_by_model: r.get(40),
-            created_at: r.get(41),
-        }))
+        drop_timeframe(&*client.lock().await, &timeframe_id).await;
     }
 }
@@ -1,3 +1,17 @@
 // pub mod kline_repostory;
 pub mod market_data_repository;
 pub mod timeframe_repository;
+pub mod model_prediction_repository;
+pub mod model_repository;
+pub mod triple_barrier_label_repository;
+pub mod order_book_repository;
+pub mod funding_rate_repository;
+pub mod open_interest_repository;
+pub mod long_short_ratio_repository;
+pub mod candle_trade_flow_repository;
+pub mod liquidation_repository;
+pub mod position_repository;
+pub mod data_issue_repository;
+pub mod symbol_repository;
+pub mod storage_repository;
+pub mod training_feature_repository;
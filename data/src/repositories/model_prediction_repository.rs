@@ -0,0 +1,302 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use tokio_postgres::{Client, Row};
+use uuid::Uuid;
+
+use crate::models::model_prediction::{ModelPrediction, ModelScore, PredictionOutcome};
+
+const PREDICTION_COLUMNS: &str = "id,
+    market_data_id,
+    timeframe_id,
+    model_id,
+    lstm_pred,
+    cnn_pred,
+    dnn_pred,
+    ensemble_pred,
+    confidence,
+    prediction_time,
+    created_at,
+    horizon_candles,
+    realized_return,
+    evaluated_at";
+
+pub struct ModelPredictionRepository {
+    client: Client,
+}
+
+impl ModelPredictionRepository {
+    pub fn new(client: Client) -> Self {
+        Self { client }
+    }
+
+    pub async fn create_batch(&mut self, predictions: &[ModelPrediction]) -> Result<Vec<Uuid>> {
+        let mut ids = Vec::with_capacity(predictions.len());
+        let transaction = self.client.transaction().await?;
+        let statement = transaction
+            .prepare(
+                "INSERT INTO ModelPredictions (
+                    market_data_id,
+                    timeframe_id,
+                    model_id,
+                    lstm_pred,
+                    cnn_pred,
+                    dnn_pred,
+                    ensemble_pred,
+                    confidence,
+                    prediction_time
+                 )
+                 VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+                 RETURNING id",
+            )
+            .await?;
+
+        for prediction in predictions {
+            let row = transaction
+                .query_one(
+                    &statement,
+                    &[
+                        &prediction.market_data_id,
+                        &prediction.timeframe_id,
+                        &prediction.model_id,
+                        &prediction.lstm_pred,
+                        &prediction.cnn_pred,
+                        &prediction.dnn_pred,
+                        &prediction.ensemble_pred,
+                        &prediction.confidence,
+                        &prediction.prediction_time,
+                    ],
+                )
+                .await?;
+            ids.push(row.get(0));
+        }
+
+        transaction.commit().await?;
+        Ok(ids)
+    }
+
+    pub async fn find_by_market_data(&self, market_data_id: &Uuid) -> Result<Vec<ModelPrediction>> {
+        let rows = self
+            .client
+            .query(
+                &format!(
+                    "SELECT {} FROM ModelPredictions
+                     WHERE market_data_id = $1
+                     ORDER BY prediction_time DESC",
+                    PREDICTION_COLUMNS
+                ),
+                &[market_data_id],
+            )
+            .await?;
+
+        Ok(rows.iter().map(Self::map_row).collect())
+    }
+
+    // Latest predictions of a timeframe with a realized outcome
+    pub async fn find_evaluated(
+        &self,
+        timeframe_id: &Uuid,
+        limit: i64,
+    ) -> Result<Vec<ModelPrediction>> {
+        let rows = self
+            .client
+            .query(
+                &format!(
+                    "SELECT {} FROM ModelPredictions
+                     WHERE timeframe_id = $1
+                       AND evaluated_at IS NOT NULL
+                     ORDER BY prediction_time DESC
+                     LIMIT $2",
+                    PREDICTION_COLUMNS
+                ),
+                &[timeframe_id, &limit],
+            )
+            .await?;
+
+        Ok(rows.iter().map(Self::map_row).collect())
+    }
+
+    // Predictions of a timeframe made from `from` to `to` excluded, oldest
+    // first, each with its candle and outcome. Candles referenced by
+    // predictions are never archived, so every prediction has its row.
+    pub async fn find_outcomes(
+        &self,
+        timeframe_id: &Uuid,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<PredictionOutcome>> {
+        let rows = self
+            .client
+            .query(
+                &format!(
+                    "SELECT p.*,
+                            md.open_time AS candle_open_time,
+                            md.close AS candle_close,
+                            l.label AS barrier_label,
+                            l.realized_return AS barrier_return
+                     FROM (
+                         SELECT {} FROM ModelPredictions
+                         WHERE timeframe_id = $1
+                           AND prediction_time >= $2
+                           AND prediction_time < $3
+                     ) AS p
+                     JOIN MarketData AS md ON md.id = p.market_data_id
+                     LEFT JOIN TripleBarrierLabels AS l ON l.market_data_id = p.market_data_id
+                     ORDER BY md.open_time, p.prediction_time",
+                    PREDICTION_COLUMNS
+                ),
+                &[timeframe_id, &from, &to],
+            )
+            .await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| PredictionOutcome {
+                prediction: Self::map_row(row),
+                open_time: row.get("candle_open_time"),
+                close: row.get("candle_close"),
+                label: row.get("barrier_label"),
+                barrier_return: row.get("barrier_return"),
+            })
+            .collect())
+    }
+
+    // Fills the realized return of pending predictions whose candle is
+    // followed by at least `horizon_candles` candles
+    pub async fn evaluate_outcomes(
+        &self,
+        timeframe_id: &Uuid,
+        horizon_candles: i32,
+    ) -> Result<u64> {
+        let evaluated = self
+            .client
+            .execute(
+                "UPDATE ModelPredictions AS p
+                 SET horizon_candles = $2::int,
+                     realized_return = (future.close - md.close) / md.close * 100,
+                     evaluated_at = CURRENT_TIMESTAMP
+                 FROM MarketData AS md
+                 CROSS JOIN LATERAL (
+                     SELECT f.close
+                     FROM MarketData AS f
+                     WHERE f.timeframe_id = md.timeframe_id
+                       AND f.open_time > md.open_time
+                     ORDER BY f.open_time ASC
+                     OFFSET $2::int - 1
+                     LIMIT 1
+                 ) AS future
+                 WHERE p.market_data_id = md.id
+                   AND p.timeframe_id = $1
+                   AND p.evaluated_at IS NULL",
+                &[timeframe_id, &horizon_candles],
+            )
+            .await?;
+
+        Ok(evaluated)
+    }
+
+    // Recomputes each head's hit rate and expectancy over the latest
+    // `window_size` evaluated predictions, neutral (0) predictions excluded
+    pub async fn refresh_scoreboard(&self, timeframe_id: &Uuid, window_size: i32) -> Result<u64> {
+        let refreshed = self
+            .client
+            .execute(
+                "INSERT INTO ModelScoreboard (
+                    timeframe_id,
+                    model,
+                    window_size,
+                    evaluated_count,
+                    accuracy,
+                    expectancy,
+                    updated_at
+                 )
+                 SELECT $1,
+                        s.model,
+                        $2::int,
+                        COUNT(*),
+                        AVG(CASE WHEN SIGN(s.pred) = SIGN(p.realized_return) THEN 1 ELSE 0 END),
+                        AVG(SIGN(s.pred) * p.realized_return),
+                        CURRENT_TIMESTAMP
+                 FROM (
+                     SELECT *
+                     FROM ModelPredictions
+                     WHERE timeframe_id = $1
+                       AND evaluated_at IS NOT NULL
+                     ORDER BY prediction_time DESC
+                     LIMIT $2::int
+                 ) AS p
+                 CROSS JOIN LATERAL (
+                     VALUES ('lstm', p.lstm_pred),
+                            ('cnn', p.cnn_pred),
+                            ('dnn', p.dnn_pred),
+                            ('ensemble', p.ensemble_pred)
+                 ) AS s(model, pred)
+                 WHERE s.pred <> 0
+                 GROUP BY s.model
+                 ON CONFLICT (timeframe_id, model) DO UPDATE SET
+                    window_size = EXCLUDED.window_size,
+                    evaluated_count = EXCLUDED.evaluated_count,
+                    accuracy = EXCLUDED.accuracy,
+                    expectancy = EXCLUDED.expectancy,
+                    updated_at = EXCLUDED.updated_at",
+                &[timeframe_id, &window_size],
+            )
+            .await?;
+
+        Ok(refreshed)
+    }
+
+    pub async fn find_scoreboard(&self) -> Result<Vec<ModelScore>> {
+        let rows = self
+            .client
+            .query(
+                "SELECT t.symbol,
+                        t.contract_type,
+                        t.interval_minutes,
+                        s.model,
+                        s.window_size,
+                        s.evaluated_count,
+                        s.accuracy,
+                        s.expectancy,
+                        s.updated_at
+                 FROM ModelScoreboard AS s
+                 JOIN Timeframes AS t ON t.id = s.timeframe_id
+                 ORDER BY t.symbol, t.contract_type, t.interval_minutes, s.model",
+                &[],
+            )
+            .await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| ModelScore {
+                symbol: row.get(0),
+                contract_type: row.get(1),
+                interval_minutes: row.get(2),
+                model: row.get(3),
+                window_size: row.get(4),
+                evaluated_count: row.get(5),
+                accuracy: row.get(6),
+                expectancy: row.get(7),
+                updated_at: row.get(8),
+            })
+            .collect())
+    }
+
+    fn map_row(row: &Row) -> ModelPrediction {
+        ModelPrediction {
+            id: row.get(0),
+            market_data_id: row.get(1),
+            timeframe_id: row.get(2),
+            model_id: row.get(3),
+            lstm_pred: row.get(4),
+            cnn_pred: row.get(5),
+            dnn_pred: row.get(6),
+            ensemble_pred: row.get(7),
+            confidence: row.get(8),
+            prediction_time: row.get(9),
+            created_at: row.get(10),
+            horizon_candles: row.get(11),
+            realized_return: row.get(12),
+            evaluated_at: row.get(13),
+        }
+    }
+}
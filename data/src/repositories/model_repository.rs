@@ -0,0 +1,45 @@
+use anyhow::Result;
+use tokio_postgres::Client;
+use uuid::Uuid;
+
+use crate::models::model::Model;
+
+pub struct ModelRepository {
+    client: Client,
+}
+
+impl ModelRepository {
+    pub fn new(client: Client) -> Self {
+        Self { client }
+    }
+
+    pub async fn create(&self, model: &Model) -> Result<Uuid> {
+        let row = self
+            .client
+            .query_one(
+                "INSERT INTO Models (
+                    name,
+                    version,
+                    artifact,
+                    training_start,
+                    training_end,
+                    hyperparameters,
+                    metrics
+                 )
+                 VALUES ($1, $2, $3, $4, $5, $6, $7)
+                 RETURNING id",
+                &[
+                    &model.name,
+                    &model.version,
+                    &model.artifact,
+                    &model.training_start,
+                    &model.training_end,
+                    &model.hyperparameters,
+                    &model.metrics,
+                ],
+            )
+            .await?;
+
+        Ok(row.get(0))
+    }
+}
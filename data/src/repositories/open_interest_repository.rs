@@ -0,0 +1,115 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use tokio::sync::Mutex;
+use tokio_postgres::Client;
+use uuid::Uuid;
+
+use crate::models::open_interest::OpenInterest;
+
+pub struct OpenInterestRepository {
+    client: Arc<Mutex<Client>>,
+}
+
+impl OpenInterestRepository {
+    pub fn from_shared(client: Arc<Mutex<Client>>) -> Self {
+        Self { client }
+    }
+
+    pub async fn create_batch(&self, points: &[OpenInterest]) -> Result<u64> {
+        let symbols: Vec<&str> = points.iter().map(|p| p.symbol.as_str()).collect();
+        let periods: Vec<i32> = points.iter().map(|p| p.period_minutes).collect();
+        let timestamps: Vec<DateTime<Utc>> = points.iter().map(|p| p.timestamp).collect();
+        let open_interests: Vec<Decimal> = points.iter().map(|p| p.sum_open_interest).collect();
+        let values: Vec<Decimal> = points.iter().map(|p| p.sum_open_interest_value).collect();
+
+        let created = self
+            .client
+            .lock()
+            .await
+            .execute(
+                "INSERT INTO OpenInterest (
+                    symbol,
+                    period_minutes,
+                    timestamp,
+                    sum_open_interest,
+                    sum_open_interest_value
+                 )
+                 SELECT * FROM UNNEST(
+                    $1::varchar[],
+                    $2::integer[],
+                    $3::timestamptz[],
+                    $4::numeric[],
+                    $5::numeric[]
+                 )
+                 ON CONFLICT (symbol, period_minutes, timestamp) DO NOTHING",
+                &[&symbols, &periods, &timestamps, &open_interests, &values],
+            )
+            .await?;
+
+        Ok(created)
+    }
+
+    pub async fn find_latest_time(
+        &self,
+        symbol: &str,
+        period_minutes: i32,
+    ) -> Result<Option<DateTime<Utc>>> {
+        let row = self
+            .client
+            .lock()
+            .await
+            .query_one(
+                "SELECT MAX(timestamp) FROM OpenInterest
+                 WHERE symbol = $1 AND period_minutes = $2",
+                &[&symbol, &period_minutes],
+            )
+            .await?;
+
+        Ok(row.get(0))
+    }
+
+    // Latest open interest known at each perpetual candle's open and close,
+    // from the finest period available
+    pub async fn find_candle_open_interest(
+        &self,
+        market_data_ids: &[Uuid],
+    ) -> Result<HashMap<Uuid, (Decimal, Option<Decimal>)>> {
+        let rows = self
+            .client
+            .lock()
+            .await
+            .query(
+                "SELECT m.id, at_close.sum_open_interest, at_open.sum_open_interest
+                 FROM MarketData AS m
+                 CROSS JOIN LATERAL (
+                     SELECT sum_open_interest
+                     FROM OpenInterest
+                     WHERE symbol = m.symbol
+                       AND timestamp <= m.close_time
+                     ORDER BY timestamp DESC, period_minutes ASC
+                     LIMIT 1
+                 ) AS at_close
+                 LEFT JOIN LATERAL (
+                     SELECT sum_open_interest
+                     FROM OpenInterest
+                     WHERE symbol = m.symbol
+                       AND timestamp <= m.open_time
+                     ORDER BY timestamp DESC, period_minutes ASC
+                     LIMIT 1
+                 ) AS at_open ON TRUE
+                 WHERE m.id = ANY($1)
+                   AND m.contract_type = 'PERPETUAL'",
+                &[&market_data_ids],
+            )
+            .await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| (row.get(0), (row.get(1), row.get(2))))
+            .collect())
+    }
+}
@@ -0,0 +1,87 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use anyhow::Result;
+use rust_decimal::Decimal;
+use tokio::sync::Mutex;
+use tokio_postgres::Client;
+use uuid::Uuid;
+
+use crate::models::order_book_snapshot::OrderBookSnapshot;
+
+pub struct OrderBookRepository {
+    client: Arc<Mutex<Client>>,
+}
+
+impl OrderBookRepository {
+    pub fn new(client: Client) -> Self {
+        Self::from_shared(Arc::new(Mutex::new(client)))
+    }
+
+    pub fn from_shared(client: Arc<Mutex<Client>>) -> Self {
+        Self { client }
+    }
+
+    pub async fn create(&self, snapshot: &OrderBookSnapshot) -> Result<u64> {
+        let created = self
+            .client
+            .lock()
+            .await
+            .execute(
+                "INSERT INTO OrderBookSnapshots (
+                    symbol,
+                    contract_type,
+                    snapshot_time,
+                    last_update_id,
+                    bid_prices,
+                    bid_quantities,
+                    ask_prices,
+                    ask_quantities,
+                    imbalance
+                 )
+                 VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+                 ON CONFLICT (symbol, contract_type, snapshot_time) DO NOTHING",
+                &[
+                    &snapshot.symbol,
+                    &snapshot.contract_type,
+                    &snapshot.snapshot_time,
+                    &snapshot.last_update_id,
+                    &snapshot.bid_prices,
+                    &snapshot.bid_quantities,
+                    &snapshot.ask_prices,
+                    &snapshot.ask_quantities,
+                    &snapshot.imbalance,
+                ],
+            )
+            .await?;
+
+        Ok(created)
+    }
+
+    // Mean imbalance of the snapshots taken while each candle was open,
+    // candles without snapshots are left out
+    pub async fn find_candle_imbalances(
+        &self,
+        market_data_ids: &[Uuid],
+    ) -> Result<HashMap<Uuid, Decimal>> {
+        let rows = self
+            .client
+            .lock()
+            .await
+            .query(
+                "SELECT m.id, AVG(s.imbalance)
+                 FROM MarketData AS m
+                 JOIN OrderBookSnapshots AS s
+                   ON s.symbol = m.symbol
+                  AND s.contract_type = m.contract_type
+                  AND s.snapshot_time BETWEEN m.open_time AND m.close_time
+                 WHERE m.id = ANY($1)
+                   AND s.imbalance IS NOT NULL
+                 GROUP BY m.id",
+                &[&market_data_ids],
+            )
+            .await?;
+
+        Ok(rows.iter().map(|row| (row.get(0), row.get(1))).collect())
+    }
+}
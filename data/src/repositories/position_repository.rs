@@ -0,0 +1,176 @@
+use anyhow::Result;
+use tokio_postgres::{Client, Row};
+
+use crate::models::position::Position;
+
+const POSITION_COLUMNS: &str = "id,
+    market_data_id,
+    symbol,
+    contract_type,
+    side,
+    size,
+    entry_price,
+    take_profit,
+    stop_loss,
+    entry_time,
+    exit_time,
+    exit_price,
+    pnl,
+    status";
+
+pub struct PositionRepository {
+    client: Client,
+}
+
+impl PositionRepository {
+    pub fn new(client: Client) -> Self {
+        Self { client }
+    }
+
+    // Stores the position under its own id. Returns false when a position
+    // with this id is already stored, it is then left as it is.
+    pub async fn create(&self, position: &Position) -> Result<bool> {
+        let created = self
+            .client
+            .execute(
+                "INSERT INTO Positions (
+                    id,
+                    market_data_id,
+                    symbol,
+                    contract_type,
+                    side,
+                    size,
+                    entry_price,
+                    take_profit,
+                    stop_loss,
+                    entry_time,
+                    exit_time,
+                    exit_price,
+                    pnl,
+                    status
+                 )
+                 VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14)
+                 ON CONFLICT (id) DO NOTHING",
+                &[
+                    &position.id,
+                    &position.market_data_id,
+                    &position.symbol,
+                    &position.contract_type,
+                    &position.side,
+                    &position.size,
+                    &position.entry_price,
+                    &position.take_profit,
+                    &position.stop_loss,
+                    &position.entry_time,
+                    &position.exit_time,
+                    &position.exit_price,
+                    &position.pnl,
+                    &position.status,
+                ],
+            )
+            .await?;
+
+        Ok(created == 1)
+    }
+
+    // Saves the exit, pnl and status of a stored position. Returns false when
+    // no position has this id.
+    pub async fn update_exit(&self, position: &Position) -> Result<bool> {
+        let updated = self
+            .client
+            .execute(
+                "UPDATE Positions
+                 SET exit_time = $2,
+                     exit_price = $3,
+                     pnl = $4,
+                     status = $5
+                 WHERE id = $1",
+                &[
+                    &position.id,
+                    &position.exit_time,
+                    &position.exit_price,
+                    &position.pnl,
+                    &position.status,
+                ],
+            )
+            .await?;
+
+        Ok(updated == 1)
+    }
+
+    pub async fn find_open(
+        &self,
+        symbols: &[String],
+        contract_type: &str,
+    ) -> Result<Vec<Position>> {
+        let rows = self
+            .client
+            .query(
+                &format!(
+                    "SELECT {} FROM Positions
+                     WHERE symbol = ANY($1)
+                       AND contract_type = $2
+                       AND status = 'open'
+                     ORDER BY entry_time",
+                    POSITION_COLUMNS
+                ),
+                &[&symbols, &contract_type],
+            )
+            .await?;
+
+        Ok(rows.iter().map(Self::map_row).collect())
+    }
+
+    pub async fn find_open_by_symbol(
+        &self,
+        symbol: &str,
+        contract_type: &str,
+    ) -> Result<Vec<Position>> {
+        self.find_open(&[symbol.to_string()], contract_type).await
+    }
+
+    // Latest closed positions of a symbol, last exited first
+    pub async fn find_history(
+        &self,
+        symbol: &str,
+        contract_type: &str,
+        limit: i64,
+    ) -> Result<Vec<Position>> {
+        let rows = self
+            .client
+            .query(
+                &format!(
+                    "SELECT {} FROM Positions
+                     WHERE symbol = $1
+                       AND contract_type = $2
+                       AND status <> 'open'
+                     ORDER BY exit_time DESC NULLS LAST, entry_time DESC
+                     LIMIT $3",
+                    POSITION_COLUMNS
+                ),
+                &[&symbol, &contract_type, &limit],
+            )
+            .await?;
+
+        Ok(rows.iter().map(Self::map_row).collect())
+    }
+
+    fn map_row(row: &Row) -> Position {
+        Position {
+            id: row.get("id"),
+            market_data_id: row.get("market_data_id"),
+            symbol: row.get("symbol"),
+            contract_type: row.get("contract_type"),
+            side: row.get("side"),
+            size: row.get("size"),
+            entry_price: row.get("entry_price"),
+            take_profit: row.get("take_profit"),
+            stop_loss: row.get("stop_loss"),
+            entry_time: row.get("entry_time"),
+            exit_time: row.get("exit_time"),
+            exit_price: row.get("exit_price"),
+            pnl: row.get("pnl"),
+            status: row.get("status"),
+        }
+    }
+}
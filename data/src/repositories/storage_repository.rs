@@ -0,0 +1,79 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use tokio_postgres::Client;
+
+const RETENTION_BATCH_SIZE: i64 = 10000;
+
+// Retention of the 1m candles of MarketData and of their archives
+pub struct StorageRepository {
+    client: Client,
+}
+
+impl StorageRepository {
+    pub fn new(client: Client) -> Self {
+        Self { client }
+    }
+
+    // Deletes the 1m candles opened before `before` in batches, except those
+    // referenced by positions or predictions. Returns the number deleted.
+    pub async fn delete_raw_candles(&self, before: DateTime<Utc>) -> Result<u64> {
+        let mut deleted = 0;
+        loop {
+            let batch = self
+                .client
+                .execute(
+                    "WITH expired AS (
+                        SELECT m.id
+                        FROM MarketData m
+                        JOIN Timeframes t ON t.id = m.timeframe_id
+                        WHERE t.interval_minutes = 1
+                          AND m.open_time < $1
+                          AND NOT EXISTS (SELECT 1 FROM Positions p WHERE p.market_data_id = m.id)
+                          AND NOT EXISTS (SELECT 1 FROM ModelPredictions mp WHERE mp.market_data_id = m.id)
+                        LIMIT $2
+                     )
+                     DELETE FROM MarketData m
+                     USING expired e
+                     WHERE m.id = e.id
+                       AND m.open_time < $1",
+                    &[&before, &RETENTION_BATCH_SIZE],
+                )
+                .await?;
+            deleted += batch;
+            if batch < RETENTION_BATCH_SIZE as u64 {
+                return Ok(deleted);
+            }
+        }
+    }
+
+    // Archives of 1m candles whose last candle opened before `before`
+    pub async fn delete_raw_archives(&self, before: DateTime<Utc>) -> Result<u64> {
+        let deleted = self
+            .client
+            .execute(
+                "DELETE FROM MarketDataArchive a
+                 USING Timeframes t
+                 WHERE t.id = a.timeframe_id
+                   AND t.interval_minutes = 1
+                   AND a.last_open_time < $1",
+                &[&before],
+            )
+            .await?;
+        Ok(deleted)
+    }
+
+    // Stored 1m candles and the open time of the oldest one
+    pub async fn raw_candle_summary(&self) -> Result<(i64, Option<DateTime<Utc>>)> {
+        let row = self
+            .client
+            .query_one(
+                "SELECT count(*), min(m.open_time)
+                 FROM MarketData m
+                 JOIN Timeframes t ON t.id = m.timeframe_id
+                 WHERE t.interval_minutes = 1",
+                &[],
+            )
+            .await?;
+        Ok((row.get(0), row.get(1)))
+    }
+}
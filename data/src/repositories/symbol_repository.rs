@@ -0,0 +1,215 @@
+use anyhow::Result;
+use rust_decimal::Decimal;
+use tokio_postgres::{Client, Row};
+
+use crate::models::symbol_info::{LeverageBracket, SymbolInfo};
+
+pub struct SymbolRepository {
+    client: Client,
+}
+
+impl SymbolRepository {
+    pub fn new(client: Client) -> Self {
+        Self { client }
+    }
+
+    pub async fn upsert_batch(&self, symbols: &[SymbolInfo]) -> Result<u64> {
+        let names: Vec<&str> = symbols.iter().map(|s| s.symbol.as_str()).collect();
+        let pairs: Vec<&str> = symbols.iter().map(|s| s.pair.as_str()).collect();
+        let contract_types: Vec<&str> = symbols.iter().map(|s| s.contract_type.as_str()).collect();
+        let statuses: Vec<&str> = symbols.iter().map(|s| s.status.as_str()).collect();
+        let base_assets: Vec<&str> = symbols.iter().map(|s| s.base_asset.as_str()).collect();
+        let quote_assets: Vec<&str> = symbols.iter().map(|s| s.quote_asset.as_str()).collect();
+        let price_precisions: Vec<i16> = symbols.iter().map(|s| s.price_precision).collect();
+        let quantity_precisions: Vec<i16> = symbols.iter().map(|s| s.quantity_precision).collect();
+        let tick_sizes: Vec<Decimal> = symbols.iter().map(|s| s.tick_size).collect();
+        let step_sizes: Vec<Decimal> = symbols.iter().map(|s| s.step_size).collect();
+        let min_quantities: Vec<Decimal> = symbols.iter().map(|s| s.min_quantity).collect();
+        let min_notionals: Vec<Option<Decimal>> = symbols.iter().map(|s| s.min_notional).collect();
+
+        let upserted = self
+            .client
+            .execute(
+                "INSERT INTO Symbols (
+                    symbol,
+                    pair,
+                    contract_type,
+                    status,
+                    base_asset,
+                    quote_asset,
+                    price_precision,
+                    quantity_precision,
+                    tick_size,
+                    step_size,
+                    min_quantity,
+                    min_notional
+                 )
+                 SELECT * FROM UNNEST(
+                    $1::varchar[],
+                    $2::varchar[],
+                    $3::varchar[],
+                    $4::varchar[],
+                    $5::varchar[],
+                    $6::varchar[],
+                    $7::smallint[],
+                    $8::smallint[],
+                    $9::numeric[],
+                    $10::numeric[],
+                    $11::numeric[],
+                    $12::numeric[]
+                 )
+                 ON CONFLICT (symbol) DO UPDATE SET
+                    pair = EXCLUDED.pair,
+                    contract_type = EXCLUDED.contract_type,
+                    status = EXCLUDED.status,
+                    base_asset = EXCLUDED.base_asset,
+                    quote_asset = EXCLUDED.quote_asset,
+                    price_precision = EXCLUDED.price_precision,
+                    quantity_precision = EXCLUDED.quantity_precision,
+                    tick_size = EXCLUDED.tick_size,
+                    step_size = EXCLUDED.step_size,
+                    min_quantity = EXCLUDED.min_quantity,
+                    min_notional = EXCLUDED.min_notional,
+                    updated_at = CURRENT_TIMESTAMP",
+                &[
+                    &names,
+                    &pairs,
+                    &contract_types,
+                    &statuses,
+                    &base_assets,
+                    &quote_assets,
+                    &price_precisions,
+                    &quantity_precisions,
+                    &tick_sizes,
+                    &step_sizes,
+                    &min_quantities,
+                    &min_notionals,
+                ],
+            )
+            .await?;
+
+        Ok(upserted)
+    }
+
+    // Replaces the tiers of the symbols present in `brackets`
+    pub async fn replace_leverage_brackets(&mut self, brackets: &[LeverageBracket]) -> Result<u64> {
+        let symbols: Vec<&str> = brackets.iter().map(|b| b.symbol.as_str()).collect();
+        let bracket_numbers: Vec<i16> = brackets.iter().map(|b| b.bracket).collect();
+        let leverages: Vec<i16> = brackets.iter().map(|b| b.initial_leverage).collect();
+        let floors: Vec<Decimal> = brackets.iter().map(|b| b.notional_floor).collect();
+        let caps: Vec<Decimal> = brackets.iter().map(|b| b.notional_cap).collect();
+        let ratios: Vec<Decimal> = brackets.iter().map(|b| b.maint_margin_ratio).collect();
+
+        let transaction = self.client.transaction().await?;
+        transaction
+            .execute(
+                "DELETE FROM LeverageBrackets WHERE symbol = ANY($1)",
+                &[&symbols],
+            )
+            .await?;
+        let inserted = transaction
+            .execute(
+                "INSERT INTO LeverageBrackets (
+                    symbol,
+                    bracket,
+                    initial_leverage,
+                    notional_floor,
+                    notional_cap,
+                    maint_margin_ratio
+                 )
+                 SELECT * FROM UNNEST(
+                    $1::varchar[],
+                    $2::smallint[],
+                    $3::smallint[],
+                    $4::numeric[],
+                    $5::numeric[],
+                    $6::numeric[]
+                 )",
+                &[
+                    &symbols,
+                    &bracket_numbers,
+                    &leverages,
+                    &floors,
+                    &caps,
+                    &ratios,
+                ],
+            )
+            .await?;
+        transaction.commit().await?;
+
+        Ok(inserted)
+    }
+
+    pub async fn find(&self, symbol: &str) -> Result<Option<SymbolInfo>> {
+        let row = self
+            .client
+            .query_opt(
+                "SELECT symbol,
+                        pair,
+                        contract_type,
+                        status,
+                        base_asset,
+                        quote_asset,
+                        price_precision,
+                        quantity_precision,
+                        tick_size,
+                        step_size,
+                        min_quantity,
+                        min_notional
+                 FROM Symbols
+                 WHERE symbol = $1",
+                &[&symbol],
+            )
+            .await?;
+
+        Ok(row.as_ref().map(Self::symbol_from_row))
+    }
+
+    // Tiers of a symbol, lowest notional first
+    pub async fn find_leverage_brackets(&self, symbol: &str) -> Result<Vec<LeverageBracket>> {
+        let rows = self
+            .client
+            .query(
+                "SELECT symbol,
+                        bracket,
+                        initial_leverage,
+                        notional_floor,
+                        notional_cap,
+                        maint_margin_ratio
+                 FROM LeverageBrackets
+                 WHERE symbol = $1
+                 ORDER BY notional_floor",
+                &[&symbol],
+            )
+            .await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| LeverageBracket {
+                symbol: row.get(0),
+                bracket: row.get(1),
+                initial_leverage: row.get(2),
+                notional_floor: row.get(3),
+                notional_cap: row.get(4),
+                maint_margin_ratio: row.get(5),
+            })
+            .collect())
+    }
+
+    fn symbol_from_row(row: &Row) -> SymbolInfo {
+        SymbolInfo {
+            symbol: row.get(0),
+            pair: row.get(1),
+            contract_type: row.get(2),
+            status: row.get(3),
+            base_asset: row.get(4),
+            quote_asset: row.get(5),
+            price_precision: row.get(6),
+            quantity_precision: row.get(7),
+            tick_size: row.get(8),
+            step_size: row.get(9),
+            min_quantity: row.get(10),
+            min_notional: row.get(11),
+        }
+    }
+}
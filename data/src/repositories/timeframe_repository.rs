@@ -1,23 +1,34 @@
+use std::sync::Arc;
+
 use anyhow::Result;
+use chrono::{DateTime, Utc};
+use tokio::sync::Mutex;
 use tokio_postgres::Client;
+use uuid::Uuid;
 
 use crate::{
-    utils::helper::Helper,
     models::timeframe::{ContractType, TimeFrame},
+    utils::helper::Helper,
 };
 
 pub struct TimeFrameRepository {
-    client: Client,
+    client: Arc<Mutex<Client>>,
 }
 
 impl TimeFrameRepository {
     pub fn new(client: Client) -> Self {
+        Self::from_shared(Arc::new(Mutex::new(client)))
+    }
+
+    pub fn from_shared(client: Arc<Mutex<Client>>) -> Self {
         Self { client }
     }
 
     pub async fn create(&self, time_frame: &TimeFrame) -> Result<TimeFrame> {
         let row = self
             .client
+            .lock()
+            .await
             .query_one(
                 "INSERT INTO Timeframes (symbol, contract_type, interval_minutes)
                     VALUES ($1, $2, $3)
@@ -47,8 +58,40 @@ impl TimeFrameRepository {
     ) -> Result<TimeFrame> {
         let interval_minutes = Helper::interval_to_minutes(&interval).unwrap();
 
-        if let Some(row) = self
+        if let Some(timeframe) = self.find(&symbol, &contract_type, interval_minutes).await? {
+            return Ok(timeframe);
+        }
+
+        let timeframe = TimeFrame::new(symbol, contract_type, interval_minutes);
+
+        self.create(&timeframe).await
+    }
+
+    // Like find_or_create without saving a missing timeframe, for dry runs
+    pub async fn find_or_new(
+        &self,
+        symbol: String,
+        contract_type: ContractType,
+        interval: String,
+    ) -> Result<TimeFrame> {
+        let interval_minutes = Helper::interval_to_minutes(&interval).unwrap();
+
+        Ok(self
+            .find(&symbol, &contract_type, interval_minutes)
+            .await?
+            .unwrap_or_else(|| TimeFrame::new(symbol, contract_type, interval_minutes)))
+    }
+
+    pub async fn find(
+        &self,
+        symbol: &str,
+        contract_type: &ContractType,
+        interval_minutes: i32,
+    ) -> Result<Option<TimeFrame>> {
+        let row = self
             .client
+            .lock()
+            .await
             .query_opt(
                 "SELECT id,
                         symbol,
@@ -61,19 +104,58 @@ impl TimeFrameRepository {
                    AND interval_minutes = $3",
                 &[&symbol, &contract_type, &interval_minutes],
             )
-            .await?
-        {
-            return Ok(TimeFrame {
-                id: row.get(0),
-                symbol: row.get(1),
-                contract_type: row.get(2),
-                interval_minutes: row.get(3),
-                created_at: row.get(4),
-            });
-        }
+            .await?;
 
-        let timeframe = TimeFrame::new(symbol, contract_type, interval_minutes);
+        Ok(row.map(|row| TimeFrame {
+            id: row.get(0),
+            symbol: row.get(1),
+            contract_type: row.get(2),
+            interval_minutes: row.get(3),
+            created_at: row.get(4),
+        }))
+    }
 
-        self.create(&timeframe).await
+    // Window start and end of the last completed chunk of the initial fetch
+    pub async fn find_initialization_progress(
+        &self,
+        timeframe_id: &Uuid,
+    ) -> Result<Option<(DateTime<Utc>, DateTime<Utc>)>> {
+        let row = self
+            .client
+            .lock()
+            .await
+            .query_one(
+                "SELECT initialized_from, initialized_until
+                 FROM Timeframes
+                 WHERE id = $1",
+                &[timeframe_id],
+            )
+            .await?;
+
+        let initialized_from: Option<DateTime<Utc>> = row.get(0);
+        let initialized_until: Option<DateTime<Utc>> = row.get(1);
+        Ok(initialized_from.zip(initialized_until))
+    }
+
+    pub async fn save_initialization_progress(
+        &self,
+        timeframe_id: &Uuid,
+        initialized_from: DateTime<Utc>,
+        initialized_until: DateTime<Utc>,
+    ) -> Result<u64> {
+        let updated = self
+            .client
+            .lock()
+            .await
+            .execute(
+                "UPDATE Timeframes
+                 SET initialized_from = $2,
+                     initialized_until = $3
+                 WHERE id = $1",
+                &[timeframe_id, &initialized_from, &initialized_until],
+            )
+            .await?;
+
+        Ok(updated)
     }
 }
@@ -0,0 +1,72 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use tokio::sync::Mutex;
+use tokio_postgres::{Client, CopyOutStream};
+use uuid::Uuid;
+
+pub struct TrainingFeatureRepository {
+    client: Arc<Mutex<Client>>,
+}
+
+impl TrainingFeatureRepository {
+    pub fn new(client: Client) -> Self {
+        Self::from_shared(Arc::new(Mutex::new(client)))
+    }
+
+    pub fn from_shared(client: Arc<Mutex<Client>>) -> Self {
+        Self { client }
+    }
+
+    // Regenerates the feature rows of the candles from TrainingFeatureRows,
+    // candles no longer usable by the model lose theirs. Returns the number
+    // of rows written.
+    pub async fn refresh(&self, market_data_ids: &[Uuid]) -> Result<u64> {
+        let mut client = self.client.lock().await;
+        let transaction = client.transaction().await?;
+        transaction
+            .execute(
+                "DELETE FROM TrainingFeatures WHERE market_data_id = ANY($1)",
+                &[&market_data_ids],
+            )
+            .await?;
+        let written = transaction
+            .execute(
+                "INSERT INTO TrainingFeatures
+                 SELECT * FROM TrainingFeatureRows
+                 WHERE market_data_id = ANY($1)",
+                &[&market_data_ids],
+            )
+            .await?;
+        transaction.commit().await?;
+
+        Ok(written)
+    }
+
+    // Feature rows of a timeframe opened from `from` to `to` excluded, oldest
+    // first, as CSV with a header line. The rows are streamed by the server,
+    // whole histories are never held in memory.
+    pub async fn copy_csv(
+        &self,
+        timeframe_id: &Uuid,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<CopyOutStream> {
+        // COPY takes no parameters, the values are formatted by their own types
+        let query = format!(
+            "COPY (
+                SELECT * FROM TrainingFeatures
+                WHERE timeframe_id = '{}'
+                  AND open_time >= '{}'
+                  AND open_time < '{}'
+                ORDER BY open_time
+             ) TO STDOUT WITH (FORMAT csv, HEADER)",
+            timeframe_id,
+            from.to_rfc3339(),
+            to.to_rfc3339()
+        );
+
+        Ok(self.client.lock().await.copy_out(&query).await?)
+    }
+}
@@ -0,0 +1,130 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use tokio::sync::Mutex;
+use tokio_postgres::Client;
+use uuid::Uuid;
+
+use crate::models::triple_barrier_label::{LabelingCandle, TripleBarrierLabel};
+
+pub struct TripleBarrierLabelRepository {
+    client: Arc<Mutex<Client>>,
+}
+
+impl TripleBarrierLabelRepository {
+    pub fn from_shared(client: Arc<Mutex<Client>>) -> Self {
+        Self { client }
+    }
+
+    // Candles from the oldest pending one onwards, oldest first, so every
+    // pending candle comes with the candles following it
+    pub async fn find_labeling_window(
+        &self,
+        timeframe_id: &Uuid,
+        limit: i64,
+    ) -> Result<Vec<LabelingCandle>> {
+        let rows = self
+            .client
+            .lock()
+            .await
+            .query(
+                "SELECT m.id,
+                        m.open_time,
+                        m.high,
+                        m.low,
+                        m.close,
+                        COALESCE(m.analyzed, FALSE) AND l.market_data_id IS NULL
+                 FROM MarketData AS m
+                 LEFT JOIN TripleBarrierLabels AS l ON l.market_data_id = m.id
+                 WHERE m.timeframe_id = $1
+                   AND m.open_time >= (
+                       SELECT MIN(u.open_time)
+                       FROM MarketData AS u
+                       WHERE u.timeframe_id = $1
+                         AND u.analyzed
+                         AND NOT EXISTS (
+                             SELECT 1 FROM TripleBarrierLabels AS x WHERE x.market_data_id = u.id
+                         )
+                   )
+                 ORDER BY m.open_time ASC
+                 LIMIT $2",
+                &[timeframe_id, &limit],
+            )
+            .await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| LabelingCandle {
+                market_data_id: row.get(0),
+                open_time: row.get(1),
+                high: row.get(2),
+                low: row.get(3),
+                close: row.get(4),
+                pending: row.get(5),
+            })
+            .collect())
+    }
+
+    // One round trip for the whole batch, already labeled candles are skipped
+    pub async fn create_batch(&self, labels: &[TripleBarrierLabel]) -> Result<u64> {
+        let market_data_ids: Vec<Uuid> = labels.iter().map(|l| l.market_data_id).collect();
+        let timeframe_ids: Vec<Uuid> = labels.iter().map(|l| l.timeframe_id).collect();
+        let open_times: Vec<DateTime<Utc>> = labels.iter().map(|l| l.open_time).collect();
+        let values: Vec<i16> = labels.iter().map(|l| l.label).collect();
+        let barriers: Vec<&str> = labels.iter().map(|l| l.barrier.as_str()).collect();
+        let realized_returns: Vec<Decimal> = labels.iter().map(|l| l.realized_return).collect();
+        let holding_candles: Vec<i32> = labels.iter().map(|l| l.holding_candles).collect();
+        let profit_targets: Vec<Decimal> = labels.iter().map(|l| l.profit_target).collect();
+        let stop_losses: Vec<Decimal> = labels.iter().map(|l| l.stop_loss).collect();
+        let max_holding_candles: Vec<i32> = labels.iter().map(|l| l.max_holding_candles).collect();
+
+        let created = self
+            .client
+            .lock()
+            .await
+            .execute(
+                "INSERT INTO TripleBarrierLabels (
+                    market_data_id,
+                    timeframe_id,
+                    open_time,
+                    label,
+                    barrier,
+                    realized_return,
+                    holding_candles,
+                    profit_target,
+                    stop_loss,
+                    max_holding_candles
+                 )
+                 SELECT * FROM UNNEST(
+                    $1::uuid[],
+                    $2::uuid[],
+                    $3::timestamptz[],
+                    $4::smallint[],
+                    $5::varchar[],
+                    $6::numeric[],
+                    $7::integer[],
+                    $8::numeric[],
+                    $9::numeric[],
+                    $10::integer[]
+                 )
+                 ON CONFLICT (market_data_id) DO NOTHING",
+                &[
+                    &market_data_ids,
+                    &timeframe_ids,
+                    &open_times,
+                    &values,
+                    &barriers,
+                    &realized_returns,
+                    &holding_candles,
+                    &profit_targets,
+                    &stop_losses,
+                    &max_holding_candles,
+                ],
+            )
+            .await?;
+
+        Ok(created)
+    }
+}
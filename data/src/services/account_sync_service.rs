@@ -0,0 +1,136 @@
+use anyhow::Result;
+use rust_decimal::Decimal;
+use std::collections::BTreeMap;
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tokio::time::interval;
+
+use crate::{
+    models::{account::ExchangePosition, position::Position, timeframe::ContractType},
+    repositories::position_repository::PositionRepository,
+};
+
+use super::binance_account_service::BinanceAccountClient;
+use super::database_service::DatabaseService;
+
+// Net position of a symbol that differs between the Positions table and the
+// exchange, amounts are signed (negative for shorts)
+#[derive(Debug)]
+pub struct PositionDivergence {
+    pub symbol: String,
+    pub local_amount: Decimal,
+    pub exchange_amount: Decimal,
+}
+
+// Periodically pulls the futures balances and open positions of the account
+// and flags the perpetual symbols whose net position differs from the open
+// local positions. Nothing is corrected, the divergences are logged as alerts.
+pub struct AccountSyncService {
+    client: BinanceAccountClient,
+    symbols: Vec<String>,
+    position_repository: PositionRepository,
+}
+
+impl AccountSyncService {
+    pub async fn new(symbols: Vec<String>) -> Result<Self> {
+        let client = BinanceAccountClient::from_env()?;
+        let database = DatabaseService::new().await?;
+
+        Ok(AccountSyncService {
+            client,
+            symbols,
+            position_repository: PositionRepository::new(database.client),
+        })
+    }
+
+    pub async fn run(&self, interval_seconds: u64, mut shutdown: broadcast::Receiver<()>) {
+        let mut ticker = interval(Duration::from_secs(interval_seconds.max(1)));
+        loop {
+            tokio::select! {
+                _ = ticker.tick() => {
+                    if let Err(e) = self.sync().await {
+                        tracing::error!("Account sync failed: {}", e);
+                    }
+                }
+                _ = shutdown.recv() => return,
+            }
+        }
+    }
+
+    // Returns the number of diverging symbols
+    pub async fn sync(&self) -> Result<usize> {
+        for balance in self.client.fetch_balances().await? {
+            tracing::info!(
+                "{} wallet balance {} ({} available, {} unrealized)",
+                balance.asset,
+                balance.wallet_balance,
+                balance.available_balance,
+                balance.unrealized_profit
+            );
+        }
+
+        let exchange_positions = self.client.fetch_positions().await?;
+        let local_positions = self
+            .position_repository
+            .find_open(&self.symbols, &ContractType::Perpetual.to_string())
+            .await?;
+
+        let divergences = Self::reconcile(&self.symbols, &local_positions, &exchange_positions);
+        for divergence in &divergences {
+            tracing::warn!(
+                target: "alerts",
+                "Position divergence on {}: {} open locally, {} on the exchange",
+                divergence.symbol,
+                divergence.local_amount,
+                divergence.exchange_amount
+            );
+        }
+        if divergences.is_empty() {
+            tracing::info!(
+                "Account in sync, {} open positions on the exchange",
+                exchange_positions
+                    .iter()
+                    .filter(|p| self.symbols.contains(&p.symbol))
+                    .count()
+            );
+        }
+
+        Ok(divergences.len())
+    }
+
+    // Compares net amounts per symbol, which also covers hedge mode where the
+    // exchange reports a LONG and a SHORT position for the same symbol
+    fn reconcile(
+        symbols: &[String],
+        local_positions: &[Position],
+        exchange_positions: &[ExchangePosition],
+    ) -> Vec<PositionDivergence> {
+        let mut amounts: BTreeMap<&str, (Decimal, Decimal)> = symbols
+            .iter()
+            .map(|symbol| (symbol.as_str(), (Decimal::ZERO, Decimal::ZERO)))
+            .collect();
+
+        for position in local_positions {
+            if let Some((local, _)) = amounts.get_mut(position.symbol.as_str()) {
+                *local += position.signed_size();
+            }
+        }
+        for position in exchange_positions {
+            if let Some((_, exchange)) = amounts.get_mut(position.symbol.as_str()) {
+                *exchange += position.position_amount;
+            }
+        }
+
+        amounts
+            .into_iter()
+            .filter(|(_, (local, exchange))| local != exchange)
+            .map(
+                |(symbol, (local_amount, exchange_amount))| PositionDivergence {
+                    symbol: symbol.to_string(),
+                    local_amount,
+                    exchange_amount,
+                },
+            )
+            .collect()
+    }
+}
@@ -0,0 +1,257 @@
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde_json::Value;
+use std::collections::HashSet;
+use std::env;
+use std::str::FromStr;
+
+use crate::{
+    models::{
+        account::{AccountBalance, ExchangePosition, IncomeRecord},
+        symbol_info::LeverageBracket,
+    },
+    services::configuration_service::Exchange,
+    utils::{
+        clock,
+        http::{self, Endpoint},
+        retry::RetryPolicy,
+        signing::hmac_sha256_hex,
+    },
+};
+
+const ACCOUNT_API_PATH: &str = "fapi/v2/account";
+const POSITION_RISK_API_PATH: &str = "fapi/v2/positionRisk";
+const INCOME_API_PATH: &str = "fapi/v1/income";
+const LEVERAGE_BRACKET_API_PATH: &str = "fapi/v1/leverageBracket";
+const INCOME_FETCH_LIMIT: usize = 1000;
+const RECV_WINDOW: u64 = 5000; // in milliseconds
+
+// Client of the signed USD-M futures endpoints. Every request carries the
+// API key header and an HMAC-SHA256 signature of its query string.
+pub struct BinanceAccountClient {
+    client: reqwest::Client,
+    api_key: String,
+    api_secret: String,
+}
+
+impl BinanceAccountClient {
+    // Reads BINANCE_API_KEY and BINANCE_API_SECRET, a read-only key is enough
+    pub fn from_env() -> Result<Self> {
+        let api_key = env::var("BINANCE_API_KEY").context("BINANCE_API_KEY is not set")?;
+        let api_secret = env::var("BINANCE_API_SECRET").context("BINANCE_API_SECRET is not set")?;
+
+        Ok(BinanceAccountClient {
+            client: http::client(),
+            api_key,
+            api_secret,
+        })
+    }
+
+    // Balances of the assets held in the futures wallet
+    pub async fn fetch_balances(&self) -> Result<Vec<AccountBalance>> {
+        let account = self.signed_get(ACCOUNT_API_PATH, &[]).await?;
+
+        account["assets"]
+            .as_array()
+            .ok_or_else(|| anyhow!("Invalid account response format"))?
+            .iter()
+            .map(|asset| {
+                Ok(AccountBalance {
+                    asset: parse_string(asset, "asset")?,
+                    wallet_balance: parse_decimal(asset, "walletBalance")?,
+                    unrealized_profit: parse_decimal(asset, "unrealizedProfit")?,
+                    available_balance: parse_decimal(asset, "availableBalance")?,
+                })
+            })
+            .filter(|balance: &Result<AccountBalance>| {
+                balance
+                    .as_ref()
+                    .map_or(true, |b| !b.wallet_balance.is_zero())
+            })
+            .collect()
+    }
+
+    // Open positions only, positionRisk also lists every flat symbol
+    pub async fn fetch_positions(&self) -> Result<Vec<ExchangePosition>> {
+        let positions = self.signed_get(POSITION_RISK_API_PATH, &[]).await?;
+
+        positions
+            .as_array()
+            .ok_or_else(|| anyhow!("Invalid positionRisk response format"))?
+            .iter()
+            .map(|position| {
+                Ok(ExchangePosition {
+                    symbol: parse_string(position, "symbol")?,
+                    position_side: parse_string(position, "positionSide")?,
+                    position_amount: parse_decimal(position, "positionAmt")?,
+                    entry_price: parse_decimal(position, "entryPrice")?,
+                    mark_price: parse_decimal(position, "markPrice")?,
+                    unrealized_profit: parse_decimal(position, "unRealizedProfit")?,
+                    leverage: parse_decimal(position, "leverage")?,
+                })
+            })
+            .filter(|position: &Result<ExchangePosition>| {
+                position
+                    .as_ref()
+                    .map_or(true, |p| !p.position_amount.is_zero())
+            })
+            .collect()
+    }
+
+    // Income history from `since` on, oldest first. Entries can share a
+    // millisecond, so every page starts at the time of the last entry of the
+    // previous one and the entries already read are skipped by tranId.
+    pub async fn fetch_income_history(&self, since: DateTime<Utc>) -> Result<Vec<IncomeRecord>> {
+        let mut records = Vec::new();
+        let mut seen = HashSet::new();
+        let mut start_time = since.timestamp_millis();
+
+        loop {
+            let page = self
+                .signed_get(
+                    INCOME_API_PATH,
+                    &[
+                        ("startTime", start_time.to_string()),
+                        ("limit", INCOME_FETCH_LIMIT.to_string()),
+                    ],
+                )
+                .await?;
+            let entries = page
+                .as_array()
+                .ok_or_else(|| anyhow!("Invalid income response format"))?;
+
+            let mut new_entries = 0;
+            for entry in entries {
+                let time = entry["time"]
+                    .as_i64()
+                    .ok_or_else(|| anyhow!("Invalid income time"))?;
+                let tran_id = entry["tranId"]
+                    .as_i64()
+                    .ok_or_else(|| anyhow!("Invalid income tranId"))?;
+                start_time = start_time.max(time);
+                if !seen.insert(tran_id) {
+                    continue;
+                }
+                new_entries += 1;
+                records.push(IncomeRecord {
+                    symbol: parse_string(entry, "symbol")?,
+                    income_type: parse_string(entry, "incomeType")?,
+                    income: parse_decimal(entry, "income")?,
+                    asset: parse_string(entry, "asset")?,
+                    time: DateTime::<Utc>::from_timestamp_millis(time)
+                        .ok_or_else(|| anyhow!("Invalid income time"))?,
+                });
+            }
+
+            if entries.len() < INCOME_FETCH_LIMIT {
+                return Ok(records);
+            }
+            // A full page within one millisecond, the rest of it cannot be reached
+            if new_entries == 0 {
+                tracing::warn!(
+                    "More than {} income entries at {} ms, skipping the rest of them",
+                    INCOME_FETCH_LIMIT,
+                    start_time
+                );
+                start_time += 1;
+            }
+        }
+    }
+
+    // Notional tiers of every symbol
+    pub async fn fetch_leverage_brackets(&self) -> Result<Vec<LeverageBracket>> {
+        let symbols = self.signed_get(LEVERAGE_BRACKET_API_PATH, &[]).await?;
+        let mut brackets = Vec::new();
+
+        for symbol in symbols
+            .as_array()
+            .ok_or_else(|| anyhow!("Invalid leverageBracket response format"))?
+        {
+            let name = parse_string(symbol, "symbol")?;
+            for bracket in symbol["brackets"]
+                .as_array()
+                .ok_or_else(|| anyhow!("Invalid brackets of {}", name))?
+            {
+                brackets.push(LeverageBracket {
+                    symbol: name.clone(),
+                    bracket: parse_number(bracket, "bracket")?.try_into()?,
+                    initial_leverage: parse_number(bracket, "initialLeverage")?.try_into()?,
+                    notional_floor: parse_number(bracket, "notionalFloor")?,
+                    notional_cap: parse_number(bracket, "notionalCap")?,
+                    maint_margin_ratio: parse_number(bracket, "maintMarginRatio")?,
+                });
+            }
+        }
+
+        Ok(brackets)
+    }
+
+    async fn signed_get(&self, path: &str, params: &[(&str, String)]) -> Result<Value> {
+        let response = RetryPolicy::DEFAULT
+            .send(|| {
+                self.client
+                    .get(format!(
+                        "{}{}?{}",
+                        Endpoint::BinanceFuturesApi.url(),
+                        path,
+                        self.signed_query(params)
+                    ))
+                    .header("X-MBX-APIKEY", &self.api_key)
+            })
+            .await?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            tracing::error!(%status, %body, "Binance signed request failed");
+            return Err(anyhow!("{} failed with {}: {}", path, status, body));
+        }
+        Ok(response.json().await?)
+    }
+
+    // Query string with the timestamp, on the exchange clock so a drifting
+    // local clock stays within the receive window, and their signature
+    fn signed_query(&self, params: &[(&str, String)]) -> String {
+        let mut query = params
+            .iter()
+            .map(|(key, value)| format!("{}={}", key, value))
+            .chain([
+                format!(
+                    "timestamp={}",
+                    clock::now(Exchange::Binance).timestamp_millis()
+                ),
+                format!("recvWindow={}", RECV_WINDOW),
+            ])
+            .collect::<Vec<_>>()
+            .join("&");
+        let signature = hmac_sha256_hex(&self.api_secret, &query);
+        query.push_str(&format!("&signature={}", signature));
+        query
+    }
+}
+
+fn parse_string(value: &Value, field: &str) -> Result<String> {
+    value[field]
+        .as_str()
+        .map(str::to_string)
+        .ok_or_else(|| anyhow!("Invalid {} field", field))
+}
+
+fn parse_decimal(value: &Value, field: &str) -> Result<Decimal> {
+    value[field]
+        .as_str()
+        .and_then(|s| Decimal::from_str(s).ok())
+        .ok_or_else(|| anyhow!("Invalid {} decimal", field))
+}
+
+// The leverageBracket fields are JSON numbers rather than strings
+fn parse_number(value: &Value, field: &str) -> Result<Decimal> {
+    let number = value[field]
+        .as_number()
+        .ok_or_else(|| anyhow!("Invalid {} number", field))?
+        .to_string();
+    Decimal::from_str(&number)
+        .or_else(|_| Decimal::from_scientific(&number))
+        .map_err(|_| anyhow!("Invalid {} decimal {}", field, number))
+}
@@ -0,0 +1,127 @@
+use anyhow::{anyhow, Result};
+use chrono::{Datelike, Duration as DurationChrono, NaiveDate, Utc};
+use reqwest::StatusCode;
+use std::io::{Cursor, Read};
+use zip::ZipArchive;
+
+use crate::{
+    models::timeframe::ContractType,
+    services::configuration_service::CsvImportConfig,
+    utils::{http, retry::RetryPolicy},
+};
+
+use super::market_data_importer_service::MarketDataImporter;
+
+const BINANCE_VISION_URL: &str = "https://data.binance.vision/data/";
+
+// Loads candles from the Binance Vision kline archives: one zip per complete
+// month, and one per day for the rest of the range or while the monthly
+// archive is not published yet. Missing days, such as before the listing,
+// are skipped.
+pub struct BinanceVisionLoader {
+    client: reqwest::Client,
+    symbol: String,
+    market: &'static str,
+    interval: String,
+    importer: MarketDataImporter,
+}
+
+impl BinanceVisionLoader {
+    pub async fn new(
+        symbol: String,
+        contract_type: ContractType,
+        interval: String,
+    ) -> Result<Self> {
+        // The USD-M archives are per symbol, so only perpetuals match them
+        let market = match contract_type {
+            ContractType::Perpetual => "futures/um",
+            ContractType::Spot => "spot",
+            _ => {
+                return Err(anyhow!(
+                    "Binance Vision archives cover PERPETUAL and SPOT pairs only"
+                ))
+            }
+        };
+        let importer = MarketDataImporter::new(
+            symbol.clone(),
+            contract_type,
+            interval.clone(),
+            CsvImportConfig::default(),
+        )
+        .await?;
+
+        Ok(BinanceVisionLoader {
+            client: http::client(),
+            symbol,
+            market,
+            interval,
+            importer,
+        })
+    }
+
+    // Candles opened from `from` to `to` included, days from today on are not
+    // published yet
+    pub async fn load(&self, from: NaiveDate, to: NaiveDate) -> Result<usize> {
+        let to = to.min(Utc::now().date_naive() - DurationChrono::days(1));
+        let mut inserted = 0;
+        let mut day = from;
+
+        while day <= to {
+            let next_month = Self::first_day_of_next_month(day);
+            if day.day() == 1 && next_month <= to + DurationChrono::days(1) {
+                let archive = day.format("%Y-%m").to_string();
+                if let Some(count) = self.load_archive("monthly", &archive).await? {
+                    inserted += count;
+                    day = next_month;
+                    continue;
+                }
+            }
+
+            let archive = day.format("%Y-%m-%d").to_string();
+            match self.load_archive("daily", &archive).await? {
+                Some(count) => inserted += count,
+                None => tracing::warn!("No Binance Vision archive for {} {}", self.symbol, archive),
+            }
+            day += DurationChrono::days(1);
+        }
+
+        Ok(inserted)
+    }
+
+    // None when the archive does not exist
+    async fn load_archive(&self, period: &str, archive: &str) -> Result<Option<usize>> {
+        let url = format!(
+            "{}{}/{}/klines/{}/{}/{}-{}-{}.zip",
+            BINANCE_VISION_URL,
+            self.market,
+            period,
+            self.symbol,
+            self.interval,
+            self.symbol,
+            self.interval,
+            archive
+        );
+        let response = RetryPolicy::DEFAULT.send(|| self.client.get(&url)).await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        let bytes = response.error_for_status()?.bytes().await?;
+
+        // A single CSV per archive
+        let mut zip = ZipArchive::new(Cursor::new(bytes))?;
+        let mut csv = String::new();
+        zip.by_index(0)?.read_to_string(&mut csv)?;
+
+        let inserted = self.importer.import_lines(csv.as_bytes(), &url).await?;
+        tracing::info!("Inserted {} candles from {}", inserted, url);
+        Ok(Some(inserted))
+    }
+
+    fn first_day_of_next_month(day: NaiveDate) -> NaiveDate {
+        let (year, month) = match day.month() {
+            12 => (day.year() + 1, 1),
+            month => (day.year(), month + 1),
+        };
+        NaiveDate::from_ymd_opt(year, month, 1).expect("first day of a month is valid")
+    }
+}
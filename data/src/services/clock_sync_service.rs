@@ -0,0 +1,84 @@
+use anyhow::{anyhow, Result};
+use chrono::Utc;
+use serde_json::Value;
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tokio::time::interval;
+
+use crate::services::configuration_service::Exchange;
+use crate::utils::{
+    clock,
+    http::{self, Endpoint},
+    rate_limit,
+};
+
+const SERVER_TIME_API_PATH: &str = "fapi/v1/time";
+const CLOCK_SYNC_INTERVAL: u64 = 600; // 10 minutes in seconds
+
+// Signed requests are rejected past their 5 second receive window
+const MAX_CLOCK_DRIFT: i64 = 1000; // in milliseconds
+
+// Measures the offset between the local clock and the Binance server time,
+// which utils::clock applies to Binance request timestamps and candle close
+// checks only
+pub struct ClockSync {
+    client: reqwest::Client,
+}
+
+impl ClockSync {
+    pub fn new() -> Self {
+        ClockSync {
+            client: http::client(),
+        }
+    }
+
+    // Periodic sync, after the one done at startup
+    pub async fn run(&self, mut shutdown: broadcast::Receiver<()>) {
+        let mut ticker = interval(Duration::from_secs(CLOCK_SYNC_INTERVAL));
+        ticker.tick().await;
+        loop {
+            tokio::select! {
+                _ = ticker.tick() => {
+                    if let Err(e) = self.sync().await {
+                        tracing::error!("Clock sync failed: {}", e);
+                    }
+                }
+                _ = shutdown.recv() => return,
+            }
+        }
+    }
+
+    // Server time against the middle of the round trip, returns the offset
+    // in milliseconds. The request is sent once: the backoff of a retry would
+    // be measured as round trip, a failed sync waits for the next interval.
+    pub async fn sync(&self) -> Result<i64> {
+        let url = format!(
+            "{}{}",
+            Endpoint::BinanceFuturesApi.url(),
+            SERVER_TIME_API_PATH
+        );
+        let request = self.client.get(&url).build()?;
+        rate_limit::pace(request.url()).await;
+        let sent = Utc::now();
+        let response = self.client.execute(request).await?.error_for_status()?;
+        let received = Utc::now();
+        let payload: Value = response.json().await?;
+
+        let server_time = payload["serverTime"]
+            .as_i64()
+            .ok_or_else(|| anyhow!("Invalid serverTime"))?;
+        let local_time =
+            sent.timestamp_millis() + (received.timestamp_millis() - sent.timestamp_millis()) / 2;
+        let offset = server_time - local_time;
+
+        if offset.abs() > MAX_CLOCK_DRIFT {
+            tracing::warn!(
+                target: "alerts",
+                "Local clock is {}ms off the exchange time, check the NTP sync",
+                -offset
+            );
+        }
+        clock::set_offset_ms(Exchange::Binance, offset);
+        Ok(offset)
+    }
+}
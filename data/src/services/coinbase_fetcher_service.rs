@@ -0,0 +1,258 @@
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Duration as DurationChrono, Utc};
+use reqwest::StatusCode;
+use rust_decimal::Decimal;
+use serde_json::Value;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use crate::{
+    models::{
+        market_data::MarketData,
+        timeframe::{ContractType, TimeFrame},
+    },
+    repositories::{
+        market_data_repository::MarketDataRepository, timeframe_repository::TimeFrameRepository,
+    },
+    utils::{
+        helper::Helper,
+        http::{self, Endpoint},
+        retry::RetryPolicy,
+    },
+};
+
+use super::database_service::DatabaseService;
+use super::exchange_service::Exchange;
+use super::market_data_fetcher_service::MarketDataFetcherError;
+
+// Coinbase rejects requests without a User-Agent
+const USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
+const COINBASE_FETCH_LIMIT: i64 = 300;
+
+// Candles of a Coinbase Exchange spot product (symbol is the product id,
+// e.g. BTC-USD). Coinbase only serves 1m, 5m, 15m, 1h, 6h and 1d candles,
+// newest first, and does not report trade counts, so `trades` is stored as 0.
+pub struct CoinbaseMarketDataFetcher {
+    client: reqwest::Client,
+    symbol: String,
+    granularity: i64, // in seconds
+    pub timeframe: TimeFrame,
+    lookback_days: u32,
+    market_data_repository: Arc<MarketDataRepository>,
+    timeframe_repository: TimeFrameRepository,
+    dry_run: bool,
+}
+
+impl CoinbaseMarketDataFetcher {
+    pub async fn new(
+        symbol: String,
+        contract_type: ContractType,
+        interval: String,
+        lookback_days: u32,
+        dry_run: bool,
+    ) -> Result<Self> {
+        if contract_type != ContractType::Spot {
+            return Err(anyhow!("Coinbase only supports SPOT pairs"));
+        }
+        let granularity = match Helper::interval_to_minutes(&interval) {
+            Some(minutes @ (1 | 5 | 15 | 60 | 360 | 1440)) => i64::from(minutes) * 60,
+            _ => return Err(anyhow!("Coinbase has no {} candles", interval)),
+        };
+
+        let database = DatabaseService::new().await?;
+        let timeframe_repository = TimeFrameRepository::new(database.client);
+
+        let database = DatabaseService::new().await?;
+        let market_data_repository = MarketDataRepository::new(database.client);
+
+        let timeframe = if dry_run {
+            timeframe_repository
+                .find_or_new(symbol.clone(), contract_type, interval)
+                .await?
+        } else {
+            timeframe_repository
+                .find_or_create(symbol.clone(), contract_type, interval)
+                .await?
+        };
+
+        Ok(CoinbaseMarketDataFetcher {
+            client: http::client_builder().user_agent(USER_AGENT).build()?,
+            symbol,
+            granularity,
+            timeframe,
+            lookback_days,
+            market_data_repository: Arc::new(market_data_repository),
+            timeframe_repository,
+            dry_run,
+        })
+    }
+
+    // On a dry run the candles are printed instead of inserted
+    async fn save_batch(&self, batch: &[MarketData]) -> Result<usize, MarketDataFetcherError> {
+        if self.dry_run {
+            batch.iter().for_each(|candle| println!("{}", candle));
+            return Ok(batch.len());
+        }
+        self.market_data_repository
+            .create_batch(batch)
+            .await
+            .map(|ids| ids.len())
+            .map_err(|e| MarketDataFetcherError::Api {
+                status: StatusCode::INTERNAL_SERVER_ERROR,
+                body: e.to_string(),
+            })
+    }
+
+    // Walks forward in windows of COINBASE_FETCH_LIMIT candles, keeping the
+    // closed ones only
+    async fn fetch_market_data(
+        &self,
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+    ) -> Result<usize, MarketDataFetcherError> {
+        let window = DurationChrono::seconds(self.granularity * COINBASE_FETCH_LIMIT);
+        let mut window_start = start_time;
+        let mut inserted_count = 0;
+
+        while window_start < end_time {
+            let window_end = (window_start + window).min(end_time);
+            let params = [
+                ("granularity", self.granularity.to_string()),
+                ("start", window_start.to_rfc3339()),
+                ("end", window_end.to_rfc3339()),
+            ];
+            let data = self.fetch_with_retry(&params).await?;
+            let candles = data.as_array().ok_or(MarketDataFetcherError::Api {
+                status: StatusCode::BAD_REQUEST,
+                body: "Invalid Coinbase response format".to_string(),
+            })?;
+
+            let mut market_data_batch = Vec::with_capacity(candles.len());
+            for candle in candles.iter().rev() {
+                let market_data = self.parse_candle(candle)?;
+                if market_data.open_time >= start_time && market_data.close_time < end_time {
+                    market_data_batch.push(market_data);
+                }
+            }
+
+            if !market_data_batch.is_empty() {
+                self.save_batch(&market_data_batch).await?;
+                inserted_count += market_data_batch.len();
+            }
+            window_start = window_end + DurationChrono::seconds(1);
+        }
+
+        if inserted_count == 0 {
+            return Err(MarketDataFetcherError::NoDataFound);
+        }
+        tracing::info!(
+            "Inserted {} Coinbase elements for {} {}",
+            inserted_count,
+            self.symbol,
+            Helper::minutes_to_interval(self.timeframe.interval_minutes)
+        );
+        Ok(inserted_count)
+    }
+
+    async fn fetch_with_retry(
+        &self,
+        params: &[(&str, String)],
+    ) -> Result<Value, MarketDataFetcherError> {
+        let url = format!(
+            "{}products/{}/candles",
+            Endpoint::CoinbaseApi.url(),
+            self.symbol
+        );
+        let response = RetryPolicy::DEFAULT
+            .send(|| self.client.get(&url).query(&params))
+            .await
+            .map_err(MarketDataFetcherError::Request)?;
+
+        match response.error_for_status() {
+            Ok(resp) => resp.json().await.map_err(MarketDataFetcherError::Json),
+            Err(err) => {
+                let status = err.status().unwrap_or_default();
+                let body = err.to_string();
+                tracing::error!(%status, %body, "Coinbase API request failed");
+                Err(MarketDataFetcherError::Api { status, body })
+            }
+        }
+    }
+
+    // [time, low, high, open, close, volume], time in seconds and prices as
+    // JSON numbers
+    fn parse_candle(&self, candle: &Value) -> Result<MarketData, MarketDataFetcherError> {
+        let parse_decimal =
+            |index: usize, field: &str| -> Result<Decimal, MarketDataFetcherError> {
+                candle[index]
+                    .as_number()
+                    .map(|n| n.to_string())
+                    .and_then(|s| {
+                        Decimal::from_str(&s)
+                            .or_else(|_| Decimal::from_scientific(&s))
+                            .ok()
+                    })
+                    .ok_or_else(|| MarketDataFetcherError::Api {
+                        status: StatusCode::BAD_REQUEST,
+                        body: format!("Invalid Coinbase {} decimal", field),
+                    })
+            };
+
+        let open_time = candle[0]
+            .as_i64()
+            .and_then(|seconds| DateTime::<Utc>::from_timestamp(seconds, 0))
+            .ok_or_else(|| MarketDataFetcherError::Api {
+                status: StatusCode::BAD_REQUEST,
+                body: "Invalid Coinbase candle timestamp".to_string(),
+            })?;
+        let close_time =
+            open_time + DurationChrono::seconds(self.granularity) - DurationChrono::milliseconds(1);
+
+        Ok(MarketData::new(
+            self.timeframe.id,
+            self.symbol.clone(),
+            ContractType::Spot.to_string(),
+            open_time,
+            close_time,
+            parse_decimal(3, "open")?,
+            parse_decimal(4, "close")?,
+            parse_decimal(2, "high")?,
+            parse_decimal(1, "low")?,
+            parse_decimal(5, "volume")?,
+            0,
+        ))
+    }
+}
+
+impl Exchange for CoinbaseMarketDataFetcher {
+    const NAME: &'static str = "Coinbase";
+
+    fn timeframe(&self) -> &TimeFrame {
+        &self.timeframe
+    }
+
+    fn lookback_days(&self) -> u32 {
+        self.lookback_days
+    }
+
+    fn market_data_repository(&self) -> &MarketDataRepository {
+        &self.market_data_repository
+    }
+
+    fn timeframe_repository(&self) -> &TimeFrameRepository {
+        &self.timeframe_repository
+    }
+
+    async fn fetch_window(
+        &self,
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+    ) -> Result<usize, MarketDataFetcherError> {
+        self.fetch_market_data(start_time, end_time).await
+    }
+
+    // Coinbase takes whole seconds
+    fn recent_start(&self, latest_open_time: DateTime<Utc>) -> DateTime<Utc> {
+        latest_open_time + DurationChrono::seconds(1)
+    }
+}
@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use thiserror::Error;
 
 use crate::models::timeframe::{ContractType, Interval};
@@ -7,6 +8,10 @@ use crate::models::timeframe::{ContractType, Interval};
 pub enum ConfigError {
     #[error("Invalid interval format: {0}")]
     InvalidInterval(String),
+    #[error("Invalid contract type: {0}")]
+    InvalidContractType(String),
+    #[error("Invalid filter, expected <column> <comparison> <value>: {0}")]
+    InvalidFilter(String),
     #[error("YAML parsing error: {0}")]
     YamlError(#[from] serde_yaml::Error),
 }
@@ -19,6 +24,15 @@ pub struct Config {
 #[derive(Debug, Serialize, Deserialize)]
 pub struct TradingConfig {
     pub lookback_days: u32,
+    pub fetch_limit: Option<i32>,
+    pub archive_after_days: Option<u32>,
+    pub prediction_horizon_candles: Option<u32>,
+    pub stream_klines: Option<bool>,
+    pub account_sync_interval_seconds: Option<u64>,
+    pub csv_import: Option<CsvImportConfig>,
+    pub http: Option<HttpConfig>,
+    pub limits: Option<LimitsConfig>,
+    pub storage: Option<StorageConfig>,
     pub pairs: Vec<PairConfig>,
 }
 
@@ -26,13 +40,123 @@ pub struct TradingConfig {
 pub struct PairConfig {
     pub symbol: String,
     pub contract_type: ContractType,
+    pub exchange: Option<Exchange>,
     pub timeframes: Vec<TimeframeConfig>,
+    pub order_book: Option<OrderBookConfig>,
+    pub trade_flow: Option<bool>,
+    pub liquidations: Option<bool>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Exchange {
+    #[default]
+    #[serde(rename = "BINANCE")]
+    Binance,
+    #[serde(rename = "OKX")]
+    Okx,
+    #[serde(rename = "COINBASE")]
+    Coinbase,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct TimeframeConfig {
     #[serde(with = "interval_string")]
     pub interval: Interval,
+    pub alerts: Option<AlertConfig>,
+    pub labeling: Option<LabelingConfig>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AlertConfig {
+    pub return_sigma: f64,
+    pub volume_sigma: f64,
+    pub spread_sigma: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct OrderBookConfig {
+    pub snapshot_interval_seconds: u64,
+    pub depth_levels: usize,
+}
+
+// Exchange endpoints and HTTP client settings, each overridable from the
+// environment (see utils::http)
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct HttpConfig {
+    pub timeout_seconds: Option<u64>,
+    pub proxy: Option<String>, // http://, https:// (not for the streams) or socks5:// URL
+    pub binance_futures_api_url: Option<String>,
+    pub binance_spot_api_url: Option<String>,
+    pub binance_futures_stream_url: Option<String>,
+    pub binance_spot_stream_url: Option<String>,
+    pub okx_api_url: Option<String>,
+    pub coinbase_api_url: Option<String>,
+}
+
+// Concurrent fetch jobs and request pacing, without limits set requests are
+// only slowed down by the Binance weight pacing and the retries
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct LimitsConfig {
+    pub max_concurrent_tasks: Option<usize>,
+    #[serde(default)]
+    pub exchanges: HashMap<Exchange, ExchangeLimitsConfig>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ExchangeLimitsConfig {
+    pub max_concurrent_tasks: Option<usize>,
+    pub requests_per_second: Option<f64>,
+    #[serde(default)]
+    pub endpoints: HashMap<String, f64>, // requests/sec by API path, e.g. "fapi/v1/klines"
+}
+
+// Bounds on the stored candles, applied daily and with --storage-policies
+// --apply
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct StorageConfig {
+    pub raw_retention_days: Option<u32>, // 1m candles and archives past this are deleted
+}
+
+// Zero-based columns of the CSV files loaded with --import-csv, the Binance
+// kline export layout by default
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct CsvImportConfig {
+    pub delimiter: char,
+    pub open_time: usize,
+    pub open: usize,
+    pub high: usize,
+    pub low: usize,
+    pub close: usize,
+    pub volume: usize,
+    pub trades: Option<usize>,
+    pub taker_buy_volume: Option<usize>,
+    pub taker_buy_quote_volume: Option<usize>,
+}
+
+impl Default for CsvImportConfig {
+    fn default() -> Self {
+        Self {
+            delimiter: ',',
+            open_time: 0,
+            open: 1,
+            high: 2,
+            low: 3,
+            close: 4,
+            volume: 5,
+            trades: Some(8),
+            taker_buy_volume: Some(9),
+            taker_buy_quote_volume: Some(10),
+        }
+    }
+}
+
+// Triple-barrier bounds, targets in % of the entry close
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LabelingConfig {
+    pub profit_target: f64,
+    pub stop_loss: f64,
+    pub max_holding_candles: u32,
 }
 
 mod interval_string {
@@ -0,0 +1,155 @@
+use anyhow::{anyhow, Result};
+use rust_decimal::Decimal;
+use serde_json::Value;
+use std::str::FromStr;
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tokio::time::interval;
+
+use crate::{
+    models::symbol_info::SymbolInfo,
+    repositories::symbol_repository::SymbolRepository,
+    utils::{
+        http::{self, Endpoint},
+        retry::RetryPolicy,
+    },
+};
+
+use super::binance_account_service::BinanceAccountClient;
+use super::database_service::DatabaseService;
+
+const EXCHANGE_INFO_API_PATH: &str = "fapi/v1/exchangeInfo";
+const EXCHANGE_INFO_SYNC_INTERVAL: u64 = 86400; // 1 day in seconds
+
+// Caches the trading rules of the USD-M futures symbols (tick and step sizes,
+// minimum quantity and notional) in the Symbols table, so prices and
+// quantities can be rounded before orders are sent. The leverage brackets are
+// signed, they are only cached when the API keys are set.
+pub struct ExchangeInfoSync {
+    client: reqwest::Client,
+    account_client: Option<BinanceAccountClient>,
+    symbol_repository: SymbolRepository,
+}
+
+impl ExchangeInfoSync {
+    pub async fn new() -> Result<Self> {
+        let database = DatabaseService::new().await?;
+
+        Ok(ExchangeInfoSync {
+            client: http::client(),
+            account_client: BinanceAccountClient::from_env().ok(),
+            symbol_repository: SymbolRepository::new(database.client),
+        })
+    }
+
+    pub async fn run(&mut self, mut shutdown: broadcast::Receiver<()>) {
+        let mut ticker = interval(Duration::from_secs(EXCHANGE_INFO_SYNC_INTERVAL));
+        loop {
+            tokio::select! {
+                _ = ticker.tick() => {
+                    if let Err(e) = self.sync().await {
+                        tracing::error!("Exchange info sync failed: {}", e);
+                    }
+                }
+                _ = shutdown.recv() => return,
+            }
+        }
+    }
+
+    // Returns the number of symbols cached
+    pub async fn sync(&mut self) -> Result<u64> {
+        let url = format!(
+            "{}{}",
+            Endpoint::BinanceFuturesApi.url(),
+            EXCHANGE_INFO_API_PATH
+        );
+        let payload: Value = RetryPolicy::DEFAULT
+            .send(|| self.client.get(&url))
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        let symbols = payload["symbols"]
+            .as_array()
+            .ok_or_else(|| anyhow!("Invalid exchangeInfo response format"))?
+            .iter()
+            .map(Self::parse_symbol)
+            .collect::<Result<Vec<_>>>()?;
+        let upserted = self.symbol_repository.upsert_batch(&symbols).await?;
+
+        if let Some(account_client) = &self.account_client {
+            let brackets = account_client.fetch_leverage_brackets().await?;
+            self.symbol_repository
+                .replace_leverage_brackets(&brackets)
+                .await?;
+            tracing::info!(
+                "Cached {} symbols and {} leverage brackets",
+                upserted,
+                brackets.len()
+            );
+        } else {
+            tracing::info!(
+                "Cached {} symbols, no API key for the leverage brackets",
+                upserted
+            );
+        }
+
+        Ok(upserted)
+    }
+
+    fn parse_symbol(symbol: &Value) -> Result<SymbolInfo> {
+        let name = Self::parse_string(symbol, "symbol")?;
+        let filter = |filter_type: &str| -> Result<&Value> {
+            symbol["filters"]
+                .as_array()
+                .and_then(|filters| {
+                    filters
+                        .iter()
+                        .find(|f| f["filterType"].as_str() == Some(filter_type))
+                })
+                .ok_or_else(|| anyhow!("No {} filter for {}", filter_type, name))
+        };
+        let lot_size = filter("LOT_SIZE")?;
+
+        Ok(SymbolInfo {
+            symbol: name.clone(),
+            pair: Self::parse_string(symbol, "pair")?,
+            contract_type: Self::parse_string(symbol, "contractType")?,
+            status: Self::parse_string(symbol, "status")?,
+            base_asset: Self::parse_string(symbol, "baseAsset")?,
+            quote_asset: Self::parse_string(symbol, "quoteAsset")?,
+            price_precision: Self::parse_precision(symbol, "pricePrecision")?,
+            quantity_precision: Self::parse_precision(symbol, "quantityPrecision")?,
+            tick_size: Self::parse_decimal(filter("PRICE_FILTER")?, "tickSize")?,
+            step_size: Self::parse_decimal(lot_size, "stepSize")?,
+            min_quantity: Self::parse_decimal(lot_size, "minQty")?,
+            // Not every contract has a minimum notional
+            min_notional: filter("MIN_NOTIONAL")
+                .ok()
+                .map(|f| Self::parse_decimal(f, "notional"))
+                .transpose()?,
+        })
+    }
+
+    fn parse_string(value: &Value, field: &str) -> Result<String> {
+        value[field]
+            .as_str()
+            .map(str::to_string)
+            .ok_or_else(|| anyhow!("Invalid {} field", field))
+    }
+
+    fn parse_decimal(value: &Value, field: &str) -> Result<Decimal> {
+        value[field]
+            .as_str()
+            .and_then(|s| Decimal::from_str(s).ok())
+            .ok_or_else(|| anyhow!("Invalid {} decimal", field))
+    }
+
+    fn parse_precision(value: &Value, field: &str) -> Result<i16> {
+        value[field]
+            .as_i64()
+            .and_then(|precision| i16::try_from(precision).ok())
+            .ok_or_else(|| anyhow!("Invalid {} field", field))
+    }
+}
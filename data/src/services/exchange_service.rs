@@ -0,0 +1,196 @@
+use chrono::{DateTime, Duration as DurationChrono, Utc};
+use reqwest::StatusCode;
+use tokio::time::sleep;
+
+use crate::{
+    models::timeframe::TimeFrame,
+    repositories::{
+        market_data_repository::MarketDataRepository, timeframe_repository::TimeFrameRepository,
+    },
+    utils::helper::Helper,
+};
+
+use super::market_data_fetcher_service::MarketDataFetcherError;
+
+// Candles per saved step of the initial fetch
+const INITIALIZATION_CHUNK_CANDLES: i64 = 10_000;
+const RECENT_DATA_MAX_RETRIES: i32 = 3;
+const RECENT_DATA_RETRY_DELAY: u64 = 2000; // 2 seconds in milliseconds
+
+// Candles of one pair and timeframe served by an exchange API. Implementors
+// fetch a window of candles, the initial fetch, the gap backfill and the
+// recent fetch are written once on top of it.
+pub trait Exchange {
+    // Exchange name in logs
+    const NAME: &'static str;
+
+    fn timeframe(&self) -> &TimeFrame;
+
+    fn lookback_days(&self) -> u32;
+
+    fn market_data_repository(&self) -> &MarketDataRepository;
+
+    fn timeframe_repository(&self) -> &TimeFrameRepository;
+
+    // Fetches and saves the closed candles opened from `start_time` to
+    // `end_time`, NoDataFound when the exchange has none
+    async fn fetch_window(
+        &self,
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+    ) -> Result<usize, MarketDataFetcherError>;
+
+    // Runs after each chunk of the initial fetch, before the chunk is saved
+    // as done
+    async fn complete_initialization_chunk(
+        &self,
+        _start_time: DateTime<Utc>,
+        _end_time: DateTime<Utc>,
+    ) -> Result<(), MarketDataFetcherError> {
+        Ok(())
+    }
+
+    // Fetches the lookback window chunk by chunk and saves the end of every
+    // completed chunk, so an interrupted initialization resumes where it
+    // stopped. A lookback reaching further back than the saved window starts over.
+    async fn initialize_market_data(&self) -> Result<usize, MarketDataFetcherError> {
+        let timeframe = self.timeframe();
+        let end_time = Utc::now();
+        let start_time = end_time - DurationChrono::days(self.lookback_days().into());
+        let progress = self
+            .timeframe_repository()
+            .find_initialization_progress(&timeframe.id)
+            .await
+            .map_err(|e| MarketDataFetcherError::Api {
+                status: StatusCode::INTERNAL_SERVER_ERROR,
+                body: e.to_string(),
+            })?;
+
+        let (initialized_from, mut chunk_start) = match progress {
+            Some((from, until)) if from <= start_time => (from, until.max(start_time)),
+            _ => (start_time, start_time),
+        };
+        let resumed = chunk_start > start_time;
+        if resumed {
+            tracing::info!(
+                "Resuming initialization of {} {} {} from {}",
+                timeframe.symbol,
+                Helper::minutes_to_interval(timeframe.interval_minutes),
+                timeframe.contract_type,
+                chunk_start
+            );
+        }
+
+        let chunk = DurationChrono::minutes(
+            i64::from(timeframe.interval_minutes) * INITIALIZATION_CHUNK_CANDLES,
+        );
+        let mut inserted_count = 0;
+        while chunk_start < end_time {
+            let chunk_end = (chunk_start + chunk).min(end_time);
+            match self.fetch_window(chunk_start, chunk_end).await {
+                Ok(count) => inserted_count += count,
+                // Before the listing of the pair
+                Err(MarketDataFetcherError::NoDataFound) => {}
+                Err(e) => return Err(e),
+            }
+            self.complete_initialization_chunk(chunk_start, chunk_end)
+                .await?;
+            self.timeframe_repository()
+                .save_initialization_progress(&timeframe.id, initialized_from, chunk_end)
+                .await
+                .map_err(|e| MarketDataFetcherError::Api {
+                    status: StatusCode::INTERNAL_SERVER_ERROR,
+                    body: e.to_string(),
+                })?;
+            chunk_start = chunk_end;
+        }
+
+        if inserted_count == 0 && !resumed {
+            return Err(MarketDataFetcherError::NoDataFound);
+        }
+        Ok(inserted_count)
+    }
+
+    // Refetches the candles missing between the stored ones opened since
+    // `since`, stretches the exchange has no candles for are left as they are
+    async fn backfill_gaps(&self, since: DateTime<Utc>) -> Result<usize, MarketDataFetcherError> {
+        let timeframe = self.timeframe();
+        let gaps = self
+            .market_data_repository()
+            .find_gaps(&timeframe.id, timeframe.interval_minutes, since)
+            .await
+            .map_err(|e| MarketDataFetcherError::Api {
+                status: StatusCode::INTERNAL_SERVER_ERROR,
+                body: e.to_string(),
+            })?;
+
+        let mut inserted_count = 0;
+        for (gap_start, gap_end) in gaps {
+            match self.fetch_window(gap_start, gap_end).await {
+                Ok(count) => inserted_count += count,
+                Err(MarketDataFetcherError::NoDataFound) => tracing::warn!(
+                    "No {} candles for {} {} between {} and {}",
+                    Self::NAME,
+                    timeframe.symbol,
+                    Helper::minutes_to_interval(timeframe.interval_minutes),
+                    gap_start,
+                    gap_end
+                ),
+                Err(e) => return Err(e),
+            }
+        }
+
+        Ok(inserted_count)
+    }
+
+    // Start of the recent fetch after the latest stored candle
+    fn recent_start(&self, latest_open_time: DateTime<Utc>) -> DateTime<Utc> {
+        latest_open_time + DurationChrono::milliseconds(1)
+    }
+
+    // Window of the recent fetch, exchanges revising their last candles
+    // overwrite the stored ones in it
+    async fn fetch_recent_window(
+        &self,
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+    ) -> Result<usize, MarketDataFetcherError> {
+        self.fetch_window(start_time, end_time).await
+    }
+
+    // Candles closed since the latest stored one, the last day on an empty
+    // timeframe. A candle closing right now may not be served yet, the
+    // fetch is retried a few times before giving up.
+    async fn fetch_recent_market_data(&self) -> Result<usize, MarketDataFetcherError> {
+        let latest_record = self
+            .market_data_repository()
+            .find_latest_by_timeframe(&self.timeframe().id)
+            .await
+            .map_err(|e| MarketDataFetcherError::Api {
+                status: StatusCode::INTERNAL_SERVER_ERROR,
+                body: e.to_string(),
+            })?;
+
+        let start_time = match latest_record {
+            Some(record) => self.recent_start(record.open_time),
+            None => Utc::now() - DurationChrono::hours(24),
+        };
+
+        let mut retries = 0;
+        loop {
+            match self.fetch_recent_window(start_time, Utc::now()).await {
+                Err(MarketDataFetcherError::NoDataFound) if retries < RECENT_DATA_MAX_RETRIES => {
+                    retries += 1;
+                    tracing::warn!(
+                        "No recent {} data found, retry {} of {}",
+                        Self::NAME,
+                        retries,
+                        RECENT_DATA_MAX_RETRIES
+                    );
+                    sleep(std::time::Duration::from_millis(RECENT_DATA_RETRY_DELAY)).await;
+                }
+                result => return result,
+            }
+        }
+    }
+}
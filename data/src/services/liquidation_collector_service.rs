@@ -0,0 +1,119 @@
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use futures_util::StreamExt;
+use rust_decimal::Decimal;
+use serde_json::Value;
+use std::str::FromStr;
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tokio::time::sleep;
+use tokio_tungstenite::tungstenite::Message;
+
+use crate::{
+    models::liquidation::Liquidation,
+    repositories::liquidation_repository::LiquidationRepository,
+    utils::http::{self, Endpoint},
+};
+
+use super::database_service::DatabaseService;
+
+const RECONNECT_DELAY: u64 = 1000; // 1 second in milliseconds
+const MAX_RECONNECT_DELAY: u64 = 60000; // 1 minute in milliseconds
+
+// Stores the liquidation orders of a perpetual pair from the forceOrder
+// stream. Binance pushes at most the latest liquidation of each second, so
+// the stored volume is a lower bound.
+pub struct LiquidationCollector {
+    symbol: String,
+    liquidation_repository: LiquidationRepository,
+}
+
+impl LiquidationCollector {
+    pub async fn new(symbol: String) -> Result<Self> {
+        let database = DatabaseService::new().await?;
+
+        Ok(LiquidationCollector {
+            symbol,
+            liquidation_repository: LiquidationRepository::new(database.client),
+        })
+    }
+
+    fn stream_url(&self) -> String {
+        format!(
+            "{}{}@forceOrder",
+            Endpoint::BinanceFuturesStream.url(),
+            self.symbol.to_lowercase()
+        )
+    }
+
+    pub async fn run(&self, mut shutdown: broadcast::Receiver<()>) {
+        let mut delay = RECONNECT_DELAY;
+        loop {
+            tokio::select! {
+                result = self.collect() => match result {
+                    Ok(()) => {
+                        tracing::warn!("Liquidation stream closed for {}", self.stream_url());
+                        delay = RECONNECT_DELAY;
+                    }
+                    Err(e) => {
+                        tracing::error!("Liquidation stream failed for {}: {}", self.stream_url(), e);
+                        delay = (delay * 2).min(MAX_RECONNECT_DELAY);
+                    }
+                },
+                _ = shutdown.recv() => return,
+            }
+
+            tokio::select! {
+                _ = sleep(Duration::from_millis(delay)) => {}
+                _ = shutdown.recv() => return,
+            }
+        }
+    }
+
+    async fn collect(&self) -> Result<()> {
+        let mut socket = http::connect_stream(&self.stream_url()).await?;
+        tracing::info!("Connected to {}", self.stream_url());
+
+        while let Some(message) = socket.next().await {
+            let text = match message? {
+                Message::Text(text) => text,
+                Message::Close(_) => break,
+                _ => continue,
+            };
+
+            let payload: Value = serde_json::from_str(&text)?;
+            self.liquidation_repository
+                .create(&Self::parse_order(&payload["o"])?)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    fn parse_order(order: &Value) -> Result<Liquidation> {
+        let parse_decimal = |field: &str| -> Result<Decimal> {
+            order[field]
+                .as_str()
+                .and_then(|s| Decimal::from_str(s).ok())
+                .ok_or_else(|| anyhow!("Invalid liquidation {} decimal", field))
+        };
+        let parse_string = |field: &str| -> Result<String> {
+            order[field]
+                .as_str()
+                .map(str::to_string)
+                .ok_or_else(|| anyhow!("Invalid liquidation {}", field))
+        };
+
+        Ok(Liquidation {
+            symbol: parse_string("s")?,
+            side: parse_string("S")?,
+            price: parse_decimal("p")?,
+            average_price: parse_decimal("ap")?,
+            quantity: parse_decimal("z")?,
+            trade_time: order["T"]
+                .as_i64()
+                .and_then(DateTime::<Utc>::from_timestamp_millis)
+                .ok_or_else(|| anyhow!("Invalid liquidation trade time"))?,
+        })
+    }
+}
@@ -0,0 +1,332 @@
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+use std::sync::Arc;
+
+use crate::{
+    models::{
+        market_data::MarketData,
+        timeframe::{ContractType, TimeFrame},
+    },
+    repositories::{
+        market_data_repository::MarketDataRepository, timeframe_repository::TimeFrameRepository,
+    },
+    utils::helper::Helper,
+};
+
+use super::database_service::DatabaseService;
+
+const DAY_MINUTES: i32 = 24 * 60;
+const WEEK_MINUTES: i32 = 7 * DAY_MINUTES;
+// Weekly candles open on Monday 00:00 UTC, 4 days after the epoch
+const WEEK_OFFSET_MINUTES: i64 = 4 * DAY_MINUTES as i64;
+
+// Builds a higher timeframe from the stored candles of a lower one instead of
+// fetching it from the API
+pub struct MarketDataAggregator {
+    market_data_repository: Arc<MarketDataRepository>,
+    pub timeframe: TimeFrame,
+    source: TimeFrame,
+    lookback_days: u32,
+}
+
+impl MarketDataAggregator {
+    pub async fn new(
+        symbol: String,
+        contract_type: ContractType,
+        interval: String,
+        source_interval: String,
+        lookback_days: u32,
+    ) -> Result<Self> {
+        let database = DatabaseService::new().await?;
+        let timeframe_repository = TimeFrameRepository::new(database.client);
+
+        let database = DatabaseService::new().await?;
+        let market_data_repository = MarketDataRepository::new(database.client);
+
+        let timeframe = timeframe_repository
+            .find_or_create(symbol.clone(), contract_type.clone(), interval)
+            .await?;
+        let source = timeframe_repository
+            .find_or_create(symbol, contract_type, source_interval)
+            .await?;
+
+        Ok(MarketDataAggregator {
+            market_data_repository: Arc::new(market_data_repository),
+            timeframe,
+            source,
+            lookback_days,
+        })
+    }
+
+    // 3d candles are anchored by the exchange rather than the epoch, so they
+    // can't be rebuilt with exact boundaries and keep being fetched
+    pub fn can_aggregate(interval_minutes: i32, source_minutes: i32) -> bool {
+        interval_minutes > source_minutes
+            && interval_minutes % source_minutes == 0
+            && (interval_minutes <= DAY_MINUTES || interval_minutes == WEEK_MINUTES)
+    }
+
+    fn bucket_start(&self, time: DateTime<Utc>) -> DateTime<Utc> {
+        Self::interval_bucket_start(self.timeframe.interval_minutes, time)
+    }
+
+    // Open time of the `interval_minutes` bucket containing `time`, buckets
+    // are aligned on the epoch and weeks on Monday
+    fn interval_bucket_start(interval_minutes: i32, time: DateTime<Utc>) -> DateTime<Utc> {
+        let interval = interval_minutes as i64;
+        let offset = if interval_minutes == WEEK_MINUTES {
+            WEEK_OFFSET_MINUTES
+        } else {
+            0
+        };
+        let minutes = time.timestamp() / 60 - offset;
+        DateTime::<Utc>::from_timestamp((minutes - minutes.rem_euclid(interval) + offset) * 60, 0)
+            .unwrap_or(time)
+    }
+
+    pub async fn aggregate_market_data(&self) -> Result<usize> {
+        let interval = Duration::minutes(self.timeframe.interval_minutes.into());
+        let latest = self
+            .market_data_repository
+            .find_latest_by_timeframe(&self.timeframe.id)
+            .await?;
+
+        let from = match latest {
+            // Rebuilt along with the new buckets, the source fetch revises
+            // its latest candles after they close
+            Some(record) => record.open_time,
+            None => {
+                // First full bucket inside the lookback period
+                let start = Utc::now() - Duration::days(self.lookback_days.into());
+                let bucket = self.bucket_start(start);
+                if bucket < start {
+                    bucket + interval
+                } else {
+                    bucket
+                }
+            }
+        };
+        // The bucket containing now is still open
+        let to = self.bucket_start(Utc::now());
+        if from >= to {
+            return Ok(0);
+        }
+
+        let aggregated = self.aggregate_between(from, to).await?;
+        self.save(&aggregated).await
+    }
+
+    // Rebuilds the buckets missing between the stored ones opened since
+    // `since`, skipped while their source candles were incomplete. Buckets
+    // still incomplete once the source gaps are backfilled stay missing.
+    pub async fn backfill_gaps(&self, since: DateTime<Utc>) -> Result<usize> {
+        let gaps = self
+            .market_data_repository
+            .find_gaps(&self.timeframe.id, self.timeframe.interval_minutes, since)
+            .await?;
+
+        let mut aggregated = Vec::new();
+        for (gap_start, gap_end) in gaps {
+            aggregated.extend(self.aggregate_between(gap_start, gap_end).await?);
+        }
+        self.save(&aggregated).await
+    }
+
+    // Complete buckets opened from `from` to `to` excluded
+    async fn aggregate_between(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<MarketData>> {
+        let candles = self
+            .market_data_repository
+            .find_candles_between(
+                &self.source.id,
+                &self.timeframe.symbol,
+                &self.timeframe.contract_type.to_string(),
+                from,
+                to,
+            )
+            .await?;
+
+        let expected = (self.timeframe.interval_minutes / self.source.interval_minutes) as usize;
+        let mut aggregated = Vec::new();
+        let buckets: Vec<&[MarketData]> = candles
+            .chunk_by(|a, b| self.bucket_start(a.open_time) == self.bucket_start(b.open_time))
+            .collect();
+        for (i, bucket) in buckets.iter().enumerate() {
+            let open_time = self.bucket_start(bucket[0].open_time);
+            if bucket.len() != expected {
+                // The newest bucket may still be filling in and is retried on the next run
+                if i + 1 == buckets.len() {
+                    break;
+                }
+                tracing::warn!(
+                    "Skipping {} {} candle at {}: {} of {} {} candles available",
+                    self.timeframe.symbol,
+                    Helper::minutes_to_interval(self.timeframe.interval_minutes),
+                    open_time,
+                    bucket.len(),
+                    expected,
+                    Helper::minutes_to_interval(self.source.interval_minutes)
+                );
+                continue;
+            }
+            aggregated.push(self.aggregate_bucket(open_time, bucket));
+        }
+
+        Ok(aggregated)
+    }
+
+    // Stored buckets rebuilt with other values are overwritten and analyzed
+    // again
+    async fn save(&self, aggregated: &[MarketData]) -> Result<usize> {
+        if aggregated.is_empty() {
+            return Ok(0);
+        }
+
+        let inserted = self.market_data_repository.upsert_batch(aggregated).await?;
+        if inserted.is_empty() {
+            return Ok(0);
+        }
+        tracing::info!(
+            "Aggregated {} {} candles for {} {} from {}",
+            inserted.len(),
+            Helper::minutes_to_interval(self.timeframe.interval_minutes),
+            self.timeframe.symbol,
+            self.timeframe.contract_type,
+            Helper::minutes_to_interval(self.source.interval_minutes)
+        );
+
+        Ok(inserted.len())
+    }
+
+    fn aggregate_bucket(&self, open_time: DateTime<Utc>, bucket: &[MarketData]) -> MarketData {
+        let first = &bucket[0];
+        let last = &bucket[bucket.len() - 1];
+
+        let mut candle = MarketData::new(
+            self.timeframe.id,
+            self.timeframe.symbol.clone(),
+            self.timeframe.contract_type.to_string(),
+            open_time,
+            last.close_time,
+            first.open,
+            last.close,
+            bucket.iter().map(|d| d.high).max().unwrap_or(first.high),
+            bucket.iter().map(|d| d.low).min().unwrap_or(first.low),
+            bucket.iter().map(|d| d.volume).sum(),
+            bucket.iter().map(|d| d.trades).sum(),
+        );
+        // Only known when every source candle reports them
+        candle.taker_buy_volume = bucket.iter().map(|d| d.taker_buy_volume).sum();
+        candle.taker_buy_quote_volume = bucket.iter().map(|d| d.taker_buy_quote_volume).sum();
+        candle
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Datelike, TimeZone, Weekday};
+
+    fn time(day: u32, hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 1, day, hour, minute, 0).unwrap()
+    }
+
+    #[test]
+    fn weeks_open_on_monday_midnight() {
+        // 2024-01-01 is a Monday
+        let monday = time(1, 0, 0);
+        for t in [monday, time(3, 12, 30), time(7, 23, 59), time(1, 0, 1)] {
+            assert_eq!(
+                MarketDataAggregator::interval_bucket_start(WEEK_MINUTES, t),
+                monday,
+                "{}",
+                t
+            );
+        }
+        assert_eq!(
+            MarketDataAggregator::interval_bucket_start(WEEK_MINUTES, time(8, 0, 0)),
+            time(8, 0, 0)
+        );
+
+        // Across the epoch and a year boundary
+        let epoch_week = MarketDataAggregator::interval_bucket_start(
+            WEEK_MINUTES,
+            Utc.with_ymd_and_hms(1970, 1, 1, 0, 0, 0).unwrap(),
+        );
+        assert_eq!(
+            epoch_week,
+            Utc.with_ymd_and_hms(1969, 12, 29, 0, 0, 0).unwrap()
+        );
+        let new_year_week = MarketDataAggregator::interval_bucket_start(
+            WEEK_MINUTES,
+            Utc.with_ymd_and_hms(2025, 1, 1, 9, 0, 0).unwrap(),
+        );
+        assert_eq!(new_year_week.weekday(), Weekday::Mon);
+        assert_eq!(
+            new_year_week,
+            Utc.with_ymd_and_hms(2024, 12, 30, 0, 0, 0).unwrap()
+        );
+    }
+
+    #[test]
+    fn shorter_buckets_are_aligned_on_the_epoch() {
+        let t = time(3, 13, 47);
+
+        assert_eq!(
+            MarketDataAggregator::interval_bucket_start(15, t),
+            time(3, 13, 45)
+        );
+        assert_eq!(
+            MarketDataAggregator::interval_bucket_start(240, t),
+            time(3, 12, 0)
+        );
+        assert_eq!(
+            MarketDataAggregator::interval_bucket_start(DAY_MINUTES, t),
+            time(3, 0, 0)
+        );
+    }
+
+    #[test]
+    fn aggregated_timeframes() {
+        let aggregated = [
+            (5, 1),
+            (15, 5),
+            (60, 15),
+            (240, 60),
+            (DAY_MINUTES, 60),
+            (WEEK_MINUTES, DAY_MINUTES),
+            (WEEK_MINUTES, 1),
+        ];
+        for (interval, source) in aggregated {
+            assert!(
+                MarketDataAggregator::can_aggregate(interval, source),
+                "{} from {}",
+                interval,
+                source
+            );
+        }
+
+        let fetched = [
+            // 3d candles are anchored by the exchange
+            (3 * DAY_MINUTES, DAY_MINUTES),
+            (3 * DAY_MINUTES, 1),
+            // Not a multiple, the same or a smaller interval
+            (5, 3),
+            (60, 60),
+            (15, 60),
+            // Months have no fixed length
+            (30 * DAY_MINUTES, DAY_MINUTES),
+        ];
+        for (interval, source) in fetched {
+            assert!(
+                !MarketDataAggregator::can_aggregate(interval, source),
+                "{} from {}",
+                interval,
+                source
+            );
+        }
+    }
+}
@@ -1,5 +1,10 @@
 use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+use std::collections::{HashMap, VecDeque};
 use std::sync::Arc;
+use std::time::Instant;
+use tokio::sync::{broadcast, mpsc, Mutex};
+use uuid::Uuid;
 
 use rust_decimal::{
     prelude::{FromPrimitive, ToPrimitive},
@@ -7,32 +12,178 @@ use rust_decimal::{
 };
 
 use crate::{
-    models::market_data::{MarketDataIndicatorUpdate, PricePattern},
-    repositories::market_data_repository::MarketDataRepository,
-    utils::helper::Helper,
+    models::market_data::{HourlyActivity, MarketData, MarketDataIndicatorUpdate, PricePattern},
+    repositories::{
+        candle_trade_flow_repository::CandleTradeFlowRepository,
+        funding_rate_repository::FundingRateRepository,
+        liquidation_repository::LiquidationRepository,
+        long_short_ratio_repository::LongShortRatioRepository,
+        market_data_repository::MarketDataRepository,
+        open_interest_repository::OpenInterestRepository,
+        order_book_repository::OrderBookRepository,
+        training_feature_repository::TrainingFeatureRepository,
+    },
+    utils::{calendar::CalendarFeatures, helper::Helper, timing::StageTimings},
 };
 
 use super::database_service::DatabaseService;
 
 const DEFAULT_FECTH_LIMIT: i8 = 100;
 const MANDATORY_RECORD_COUNT: usize = 250;
+const HISTORICAL_WINDOW_SIZE: usize = 250;
+// History of the session and hour of week baselines
+const BASELINE_DAYS: i64 = 28;
+const TIMING_REPORT_INTERVAL: u32 = 500;
 
 pub struct MarketDataAnalyzer {
     market_data_repository: Arc<MarketDataRepository>,
+    order_book_repository: OrderBookRepository,
+    funding_rate_repository: FundingRateRepository,
+    open_interest_repository: OpenInterestRepository,
+    long_short_ratio_repository: LongShortRatioRepository,
+    candle_trade_flow_repository: CandleTradeFlowRepository,
+    liquidation_repository: LiquidationRepository,
+    training_feature_repository: TrainingFeatureRepository,
+    // Held for a whole pass: the cron jobs and the stream analyzer would
+    // otherwise read and write the same unanalyzed candles concurrently
+    pass: Mutex<()>,
 }
 
 impl MarketDataAnalyzer {
+    // Every repository works on the same connection, the analysis queries
+    // run one after the other anyway
     pub async fn new() -> Result<Self> {
         let database = DatabaseService::new().await?;
-        let market_data_repository = MarketDataRepository::new(database.client);
+        let client = Arc::new(Mutex::new(database.client));
 
         Ok(MarketDataAnalyzer {
-            market_data_repository: Arc::new(market_data_repository),
+            market_data_repository: Arc::new(MarketDataRepository::from_shared(client.clone())),
+            order_book_repository: OrderBookRepository::from_shared(client.clone()),
+            funding_rate_repository: FundingRateRepository::from_shared(client.clone()),
+            open_interest_repository: OpenInterestRepository::from_shared(client.clone()),
+            long_short_ratio_repository: LongShortRatioRepository::from_shared(client.clone()),
+            candle_trade_flow_repository: CandleTradeFlowRepository::from_shared(client.clone()),
+            liquidation_repository: LiquidationRepository::from_shared(client.clone()),
+            training_feature_repository: TrainingFeatureRepository::from_shared(client),
+            pass: Mutex::new(()),
         })
     }
 
+    // Analyzes the streamed candles as soon as they are stored instead of on the
+    // next job tick. Signals received during a pass are served by a single
+    // following pass.
+    pub async fn run(
+        &self,
+        mut candle_closed: mpsc::Receiver<()>,
+        mut shutdown: broadcast::Receiver<()>,
+    ) {
+        loop {
+            tokio::select! {
+                signal = candle_closed.recv() => {
+                    if signal.is_none() {
+                        return;
+                    }
+                    if let Err(e) = self.analyze_market_data().await {
+                        tracing::error!("Error analyzing streamed candles: {}", e);
+                    }
+                }
+                _ = shutdown.recv() => return,
+            }
+        }
+    }
+
+    // Returns the window of candles up to `market_data` (newest first), reusing the
+    // window of the previous candle in the same timeframe and only fetching the delta
+    async fn historical_window<'a>(
+        &self,
+        windows: &'a mut HashMap<Uuid, VecDeque<MarketData>>,
+        market_data: &MarketData,
+    ) -> Result<&'a [MarketData]> {
+        let window = windows.entry(market_data.timeframe_id).or_default();
+
+        let interval = market_data.close_time - market_data.open_time + Duration::milliseconds(1);
+        let expected_delta = window.front().and_then(|newest| {
+            let elapsed = market_data.open_time - newest.open_time;
+            let candles = elapsed.num_milliseconds() / interval.num_milliseconds().max(1);
+            (elapsed > Duration::zero() && (candles as usize) < HISTORICAL_WINDOW_SIZE)
+                .then_some((newest.open_time, candles.max(1)))
+        });
+
+        match expected_delta {
+            Some((newest_open_time, candles)) => {
+                let delta = self
+                    .market_data_repository
+                    .get_historical_data(
+                        market_data.timeframe_id,
+                        &market_data.symbol,
+                        &market_data.contract_type,
+                        market_data.open_time,
+                        candles as i32,
+                    )
+                    .await?;
+                for candle in delta
+                    .into_iter()
+                    .rev()
+                    .filter(|d| d.open_time > newest_open_time)
+                {
+                    window.push_front(candle);
+                }
+                window.truncate(HISTORICAL_WINDOW_SIZE);
+            }
+            None => {
+                let historical_data = self
+                    .market_data_repository
+                    .get_historical_data(
+                        market_data.timeframe_id,
+                        &market_data.symbol,
+                        &market_data.contract_type,
+                        market_data.open_time,
+                        HISTORICAL_WINDOW_SIZE as i32,
+                    )
+                    .await?;
+                *window = historical_data.into();
+            }
+        }
+
+        Ok(window.make_contiguous())
+    }
+
+    // Hourly activity of each timeframe of the batch over the baseline days
+    // before its oldest candle until its newest one
+    async fn baseline_history(
+        &self,
+        batch: &[MarketData],
+    ) -> Result<HashMap<Uuid, Vec<HourlyActivity>>> {
+        let mut ranges: HashMap<Uuid, (DateTime<Utc>, DateTime<Utc>)> = HashMap::new();
+        for d in batch {
+            let range = ranges
+                .entry(d.timeframe_id)
+                .or_insert((d.open_time, d.open_time));
+            range.0 = range.0.min(d.open_time);
+            range.1 = range.1.max(d.open_time);
+        }
+
+        let mut history = HashMap::with_capacity(ranges.len());
+        for (timeframe_id, (oldest, newest)) in ranges {
+            let hours = self
+                .market_data_repository
+                .find_hourly_activity(
+                    &timeframe_id,
+                    oldest - Duration::days(BASELINE_DAYS),
+                    newest,
+                )
+                .await?;
+            history.insert(timeframe_id, hours);
+        }
+
+        Ok(history)
+    }
+
     pub async fn analyze_market_data(&self) -> Result<i32> {
+        let _pass = self.pass.lock().await;
         let mut analyzed_count = 0;
+        let mut timings = StageTimings::default();
+        let mut windows: HashMap<Uuid, VecDeque<MarketData>> = HashMap::new();
 
         // Constants for market regime and pattern detection
         const VOLATILITY_THRESHOLD: f64 = 0.02; // 2% daily volatility threshold
@@ -41,7 +192,7 @@ impl MarketDataAnalyzer {
         const SR_THRESHOLD: f64 = 0.02; // 2% threshold for S/R clustering
 
         loop {
-            let unanalyzed_data = self
+            let mut unanalyzed_data = self
                 .market_data_repository
                 .find_market_data_for_analysis(DEFAULT_FECTH_LIMIT, 100)
                 .await?;
@@ -49,59 +200,148 @@ impl MarketDataAnalyzer {
                 break;
             }
 
+            // Chronological order per timeframe so consecutive candles share their window
+            unanalyzed_data.sort_by_key(|d| (d.timeframe_id, d.open_time));
+            let mut updates = Vec::with_capacity(unanalyzed_data.len());
+
+            // State collected next to the klines: order book imbalance, funding,
+            // open interest, long/short ratios, taker flow and liquidations
+            let started = Instant::now();
+            let ids: Vec<Uuid> = unanalyzed_data.iter().map(|d| d.id).collect();
+            let book_imbalances = self
+                .order_book_repository
+                .find_candle_imbalances(&ids)
+                .await?;
+            let funding_rates = self
+                .funding_rate_repository
+                .find_candle_funding_rates(&ids)
+                .await?;
+            let open_interests = self
+                .open_interest_repository
+                .find_candle_open_interest(&ids)
+                .await?;
+            let long_short_ratios = self
+                .long_short_ratio_repository
+                .find_candle_ratios(&ids)
+                .await?;
+            let volume_deltas = self
+                .candle_trade_flow_repository
+                .find_candle_deltas(&ids)
+                .await?;
+            let liquidation_volumes = self
+                .liquidation_repository
+                .find_candle_volumes(&ids)
+                .await?;
+            let baselines = self.baseline_history(&unanalyzed_data).await?;
+            timings.record("db_read", started);
+
             for market_data in unanalyzed_data {
-                let historical_data = self
-                    .market_data_repository
-                    .get_historical_data(
-                        market_data.timeframe_id,
-                        &market_data.symbol,
-                        &market_data.contract_type,
-                        market_data.open_time,
-                        250,
-                    )
-                    .await?;
+                if timings.samples() >= TIMING_REPORT_INTERVAL {
+                    timings.report("Market data analysis");
+                    timings.reset();
+                }
+                timings.tick();
+
+                let started = Instant::now();
+                let historical_data = self.historical_window(&mut windows, &market_data).await?;
+                timings.record("db_read", started);
 
                 let usable = historical_data.len() >= MANDATORY_RECORD_COUNT;
+                let calendar = CalendarFeatures::from_time(market_data.open_time);
+                let book_imbalance = book_imbalances.get(&market_data.id).copied();
+                let funding_rate = funding_rates.get(&market_data.id).copied();
+                let (open_interest, open_interest_change) =
+                    match open_interests.get(&market_data.id) {
+                        Some((at_close, at_open)) => (
+                            Some(*at_close),
+                            at_open.filter(|o| !o.is_zero()).map(|at_open| {
+                                (*at_close - at_open) / at_open * Decimal::ONE_HUNDRED
+                            }),
+                        ),
+                        None => (None, None),
+                    };
+                let (long_short_ratio, top_trader_account_ratio, top_trader_position_ratio) =
+                    long_short_ratios
+                        .get(&market_data.id)
+                        .copied()
+                        .unwrap_or((None, None, None));
+                let (volume_delta, cvd) = match volume_deltas.get(&market_data.id) {
+                    Some((delta, cvd)) => (Some(*delta), *cvd),
+                    None => (None, None),
+                };
+                let (long_liquidation_volume, short_liquidation_volume) =
+                    match liquidation_volumes.get(&market_data.id) {
+                        Some((long, short)) => (Some(*long), Some(*short)),
+                        None => (None, None),
+                    };
+                let basis = market_data
+                    .index_close
+                    .filter(|index| !index.is_zero())
+                    .map(|index| (market_data.close - index) / index * Decimal::ONE_HUNDRED);
+                let taker_buy_ratio = market_data
+                    .taker_buy_volume
+                    .filter(|_| !market_data.volume.is_zero())
+                    .map(|taker_buy_volume| taker_buy_volume / market_data.volume);
 
                 if !usable {
-                    self.market_data_repository
-                        .update_indicators(MarketDataIndicatorUpdate {
-                            id: market_data.id,
-                            rsi_14: None,
-                            macd_line: None,
-                            macd_signal: None,
-                            macd_histogram: None,
-                            bb_upper: None,
-                            bb_middle: None,
-                            bb_lower: None,
-                            atr_14: None,
-                            market_regime: None,
-                            adx: None,
-                            dmi_plus: None,
-                            dmi_minus: None,
-                            trend_strength: None,
-                            trend_direction: None,
-                            support_levels: None,
-                            resistance_levels: None,
-                            nearest_support: None,
-                            nearest_resistance: None,
-                            detected_patterns: None,
-                            pattern_strength: None,
-                            depth_imbalance: None,
-                            volatility_1h: None,
-                            volatility_24h: None,
-                            price_change_1h: None,
-                            price_change_24h: None,
-                            volume_change_1h: None,
-                            volume_change_24h: None,
-                            analyzed: true,
-                            usable_by_model: false,
-                        })
-                        .await?;
+                    updates.push(MarketDataIndicatorUpdate {
+                        id: market_data.id,
+                        rsi_14: None,
+                        macd_line: None,
+                        macd_signal: None,
+                        macd_histogram: None,
+                        bb_upper: None,
+                        bb_middle: None,
+                        bb_lower: None,
+                        atr_14: None,
+                        market_regime: None,
+                        adx: None,
+                        dmi_plus: None,
+                        dmi_minus: None,
+                        trend_strength: None,
+                        trend_direction: None,
+                        support_levels: None,
+                        resistance_levels: None,
+                        nearest_support: None,
+                        nearest_resistance: None,
+                        detected_patterns: None,
+                        pattern_strength: None,
+                        depth_imbalance: book_imbalance,
+                        volatility_1h: None,
+                        volatility_24h: None,
+                        price_change_1h: None,
+                        price_change_24h: None,
+                        volume_change_1h: None,
+                        volume_change_24h: None,
+                        analyzed: true,
+                        usable_by_model: false,
+                        trading_session: Some(calendar.trading_session),
+                        day_of_week: Some(calendar.day_of_week),
+                        hours_to_weekly_close: Decimal::from_f64(calendar.hours_to_weekly_close),
+                        hours_to_monthly_close: Decimal::from_f64(calendar.hours_to_monthly_close),
+                        is_holiday: Some(calendar.is_holiday),
+                        session_volume_ratio: None,
+                        session_volatility_ratio: None,
+                        hour_of_week_volume_ratio: None,
+                        hour_of_week_volatility_ratio: None,
+                        long_liquidation_volume,
+                        short_liquidation_volume,
+                        basis,
+                        taker_buy_ratio,
+                        volume_delta,
+                        cvd,
+                        long_short_ratio,
+                        top_trader_account_ratio,
+                        top_trader_position_ratio,
+                        open_interest,
+                        open_interest_change,
+                        funding_rate,
+                    });
                     continue;
                 }
 
                 // Calculate existing indicators
+                let started = Instant::now();
                 let closes: Vec<f64> = historical_data
                     .iter()
                     .map(|d| d.close.to_f64().unwrap())
@@ -110,29 +350,63 @@ impl MarketDataAnalyzer {
                 let rsi = Helper::calculate_rsi(&closes, 14);
                 let (macd_line, signal, hist) = Helper::calculate_macd(&closes);
                 let (upper, middle, lower) = Helper::calculate_bollinger_bands(&closes, 20, 2.0);
-                let atr = Helper::calculate_atr(&historical_data, 14);
-                let depth_imbalance = Helper::calculate_depth_imbalance(&historical_data);
+                let atr = Helper::calculate_atr(historical_data, 14);
+                timings.record("momentum", started);
+
+                let started = Instant::now();
+                // Volume and volatility proxy when no snapshots cover the candle
+                let depth_imbalance = book_imbalance.unwrap_or_else(|| {
+                    Decimal::from_f64(Helper::calculate_depth_imbalance(historical_data))
+                        .unwrap_or_default()
+                });
                 let volatility_1h = Helper::calculate_volatility(&closes, 1);
                 let volatility_24h = Helper::calculate_volatility(&closes, 24);
-                let price_change_1h = Helper::calculate_price_change(&historical_data, 1);
-                let price_change_24h = Helper::calculate_price_change(&historical_data, 24);
-                let volume_change_1h = Helper::calculate_volume_change(&historical_data, 1);
-                let volume_change_24h = Helper::calculate_volume_change(&historical_data, 24);
+                let price_change_1h = Helper::calculate_price_change(historical_data, 1);
+                let price_change_24h = Helper::calculate_price_change(historical_data, 24);
+                let volume_change_1h = Helper::calculate_volume_change(historical_data, 1);
+                let volume_change_24h = Helper::calculate_volume_change(historical_data, 24);
+
+                // Baselines from the complete hours before the candle in the
+                // same session and hour of week
+                let history = baselines
+                    .get(&market_data.timeframe_id)
+                    .map(|history| {
+                        let from = market_data.open_time - Duration::days(BASELINE_DAYS);
+                        let first = history.partition_point(|h| h.hour < from);
+                        let end = history.partition_point(|h| {
+                            h.hour + Duration::hours(1) <= market_data.open_time
+                        });
+                        &history[first..end.max(first)]
+                    })
+                    .unwrap_or_default();
+                let (session_volume_ratio, session_volatility_ratio) =
+                    Helper::calculate_baseline_deviation(&market_data, history, |hour| {
+                        CalendarFeatures::trading_session(hour) == calendar.trading_session
+                    });
+                let hour_of_week = CalendarFeatures::hour_of_week(market_data.open_time);
+                let (hour_of_week_volume_ratio, hour_of_week_volatility_ratio) =
+                    Helper::calculate_baseline_deviation(&market_data, history, |hour| {
+                        CalendarFeatures::hour_of_week(hour) == hour_of_week
+                    });
+                timings.record("volatility", started);
 
                 // Calculate new technical indicators
-                let adx = Helper::calculate_adx(&historical_data, 14);
-                let price_direction = Helper::calculate_price_direction(&historical_data, 20);
+                let started = Instant::now();
+                let adx = Helper::calculate_adx(historical_data, 14);
+                let price_direction = Helper::calculate_price_direction(historical_data, 20);
 
                 // Detect market regime
                 let market_regime = Helper::identify_market_regime(
-                    &historical_data,
+                    historical_data,
                     VOLATILITY_THRESHOLD,
                     TREND_STRENGTH_THRESHOLD,
                 );
+                timings.record("trend", started);
 
                 // Find support and resistance levels
+                let started = Instant::now();
                 let (support_levels, resistance_levels) = Helper::calculate_support_resistance(
-                    &historical_data,
+                    historical_data,
                     SR_WINDOW_SIZE,
                     SR_THRESHOLD,
                 );
@@ -162,13 +436,17 @@ impl MarketDataAnalyzer {
                     .min_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal))
                     .map(|&x| Decimal::from_f64(x).unwrap());
 
-                let (dmi_plus, dmi_minus) = Helper::calculate_dmi(&historical_data, 14);
+                timings.record("support_resistance", started);
+
+                let (dmi_plus, dmi_minus) =
+                    timings.time("trend", || Helper::calculate_dmi(historical_data, 14));
 
                 const VOLUME_THRESHOLD: f64 = 1.5; // 150% of average volume
                 let mut detected_patterns = Vec::new();
                 let mut max_pattern_strength: f32 = 0.0;
 
                 // Check each pattern type
+                let started = Instant::now();
                 let patterns_to_check = [
                     PricePattern::DoubleTop,
                     PricePattern::DoubleBottom,
@@ -183,7 +461,7 @@ impl MarketDataAnalyzer {
 
                 for pattern in patterns_to_check.iter() {
                     if let Some(strength) = Helper::calculate_pattern_strength(
-                        &historical_data,
+                        historical_data,
                         pattern,
                         VOLUME_THRESHOLD,
                     ) {
@@ -193,52 +471,81 @@ impl MarketDataAnalyzer {
                         }
                     }
                 }
+                timings.record("patterns", started);
 
-                self.market_data_repository
-                    .update_indicators(MarketDataIndicatorUpdate {
-                        id: market_data.id,
-                        rsi_14: Some(Decimal::from_f64(rsi).unwrap_or_default()),
-                        macd_line: Some(Decimal::from_f64(macd_line).unwrap_or_default()),
-                        macd_signal: Some(Decimal::from_f64(signal).unwrap_or_default()),
-                        macd_histogram: Some(Decimal::from_f64(hist).unwrap_or_default()),
-                        bb_upper: Some(Decimal::from_f64(upper).unwrap_or_default()),
-                        bb_middle: Some(Decimal::from_f64(middle).unwrap_or_default()),
-                        bb_lower: Some(Decimal::from_f64(lower).unwrap_or_default()),
-                        atr_14: Some(Decimal::from_f64(atr).unwrap_or_default()),
-                        market_regime,
-                        adx: Some(Decimal::from_f64(adx).unwrap_or_default()),
-                        dmi_plus: Some(Decimal::from_f64(dmi_plus).unwrap_or_default()),
-                        dmi_minus: Some(Decimal::from_f64(dmi_minus).unwrap_or_default()),
-                        trend_strength: Some(Decimal::from_f64(adx).unwrap_or_default()),
-                        trend_direction: Some(price_direction as i32),
-                        support_levels: Some(support_decimals),
-                        resistance_levels: Some(resistance_decimals),
-                        nearest_support,
-                        nearest_resistance,
-                        detected_patterns: Some(detected_patterns.clone()),
-                        pattern_strength: if !detected_patterns.is_empty() {
-                            Some(Decimal::from_f64(max_pattern_strength.into()).unwrap_or_default())
-                        } else {
-                            None
-                        },
-                        depth_imbalance: Some(
-                            Decimal::from_f64(depth_imbalance).unwrap_or_default(),
-                        ),
-                        volatility_1h: Some(Decimal::from_f64(volatility_1h).unwrap_or_default()),
-                        volatility_24h: Some(Decimal::from_f64(volatility_24h).unwrap_or_default()),
-                        price_change_1h: Some(price_change_1h),
-                        price_change_24h: Some(price_change_24h),
-                        volume_change_1h: Some(volume_change_1h),
-                        volume_change_24h: Some(volume_change_24h),
-                        analyzed: true,
-                        usable_by_model: true,
-                    })
-                    .await?;
+                updates.push(MarketDataIndicatorUpdate {
+                    id: market_data.id,
+                    rsi_14: Some(Decimal::from_f64(rsi).unwrap_or_default()),
+                    macd_line: Some(Decimal::from_f64(macd_line).unwrap_or_default()),
+                    macd_signal: Some(Decimal::from_f64(signal).unwrap_or_default()),
+                    macd_histogram: Some(Decimal::from_f64(hist).unwrap_or_default()),
+                    bb_upper: Some(Decimal::from_f64(upper).unwrap_or_default()),
+                    bb_middle: Some(Decimal::from_f64(middle).unwrap_or_default()),
+                    bb_lower: Some(Decimal::from_f64(lower).unwrap_or_default()),
+                    atr_14: Some(Decimal::from_f64(atr).unwrap_or_default()),
+                    market_regime,
+                    adx: Some(Decimal::from_f64(adx).unwrap_or_default()),
+                    dmi_plus: Some(Decimal::from_f64(dmi_plus).unwrap_or_default()),
+                    dmi_minus: Some(Decimal::from_f64(dmi_minus).unwrap_or_default()),
+                    trend_strength: Some(Decimal::from_f64(adx).unwrap_or_default()),
+                    trend_direction: Some(price_direction as i32),
+                    support_levels: Some(support_decimals),
+                    resistance_levels: Some(resistance_decimals),
+                    nearest_support,
+                    nearest_resistance,
+                    detected_patterns: Some(detected_patterns.clone()),
+                    pattern_strength: if !detected_patterns.is_empty() {
+                        Some(Decimal::from_f64(max_pattern_strength.into()).unwrap_or_default())
+                    } else {
+                        None
+                    },
+                    depth_imbalance: Some(depth_imbalance),
+                    volatility_1h: Some(Decimal::from_f64(volatility_1h).unwrap_or_default()),
+                    volatility_24h: Some(Decimal::from_f64(volatility_24h).unwrap_or_default()),
+                    price_change_1h: Some(price_change_1h),
+                    price_change_24h: Some(price_change_24h),
+                    volume_change_1h: Some(volume_change_1h),
+                    volume_change_24h: Some(volume_change_24h),
+                    analyzed: true,
+                    usable_by_model: true,
+                    trading_session: Some(calendar.trading_session),
+                    day_of_week: Some(calendar.day_of_week),
+                    hours_to_weekly_close: Decimal::from_f64(calendar.hours_to_weekly_close),
+                    hours_to_monthly_close: Decimal::from_f64(calendar.hours_to_monthly_close),
+                    is_holiday: Some(calendar.is_holiday),
+                    session_volume_ratio: session_volume_ratio.and_then(Decimal::from_f64),
+                    session_volatility_ratio: session_volatility_ratio.and_then(Decimal::from_f64),
+                    hour_of_week_volume_ratio: hour_of_week_volume_ratio
+                        .and_then(Decimal::from_f64),
+                    hour_of_week_volatility_ratio: hour_of_week_volatility_ratio
+                        .and_then(Decimal::from_f64),
+                    funding_rate,
+                    open_interest,
+                    open_interest_change,
+                    long_short_ratio,
+                    top_trader_account_ratio,
+                    top_trader_position_ratio,
+                    volume_delta,
+                    cvd,
+                    long_liquidation_volume,
+                    short_liquidation_volume,
+                    basis,
+                    taker_buy_ratio,
+                });
 
                 analyzed_count += 1;
             }
+
+            let started = Instant::now();
+            self.market_data_repository
+                .update_indicators_batch(&updates)
+                .await?;
+            self.training_feature_repository.refresh(&ids).await?;
+            timings.record("db_write", started);
         }
 
+        timings.report("Market data analysis");
+
         Ok(analyzed_count)
     }
 }
@@ -0,0 +1,48 @@
+use anyhow::Result;
+use chrono::{Duration, Utc};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::repositories::market_data_repository::MarketDataRepository;
+
+use super::database_service::DatabaseService;
+
+const ARCHIVE_CHUNK_SIZE: usize = 1000;
+
+pub struct MarketDataArchiver {
+    market_data_repository: Arc<MarketDataRepository>,
+    timeframe_id: Uuid,
+    archive_after_days: u32,
+}
+
+impl MarketDataArchiver {
+    pub async fn new(timeframe_id: Uuid, archive_after_days: u32) -> Result<Self> {
+        let database = DatabaseService::new().await?;
+        let market_data_repository = MarketDataRepository::new(database.client);
+
+        Ok(MarketDataArchiver {
+            market_data_repository: Arc::new(market_data_repository),
+            timeframe_id,
+            archive_after_days,
+        })
+    }
+
+    pub async fn archive_market_data(&self) -> Result<usize> {
+        let cutoff = Utc::now() - Duration::days(self.archive_after_days.into());
+        let archived = self
+            .market_data_repository
+            .archive_before(&self.timeframe_id, cutoff, ARCHIVE_CHUNK_SIZE)
+            .await?;
+
+        if archived > 0 {
+            tracing::info!(
+                "Archived {} candles older than {} for timeframe {}",
+                archived,
+                cutoff,
+                self.timeframe_id
+            );
+        }
+
+        Ok(archived)
+    }
+}
@@ -3,30 +3,89 @@ use chrono::{DateTime, Duration as DurationChrono, Utc};
 use reqwest::{Error, StatusCode};
 use rust_decimal::Decimal;
 use serde_json::Value;
+use std::collections::{BTreeSet, HashMap};
+use std::future::Future;
 use std::str::FromStr;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use std::{fmt, usize};
 use tokio::time::sleep;
 
 use crate::models::timeframe::{ContractType, TimeFrame};
+use crate::utils::clock;
 use crate::utils::helper::Helper;
+use crate::utils::http::{self, Endpoint};
+use crate::utils::retry::RetryPolicy;
 use crate::{
-    models::market_data::MarketData,
+    models::{
+        candle_trade_flow::{CandleTradeFlow, TradeFlowCandle},
+        funding_rate::FundingRate,
+        long_short_ratio::{LongShortRatio, LongShortRatioType},
+        market_data::MarketData,
+        open_interest::OpenInterest,
+    },
     repositories::{
-        market_data_repository::MarketDataRepository, timeframe_repository::TimeFrameRepository,
+        candle_trade_flow_repository::CandleTradeFlowRepository,
+        funding_rate_repository::FundingRateRepository,
+        long_short_ratio_repository::LongShortRatioRepository,
+        market_data_repository::MarketDataRepository,
+        open_interest_repository::OpenInterestRepository,
+        timeframe_repository::TimeFrameRepository,
     },
 };
 
+use super::configuration_service;
 use super::database_service::DatabaseService;
+use super::exchange_service::Exchange;
 
-const BINANCE_FUTURE_API_URL: &str = "https://fapi.binance.com/fapi/v1/";
-const CONTINUOUS_KLINES_API_PATH: &str = "continuousKlines";
+const CONTINUOUS_KLINES_API_PATH: &str = "fapi/v1/continuousKlines";
+const SPOT_KLINES_API_PATH: &str = "api/v3/klines";
+const MARK_PRICE_KLINES_API_PATH: &str = "fapi/v1/markPriceKlines";
+const INDEX_PRICE_KLINES_API_PATH: &str = "fapi/v1/indexPriceKlines";
+const FUNDING_RATE_API_PATH: &str = "fapi/v1/fundingRate";
+const PREMIUM_INDEX_API_PATH: &str = "fapi/v1/premiumIndex";
+const OPEN_INTEREST_HIST_API_PATH: &str = "futures/data/openInterestHist";
+const AGG_TRADES_API_PATH: &str = "fapi/v1/aggTrades";
+const LONG_SHORT_RATIO_API_PATHS: [(LongShortRatioType, &str); 3] = [
+    (
+        LongShortRatioType::GlobalAccount,
+        "futures/data/globalLongShortAccountRatio",
+    ),
+    (
+        LongShortRatioType::TopTraderAccount,
+        "futures/data/topLongShortAccountRatio",
+    ),
+    (
+        LongShortRatioType::TopTraderPosition,
+        "futures/data/topLongShortPositionRatio",
+    ),
+];
+// Periods served by the futures/data endpoints, which only keep 30 days
+const FUTURES_DATA_PERIODS: [i32; 9] = [5, 15, 30, 60, 120, 240, 360, 720, 1440];
+const FUTURES_DATA_LIMIT: i64 = 500;
+const FUTURES_DATA_MAX_DAYS: i64 = 29;
 const FETCH_LIMIT: i32 = 1000;
-const MAX_RETRIES: i32 = 5;
-const RECENT_DATA_MAX_RETRIES: i32 = 3;
+// Largest kline pages served, futures klines go up to 1500 and spot ones to 1000
+const MAX_KLINES_FETCH_LIMIT: i32 = 1500;
+const MAX_SPOT_KLINES_FETCH_LIMIT: i32 = 1000;
+// aggTrades time windows must be shorter than an hour
+const AGG_TRADES_MAX_WINDOW: i64 = 3_599_999; // in milliseconds
+const TRADE_FLOW_BATCH_SIZE: usize = 20;
+// Candles walked per trade flow fetch, each one costs two aggTrades requests
+// or more
+const TRADE_FLOW_MAX_CANDLES: i64 = 60;
+// Latest stored candles fetched again on every poll, Binance occasionally
+// revises candles shortly after they close
+const REVISION_WINDOW_CANDLES: i64 = 3;
 const RATE_LIMIT_TIMEOUT: i64 = 100;
-const RECENT_DATA_RETRY_DELAY: u64 = 2000; // 2 seconds in milliseconds
-const RATE_LIMIT_MAX_WEIGHT: i32 = 4000;
+// REQUEST_WEIGHT limits per minute of the exchangeInfo rateLimits
+const FUTURES_MAX_WEIGHT: i32 = 2400;
+const SPOT_MAX_WEIGHT: i32 = 6000;
+const MIN_FETCH_LIMIT: i32 = 100;
+const MAX_REQUEST_DELAY: u64 = 5000; // 5 seconds in milliseconds
+                                     // Share of the weight limit above which requests slow down, and below which
+                                     // they speed up again
+const WEIGHT_HIGH_WATERMARK: f64 = 0.75;
+const WEIGHT_LOW_WATERMARK: f64 = 0.5;
 
 #[derive(Debug)]
 pub enum MarketDataFetcherError {
@@ -57,54 +116,719 @@ impl From<Error> for MarketDataFetcherError {
     }
 }
 
+// Fetch window and inter-request delay adjusted from the observed used weight
+struct FetchPacing {
+    limit: i32,
+    max_limit: i32,
+    delay_ms: u64,
+    max_weight: i32,
+}
+
+impl FetchPacing {
+    fn new(max_limit: i32, max_weight: i32) -> Self {
+        Self {
+            limit: max_limit,
+            max_limit,
+            delay_ms: 0,
+            max_weight,
+        }
+    }
+
+    // Between the watermarks the pace is kept as is
+    fn observe(&mut self, weight: i32) {
+        let usage = weight as f64 / self.max_weight as f64;
+
+        if usage >= WEIGHT_HIGH_WATERMARK {
+            self.limit = (self.limit / 2).max(MIN_FETCH_LIMIT.min(self.max_limit));
+            self.delay_ms =
+                (self.delay_ms.max(RATE_LIMIT_TIMEOUT as u64) * 2).min(MAX_REQUEST_DELAY);
+        } else if usage < WEIGHT_LOW_WATERMARK {
+            self.limit = (self.limit + self.limit / 2).min(self.max_limit);
+            self.delay_ms /= 2;
+        }
+    }
+}
+
+// Binance REST requests of one contract type, paced from the weight the
+// responses report as used
+struct BinanceApi {
+    client: reqwest::Client,
+    base_url: String,
+    contract_type: ContractType,
+    pacing: Mutex<FetchPacing>,
+}
+
 pub struct MarketDataFetcher {
-    pub client: reqwest::Client,
     pub symbol: String,
     pub contract_type: ContractType,
     pub timeframe: TimeFrame,
     pub lookback_days: u32,
     market_data_repository: Arc<MarketDataRepository>,
+    timeframe_repository: TimeFrameRepository,
+    funding_rate_repository: FundingRateRepository,
+    open_interest_repository: OpenInterestRepository,
+    long_short_ratio_repository: LongShortRatioRepository,
+    candle_trade_flow_repository: CandleTradeFlowRepository,
+    api: BinanceApi,
+    dry_run: bool,
 }
 
 impl MarketDataFetcher {
+    // A dry run reads the database but never writes to it
     pub async fn new(
         symbol: String,
         contract_type: ContractType,
         interval: String,
         lookback_days: u32,
+        fetch_limit: Option<i32>,
+        dry_run: bool,
     ) -> Result<Self> {
+        // The repositories are used one after the other, they share a connection
         let database = DatabaseService::new().await?;
-        let timeframe_repository = TimeFrameRepository::new(database.client);
+        let client = Arc::new(tokio::sync::Mutex::new(database.client));
+        let timeframe_repository = TimeFrameRepository::from_shared(client.clone());
+        let market_data_repository = MarketDataRepository::from_shared(client.clone());
+        let funding_rate_repository = FundingRateRepository::from_shared(client.clone());
+        let open_interest_repository = OpenInterestRepository::from_shared(client.clone());
+        let long_short_ratio_repository = LongShortRatioRepository::from_shared(client.clone());
+        let candle_trade_flow_repository = CandleTradeFlowRepository::from_shared(client);
 
-        let database = DatabaseService::new().await?;
-        let market_data_repository = MarketDataRepository::new(database.client);
-
-        let timeframe = timeframe_repository
-            .find_or_create(symbol.clone(), contract_type.clone(), interval)
-            .await?;
+        let timeframe = if dry_run {
+            timeframe_repository
+                .find_or_new(symbol.clone(), contract_type.clone(), interval)
+                .await?
+        } else {
+            timeframe_repository
+                .find_or_create(symbol.clone(), contract_type.clone(), interval)
+                .await?
+        };
 
         Ok(MarketDataFetcher {
-            client: reqwest::Client::new(),
+            api: BinanceApi::new(contract_type.clone(), fetch_limit),
             symbol,
             contract_type,
             timeframe,
             lookback_days,
             market_data_repository: Arc::new(market_data_repository),
+            timeframe_repository,
+            funding_rate_repository,
+            open_interest_repository,
+            long_short_ratio_repository,
+            candle_trade_flow_repository,
+            dry_run,
         })
     }
 
+    // The candle still open on the Binance clock is left out. A dry run
+    // prints the batch instead of inserting it, an upsert also overwrites the
+    // stored candles the exchange has revised.
+    async fn save_batch(
+        &self,
+        batch: &[MarketData],
+        upsert: bool,
+    ) -> Result<usize, MarketDataFetcherError> {
+        let now = clock::now(configuration_service::Exchange::Binance);
+        let closed: Vec<MarketData> = batch
+            .iter()
+            .filter(|candle| candle.close_time <= now)
+            .cloned()
+            .collect();
+        if self.dry_run {
+            closed.iter().for_each(|candle| println!("{}", candle));
+            return Ok(closed.len());
+        }
+        let saved = if upsert {
+            self.market_data_repository.upsert_batch(&closed).await
+        } else {
+            self.market_data_repository.create_batch(&closed).await
+        };
+        saved
+            .map(|ids| ids.len())
+            .map_err(|e| MarketDataFetcherError::Api {
+                status: StatusCode::INTERNAL_SERVER_ERROR,
+                body: e.to_string(),
+            })
+    }
+
+    async fn fetch_market_data(
+        &self,
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+        upsert: bool,
+    ) -> Result<usize, MarketDataFetcherError> {
+        let inserted_count = self
+            .api
+            .fetch_klines(&self.timeframe, start_time, end_time, |batch| async move {
+                let inserted = self.save_batch(&batch, upsert).await?;
+                tracing::info!(
+                    "Inserted {} elements for {} {} {}",
+                    inserted,
+                    self.symbol,
+                    Helper::minutes_to_interval(self.timeframe.interval_minutes),
+                    self.timeframe.contract_type
+                );
+                Ok(inserted)
+            })
+            .await?;
+
+        if inserted_count == 0 {
+            return Err(MarketDataFetcherError::NoDataFound);
+        }
+        tracing::info!(
+            "MarketData initalization done {} elements inserted for {} {} {}",
+            inserted_count,
+            self.symbol,
+            Helper::minutes_to_interval(self.timeframe.interval_minutes),
+            self.timeframe.contract_type
+        );
+
+        Ok(inserted_count)
+    }
+
+    // Mark and index price closes of the closed candles of the lookback
+    // window that miss them
+    pub async fn fetch_price_closes(&self) -> Result<usize, MarketDataFetcherError> {
+        let since = Utc::now() - DurationChrono::days(self.lookback_days.into());
+        let start_time = match self
+            .market_data_repository
+            .find_oldest_without_price_closes(&self.timeframe.id, since)
+            .await
+            .map_err(|e| MarketDataFetcherError::Api {
+                status: StatusCode::INTERNAL_SERVER_ERROR,
+                body: e.to_string(),
+            })? {
+            Some(start_time) => start_time,
+            None => return Ok(0),
+        };
+
+        let mark_closes = self
+            .fetch_kline_closes(MARK_PRICE_KLINES_API_PATH, "symbol", start_time)
+            .await?;
+        let index_closes = self
+            .fetch_kline_closes(INDEX_PRICE_KLINES_API_PATH, "pair", start_time)
+            .await?;
+
+        let open_times: Vec<DateTime<Utc>> = mark_closes
+            .keys()
+            .chain(index_closes.keys())
+            .copied()
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect();
+        let marks: Vec<Option<Decimal>> = open_times
+            .iter()
+            .map(|t| mark_closes.get(t).copied())
+            .collect();
+        let indexes: Vec<Option<Decimal>> = open_times
+            .iter()
+            .map(|t| index_closes.get(t).copied())
+            .collect();
+
+        let updated = self
+            .market_data_repository
+            .update_price_closes(&self.timeframe.id, &open_times, &marks, &indexes)
+            .await
+            .map_err(|e| MarketDataFetcherError::Api {
+                status: StatusCode::INTERNAL_SERVER_ERROR,
+                body: e.to_string(),
+            })?;
+
+        Ok(updated as usize)
+    }
+
+    // Close of every kline of a price klines endpoint from `start_time` on,
+    // by open time
+    async fn fetch_kline_closes(
+        &self,
+        path: &str,
+        symbol_param: &str,
+        start_time: DateTime<Utc>,
+    ) -> Result<HashMap<DateTime<Utc>, Decimal>, MarketDataFetcherError> {
+        let now = Utc::now().timestamp_millis();
+        let mut current_time = start_time.timestamp_millis();
+        let mut closes = HashMap::new();
+
+        while current_time < now {
+            let params = [
+                (symbol_param, self.symbol.to_string()),
+                (
+                    "interval",
+                    Helper::minutes_to_interval(self.timeframe.interval_minutes),
+                ),
+                ("startTime", current_time.to_string()),
+                ("limit", FETCH_LIMIT.to_string()),
+            ];
+            let data = self.api.fetch_with_retry(path, &params).await?;
+            let klines = data.as_array().ok_or(MarketDataFetcherError::Api {
+                status: StatusCode::BAD_REQUEST,
+                body: format!("Invalid {} response format", path),
+            })?;
+
+            for kline in klines {
+                closes.insert(
+                    Self::parse_timestamp(&kline[0], "open time")?,
+                    Self::parse_decimal(&kline[4], "close")?,
+                );
+            }
+
+            match klines.last() {
+                Some(last) if klines.len() as i32 == FETCH_LIMIT => {
+                    current_time =
+                        Self::parse_timestamp(&last[0], "open time")?.timestamp_millis() + 1;
+                }
+                _ => break,
+            }
+        }
+
+        Ok(closes)
+    }
+
+    // Settled funding history since the last stored funding, then the
+    // predicted rate of the upcoming one from the premium index
+    pub async fn fetch_funding_rates(&self) -> Result<usize, MarketDataFetcherError> {
+        let latest_settled = self
+            .funding_rate_repository
+            .find_latest_settled_time(&self.symbol)
+            .await
+            .map_err(|e| MarketDataFetcherError::Api {
+                status: StatusCode::INTERNAL_SERVER_ERROR,
+                body: e.to_string(),
+            })?;
+        let start_time = match latest_settled {
+            Some(funding_time) => funding_time + DurationChrono::milliseconds(1),
+            None => Utc::now() - DurationChrono::days(self.lookback_days.into()),
+        };
+
+        let mut rates = self.fetch_funding_history(start_time, None).await?;
+
+        let params = [("symbol", self.symbol.to_string())];
+        let premium = self
+            .api
+            .fetch_with_retry(PREMIUM_INDEX_API_PATH, &params)
+            .await?;
+        rates.push(FundingRate {
+            symbol: self.symbol.clone(),
+            funding_time: Self::parse_timestamp(&premium["nextFundingTime"], "nextFundingTime")?,
+            funding_rate: Self::parse_decimal(&premium["lastFundingRate"], "lastFundingRate")?,
+            mark_price: Some(Self::parse_decimal(&premium["markPrice"], "markPrice")?),
+            settled: false,
+        });
+
+        self.funding_rate_repository
+            .upsert_batch(&rates)
+            .await
+            .map_err(|e| MarketDataFetcherError::Api {
+                status: StatusCode::INTERNAL_SERVER_ERROR,
+                body: e.to_string(),
+            })?;
+
+        Ok(rates.len())
+    }
+
+    // Settled funding rates of a past window, stored with the candles of the
+    // same initialization chunk
+    async fn backfill_funding_rates(
+        &self,
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+    ) -> Result<(), MarketDataFetcherError> {
+        let rates = self
+            .fetch_funding_history(start_time, Some(end_time))
+            .await?;
+        if rates.is_empty() {
+            return Ok(());
+        }
+
+        self.funding_rate_repository
+            .upsert_batch(&rates)
+            .await
+            .map_err(|e| MarketDataFetcherError::Api {
+                status: StatusCode::INTERNAL_SERVER_ERROR,
+                body: e.to_string(),
+            })?;
+        Ok(())
+    }
+
+    // Settled funding rates from `start_time` on, oldest first
+    async fn fetch_funding_history(
+        &self,
+        mut start_time: DateTime<Utc>,
+        end_time: Option<DateTime<Utc>>,
+    ) -> Result<Vec<FundingRate>, MarketDataFetcherError> {
+        let mut rates = Vec::new();
+        loop {
+            let mut params = vec![
+                ("symbol", self.symbol.to_string()),
+                ("startTime", start_time.timestamp_millis().to_string()),
+                ("limit", FETCH_LIMIT.to_string()),
+            ];
+            if let Some(end_time) = end_time {
+                params.push(("endTime", end_time.timestamp_millis().to_string()));
+            }
+            let data = self
+                .api
+                .fetch_with_retry(FUNDING_RATE_API_PATH, &params)
+                .await?;
+            let history = data.as_array().ok_or(MarketDataFetcherError::Api {
+                status: StatusCode::BAD_REQUEST,
+                body: "Invalid funding rate response format".to_string(),
+            })?;
+
+            for value in history {
+                rates.push(FundingRate {
+                    symbol: self.symbol.clone(),
+                    funding_time: Self::parse_timestamp(&value["fundingTime"], "fundingTime")?,
+                    funding_rate: Self::parse_decimal(&value["fundingRate"], "fundingRate")?,
+                    mark_price: value["markPrice"]
+                        .as_str()
+                        .and_then(|s| Decimal::from_str(s).ok()),
+                    settled: true,
+                });
+            }
+
+            match rates.last() {
+                Some(last) if history.len() as i32 == FETCH_LIMIT => {
+                    start_time = last.funding_time + DurationChrono::milliseconds(1);
+                }
+                _ => return Ok(rates),
+            }
+        }
+    }
+
+    // Closest futures/data period not finer than the timeframe
+    fn futures_data_period(&self) -> i32 {
+        FUTURES_DATA_PERIODS
+            .into_iter()
+            .find(|&p| p >= self.timeframe.interval_minutes)
+            .unwrap_or(FUTURES_DATA_PERIODS[FUTURES_DATA_PERIODS.len() - 1])
+    }
+
+    // Points of a futures/data endpoint newer than `latest`, oldest first
+    async fn fetch_futures_data(
+        &self,
+        path: &str,
+        period_minutes: i32,
+        latest: Option<DateTime<Utc>>,
+    ) -> Result<Vec<Value>, MarketDataFetcherError> {
+        let now = Utc::now();
+        let lookback = i64::from(self.lookback_days).min(FUTURES_DATA_MAX_DAYS);
+        let earliest = now - DurationChrono::days(lookback);
+        let mut start_time = match latest {
+            Some(latest) => (latest + DurationChrono::milliseconds(1)).max(earliest),
+            None => earliest,
+        };
+
+        let page = DurationChrono::minutes(i64::from(period_minutes) * FUTURES_DATA_LIMIT);
+        let mut points = Vec::new();
+        while start_time < now {
+            let end_time = (start_time + page).min(now);
+            let params = [
+                ("symbol", self.symbol.to_string()),
+                ("period", Helper::minutes_to_interval(period_minutes)),
+                ("startTime", start_time.timestamp_millis().to_string()),
+                ("endTime", end_time.timestamp_millis().to_string()),
+                ("limit", FUTURES_DATA_LIMIT.to_string()),
+            ];
+            let data = self.api.fetch_with_retry(path, &params).await?;
+            let page_points = data.as_array().ok_or(MarketDataFetcherError::Api {
+                status: StatusCode::BAD_REQUEST,
+                body: format!("Invalid {} response format", path),
+            })?;
+            points.extend(page_points.iter().cloned());
+            start_time = end_time + DurationChrono::milliseconds(1);
+        }
+
+        Ok(points)
+    }
+
+    pub async fn fetch_open_interest(&self) -> Result<usize, MarketDataFetcherError> {
+        let period_minutes = self.futures_data_period();
+        let latest = self
+            .open_interest_repository
+            .find_latest_time(&self.symbol, period_minutes)
+            .await
+            .map_err(|e| MarketDataFetcherError::Api {
+                status: StatusCode::INTERNAL_SERVER_ERROR,
+                body: e.to_string(),
+            })?;
+
+        let points = self
+            .fetch_futures_data(OPEN_INTEREST_HIST_API_PATH, period_minutes, latest)
+            .await?
+            .iter()
+            .map(|value| {
+                Ok(OpenInterest {
+                    symbol: self.symbol.clone(),
+                    period_minutes,
+                    timestamp: Self::parse_timestamp(&value["timestamp"], "timestamp")?,
+                    sum_open_interest: Self::parse_decimal(
+                        &value["sumOpenInterest"],
+                        "sumOpenInterest",
+                    )?,
+                    sum_open_interest_value: Self::parse_decimal(
+                        &value["sumOpenInterestValue"],
+                        "sumOpenInterestValue",
+                    )?,
+                })
+            })
+            .collect::<Result<Vec<OpenInterest>, MarketDataFetcherError>>()?;
+
+        let inserted = self
+            .open_interest_repository
+            .create_batch(&points)
+            .await
+            .map_err(|e| MarketDataFetcherError::Api {
+                status: StatusCode::INTERNAL_SERVER_ERROR,
+                body: e.to_string(),
+            })?;
+
+        Ok(inserted as usize)
+    }
+
+    pub async fn fetch_long_short_ratios(&self) -> Result<usize, MarketDataFetcherError> {
+        let period_minutes = self.futures_data_period();
+        let mut inserted = 0;
+
+        for (ratio_type, path) in LONG_SHORT_RATIO_API_PATHS {
+            let latest = self
+                .long_short_ratio_repository
+                .find_latest_time(&self.symbol, period_minutes, &ratio_type)
+                .await
+                .map_err(|e| MarketDataFetcherError::Api {
+                    status: StatusCode::INTERNAL_SERVER_ERROR,
+                    body: e.to_string(),
+                })?;
+
+            let ratios = self
+                .fetch_futures_data(path, period_minutes, latest)
+                .await?
+                .iter()
+                .map(|value| {
+                    Ok(LongShortRatio {
+                        symbol: self.symbol.clone(),
+                        period_minutes,
+                        ratio_type: ratio_type.clone(),
+                        timestamp: Self::parse_timestamp(&value["timestamp"], "timestamp")?,
+                        long_short_ratio: Self::parse_decimal(
+                            &value["longShortRatio"],
+                            "longShortRatio",
+                        )?,
+                        long_share: Self::parse_decimal(&value["longAccount"], "longAccount")?,
+                        short_share: Self::parse_decimal(&value["shortAccount"], "shortAccount")?,
+                    })
+                })
+                .collect::<Result<Vec<LongShortRatio>, MarketDataFetcherError>>()?;
+
+            inserted += self
+                .long_short_ratio_repository
+                .create_batch(&ratios)
+                .await
+                .map_err(|e| MarketDataFetcherError::Api {
+                    status: StatusCode::INTERNAL_SERVER_ERROR,
+                    body: e.to_string(),
+                })? as usize;
+        }
+
+        Ok(inserted)
+    }
+
+    // Taker flow of the latest TRADE_FLOW_MAX_CANDLES closed candles that
+    // have none yet, stored every TRADE_FLOW_BATCH_SIZE candles. A backfill
+    // looks back over the lookback window, otherwise only the candles opened
+    // after the last one with a flow are fetched. Older candles are left
+    // without a flow.
+    pub async fn fetch_trade_flows(&self, backfill: bool) -> Result<usize, MarketDataFetcherError> {
+        let lookback_start = Utc::now() - DurationChrono::days(self.lookback_days.into());
+        let latest = if backfill {
+            None
+        } else {
+            self.candle_trade_flow_repository
+                .find_latest_flow_time(&self.timeframe.id)
+                .await
+                .map_err(|e| MarketDataFetcherError::Api {
+                    status: StatusCode::INTERNAL_SERVER_ERROR,
+                    body: e.to_string(),
+                })?
+        };
+        let since = latest.map_or(lookback_start, |time| {
+            (time + DurationChrono::milliseconds(1)).max(lookback_start)
+        });
+
+        let candles = self
+            .candle_trade_flow_repository
+            .find_pending_candles(&self.timeframe.id, since, TRADE_FLOW_MAX_CANDLES)
+            .await
+            .map_err(|e| MarketDataFetcherError::Api {
+                status: StatusCode::INTERNAL_SERVER_ERROR,
+                body: e.to_string(),
+            })?;
+
+        let mut inserted = 0;
+        for chunk in candles.chunks(TRADE_FLOW_BATCH_SIZE) {
+            let mut flows = Vec::with_capacity(chunk.len());
+            for candle in chunk {
+                flows.push(self.fetch_candle_trade_flow(candle).await?);
+            }
+
+            inserted += self
+                .candle_trade_flow_repository
+                .create_batch(&flows)
+                .await
+                .map_err(|e| MarketDataFetcherError::Api {
+                    status: StatusCode::INTERNAL_SERVER_ERROR,
+                    body: e.to_string(),
+                })? as usize;
+        }
+
+        Ok(inserted)
+    }
+
+    // Walks the aggTrades of a candle by time window until the first trade,
+    // then by consecutive ids until a trade past the candle close
+    async fn fetch_candle_trade_flow(
+        &self,
+        candle: &TradeFlowCandle,
+    ) -> Result<CandleTradeFlow, MarketDataFetcherError> {
+        let mut flow = CandleTradeFlow {
+            market_data_id: candle.market_data_id,
+            first_trade_id: None,
+            last_trade_id: None,
+            trade_count: 0,
+            taker_buy_volume: Decimal::ZERO,
+            taker_sell_volume: Decimal::ZERO,
+        };
+        let mut window_start = candle.open_time;
+
+        loop {
+            let by_id = flow.last_trade_id.is_some();
+            let params = match flow.last_trade_id {
+                Some(last_trade_id) => vec![
+                    ("symbol", self.symbol.to_string()),
+                    ("fromId", (last_trade_id + 1).to_string()),
+                    ("limit", FETCH_LIMIT.to_string()),
+                ],
+                None if window_start > candle.close_time => break,
+                None => {
+                    let window_end = (window_start
+                        + DurationChrono::milliseconds(AGG_TRADES_MAX_WINDOW))
+                    .min(candle.close_time);
+                    let params = vec![
+                        ("symbol", self.symbol.to_string()),
+                        ("startTime", window_start.timestamp_millis().to_string()),
+                        ("endTime", window_end.timestamp_millis().to_string()),
+                        ("limit", FETCH_LIMIT.to_string()),
+                    ];
+                    window_start = window_end + DurationChrono::milliseconds(1);
+                    params
+                }
+            };
+
+            let data = self
+                .api
+                .fetch_with_retry(AGG_TRADES_API_PATH, &params)
+                .await?;
+            let trades = data.as_array().ok_or(MarketDataFetcherError::Api {
+                status: StatusCode::BAD_REQUEST,
+                body: "Invalid aggregated trades response format".to_string(),
+            })?;
+
+            let mut past_close = false;
+            for trade in trades {
+                if Self::parse_timestamp(&trade["T"], "T")? > candle.close_time {
+                    past_close = true;
+                    break;
+                }
+                let trade_id = trade["a"].as_i64().ok_or(MarketDataFetcherError::Api {
+                    status: StatusCode::BAD_REQUEST,
+                    body: "Invalid aggregated trade id".to_string(),
+                })?;
+                let quantity = Self::parse_decimal(&trade["q"], "q")?;
+
+                // A maker buyer means the taker sold
+                match trade["m"].as_bool() {
+                    Some(true) => flow.taker_sell_volume += quantity,
+                    Some(false) => flow.taker_buy_volume += quantity,
+                    None => {
+                        return Err(MarketDataFetcherError::Api {
+                            status: StatusCode::BAD_REQUEST,
+                            body: "Invalid aggregated trade side".to_string(),
+                        })
+                    }
+                }
+                flow.first_trade_id.get_or_insert(trade_id);
+                flow.last_trade_id = Some(trade_id);
+                flow.trade_count += 1;
+            }
+
+            if past_close || (by_id && trades.len() < FETCH_LIMIT as usize) {
+                break;
+            }
+        }
+
+        Ok(flow)
+    }
+
+    fn parse_timestamp(
+        value: &Value,
+        field: &str,
+    ) -> Result<DateTime<Utc>, MarketDataFetcherError> {
+        value
+            .as_i64()
+            .and_then(DateTime::<Utc>::from_timestamp_millis)
+            .ok_or_else(|| MarketDataFetcherError::Api {
+                status: StatusCode::BAD_REQUEST,
+                body: format!("Invalid {} timestamp", field),
+            })
+    }
+
+    fn parse_decimal(value: &Value, field: &str) -> Result<Decimal, MarketDataFetcherError> {
+        value
+            .as_str()
+            .and_then(|s| Decimal::from_str(s).ok())
+            .ok_or_else(|| MarketDataFetcherError::Api {
+                status: StatusCode::BAD_REQUEST,
+                body: format!("Invalid {} decimal", field),
+            })
+    }
+}
+
+impl BinanceApi {
+    // Spot pairs only call spot endpoints, every other contract the futures ones
+    fn new(contract_type: ContractType, fetch_limit: Option<i32>) -> Self {
+        let base_url = match contract_type {
+            ContractType::Spot => Endpoint::BinanceSpotApi.url(),
+            _ => Endpoint::BinanceFuturesApi.url(),
+        };
+        Self::with_base_url(base_url, contract_type, fetch_limit)
+    }
+
+    fn with_base_url(
+        base_url: String,
+        contract_type: ContractType,
+        fetch_limit: Option<i32>,
+    ) -> Self {
+        // Candles per kline request, before the pacing lowers it
+        let (max_fetch_limit, max_weight) = match contract_type {
+            ContractType::Spot => (MAX_SPOT_KLINES_FETCH_LIMIT, SPOT_MAX_WEIGHT),
+            _ => (MAX_KLINES_FETCH_LIMIT, FUTURES_MAX_WEIGHT),
+        };
+        let fetch_limit = fetch_limit.unwrap_or(FETCH_LIMIT).clamp(1, max_fetch_limit);
+        Self {
+            client: http::client(),
+            base_url,
+            contract_type,
+            pacing: Mutex::new(FetchPacing::new(fetch_limit, max_weight)),
+        }
+    }
+
     async fn fetch_with_retry(
         &self,
         path: &str,
         params: &[(&str, String)],
-        retry_count: i32,
     ) -> Result<Value, MarketDataFetcherError> {
-        let url = format!("{}{}", BINANCE_FUTURE_API_URL, path);
-        let response = self
-            .client
-            .get(&url)
-            .query(&params)
-            .send()
+        let url = format!("{}{}", self.base_url, path);
+        let response = RetryPolicy::DEFAULT
+            .send(|| self.client.get(&url).query(&params))
             .await
             .map_err(MarketDataFetcherError::Request)?;
 
@@ -114,34 +838,106 @@ impl MarketDataFetcher {
             .and_then(|h| h.to_str().ok())
             .and_then(|s| s.parse::<i32>().ok())
         {
-            if weight >= RATE_LIMIT_MAX_WEIGHT {
+            // The pacing slows the next requests down before Binance answers 429
+            let mut pacing = self.pacing.lock().unwrap();
+            if weight >= pacing.max_weight {
                 tracing::warn!("Rate limit weight threshold reached: {}", weight);
-                sleep(std::time::Duration::from_millis(RATE_LIMIT_TIMEOUT as u64)).await;
-                return Box::pin(self.fetch_with_retry(path, params, retry_count)).await;
             }
+            pacing.observe(weight);
         }
 
-        match response.status() {
-            StatusCode::TOO_MANY_REQUESTS if retry_count < MAX_RETRIES => {
-                tracing::warn!("Rate limited, retry {} of {}", retry_count + 1, MAX_RETRIES);
-                sleep(std::time::Duration::from_millis(RATE_LIMIT_TIMEOUT as u64)).await;
-                Box::pin(self.fetch_with_retry(path, params, retry_count + 1)).await
+        match response.error_for_status() {
+            Ok(resp) => resp.json().await.map_err(MarketDataFetcherError::Json),
+            Err(err) => {
+                let status = err.status().unwrap_or_default();
+                let body = err.to_string();
+                tracing::error!(%status, %body, "Binance API request failed");
+                Err(MarketDataFetcherError::Api { status, body })
             }
-            _ => match response.error_for_status() {
-                Ok(resp) => resp.json().await.map_err(MarketDataFetcherError::Json),
-                Err(err) => {
-                    let status = err.status().unwrap_or_default();
-                    let body = err.to_string();
-                    tracing::error!(%status, %body, "Binance API request failed");
-                    Err(MarketDataFetcherError::Api { status, body })
-                }
-            },
         }
     }
 
-    fn format_values_to_kline_create_payload(
+    // Klines of `timeframe` opened from `start_time` to `end_time`, page by
+    // page. Each page starts right after the close of the last candle of the
+    // previous one and is handed to `save`, a short page ends the window.
+    // Returns the number of candles fetched.
+    async fn fetch_klines<F, Fut>(
         &self,
-        value: Value,
+        timeframe: &TimeFrame,
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+        mut save: F,
+    ) -> Result<usize, MarketDataFetcherError>
+    where
+        F: FnMut(Vec<MarketData>) -> Fut,
+        Fut: Future<Output = Result<usize, MarketDataFetcherError>>,
+    {
+        let mut fetched_count = 0;
+        let mut current_time = start_time.timestamp_millis();
+
+        while current_time < end_time.timestamp_millis() {
+            let (limit, delay_ms) = {
+                let pacing = self.pacing.lock().unwrap();
+                (pacing.limit, pacing.delay_ms)
+            };
+            if delay_ms > 0 {
+                sleep(std::time::Duration::from_millis(delay_ms)).await;
+            }
+
+            let (path, mut params) = match self.contract_type {
+                ContractType::Spot => (
+                    SPOT_KLINES_API_PATH,
+                    vec![("symbol", timeframe.symbol.to_string())],
+                ),
+                _ => (
+                    CONTINUOUS_KLINES_API_PATH,
+                    vec![
+                        ("pair", timeframe.symbol.to_string()),
+                        ("contractType", self.contract_type.to_string()),
+                    ],
+                ),
+            };
+            params.extend([
+                (
+                    "interval",
+                    Helper::minutes_to_interval(timeframe.interval_minutes),
+                ),
+                ("startTime", current_time.to_string()),
+                ("endTime", end_time.timestamp_millis().to_string()),
+                ("limit", limit.to_string()),
+            ]);
+
+            let data = self.fetch_with_retry(path, &params).await?;
+            let market_data_array = data.as_array().ok_or(MarketDataFetcherError::Api {
+                status: StatusCode::BAD_REQUEST,
+                body: "Invalid response format".to_string(),
+            })?;
+
+            if market_data_array.is_empty() {
+                break;
+            }
+
+            let market_data_batch = market_data_array
+                .iter()
+                .map(|raw_data| Self::format_values_to_kline_create_payload(timeframe, raw_data))
+                .collect::<Result<Vec<MarketData>, _>>()?;
+            if let Some(last_record) = market_data_batch.last() {
+                current_time = last_record.close_time.timestamp_millis() + 1;
+                fetched_count += market_data_batch.len();
+            }
+            save(market_data_batch).await?;
+            // A short page means the window is exhausted
+            if (market_data_array.len() as i32) < limit {
+                break;
+            }
+        }
+
+        Ok(fetched_count)
+    }
+
+    fn format_values_to_kline_create_payload(
+        timeframe: &TimeFrame,
+        value: &Value,
     ) -> Result<MarketData, MarketDataFetcherError> {
         let open_time = value[0]
             .as_i64()
@@ -167,9 +963,9 @@ impl MarketDataFetcher {
             };
 
         Ok(MarketData::new(
-            self.timeframe.id,
-            self.symbol.clone(),
-            self.contract_type.to_string(),
+            timeframe.id,
+            timeframe.symbol.clone(),
+            timeframe.contract_type.to_string(),
             DateTime::<Utc>::from_timestamp_millis(open_time).ok_or_else(|| {
                 MarketDataFetcherError::Api {
                     status: StatusCode::BAD_REQUEST,
@@ -197,131 +993,321 @@ impl MarketDataFetcher {
                     status: StatusCode::BAD_REQUEST,
                     body: "Invalid trades format".to_string(),
                 })?,
+        )
+        .with_taker_buy_volumes(
+            parse_decimal(&value[9], "taker_buy_volume")?,
+            parse_decimal(&value[10], "taker_buy_quote_volume")?,
         ))
     }
+}
 
-    async fn fetch_market_data(
+impl Exchange for MarketDataFetcher {
+    const NAME: &'static str = "Binance";
+
+    fn timeframe(&self) -> &TimeFrame {
+        &self.timeframe
+    }
+
+    fn lookback_days(&self) -> u32 {
+        self.lookback_days
+    }
+
+    fn market_data_repository(&self) -> &MarketDataRepository {
+        &self.market_data_repository
+    }
+
+    fn timeframe_repository(&self) -> &TimeFrameRepository {
+        &self.timeframe_repository
+    }
+
+    async fn fetch_window(
         &self,
         start_time: DateTime<Utc>,
         end_time: DateTime<Utc>,
     ) -> Result<usize, MarketDataFetcherError> {
-        let mut inserted_count = 0;
-        let mut current_time = start_time.timestamp_millis();
+        self.fetch_market_data(start_time, end_time, false).await
+    }
 
-        while current_time < end_time.timestamp_millis() {
-            let params = [
-                ("pair", self.symbol.to_string()),
-                ("contractType", self.contract_type.to_string()),
-                (
-                    "interval",
-                    Helper::minutes_to_interval(self.timeframe.interval_minutes),
-                ),
-                ("startTime", current_time.to_string()),
-                ("endTime", end_time.timestamp_millis().to_string()),
-                ("limit", FETCH_LIMIT.to_string()),
-            ];
+    // Funding is only saved as done with the candles of the chunk, so the
+    // funding features are complete from the first analysis
+    async fn complete_initialization_chunk(
+        &self,
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+    ) -> Result<(), MarketDataFetcherError> {
+        if self.contract_type == ContractType::Perpetual {
+            self.backfill_funding_rates(start_time, end_time).await?;
+        }
+        Ok(())
+    }
 
-            let data = self
-                .fetch_with_retry(CONTINUOUS_KLINES_API_PATH, &params, 0)
-                .await?;
-            let market_data_array = data.as_array().ok_or(MarketDataFetcherError::Api {
-                status: StatusCode::BAD_REQUEST,
-                body: "Invalid response format".to_string(),
-            })?;
+    // The revision window is upserted along with the new candles
+    fn recent_start(&self, latest_open_time: DateTime<Utc>) -> DateTime<Utc> {
+        latest_open_time
+            - DurationChrono::minutes(
+                i64::from(self.timeframe.interval_minutes) * (REVISION_WINDOW_CANDLES - 1),
+            )
+    }
 
-            if market_data_array.is_empty() {
-                break;
-            }
+    async fn fetch_recent_window(
+        &self,
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+    ) -> Result<usize, MarketDataFetcherError> {
+        self.fetch_market_data(start_time, end_time, true).await
+    }
+}
 
-            let market_data_batch: Result<Vec<MarketData>, _> = market_data_array
-                .iter()
-                .map(|raw_data| self.format_values_to_kline_create_payload(raw_data.clone()))
-                .collect();
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, Request, Respond, ResponseTemplate};
 
-            let market_data_batch = market_data_batch?;
-            let market_data_inserted = self
-                .market_data_repository
-                .create_batch(&market_data_batch)
-                .await
-                .map_err(|e| MarketDataFetcherError::Api {
-                    status: StatusCode::INTERNAL_SERVER_ERROR,
-                    body: e.to_string(),
-                })?;
-            tracing::info!(
-                "Inserted {} elements for {} {} {}",
-                market_data_inserted.len(),
-                self.symbol,
-                Helper::minutes_to_interval(self.timeframe.interval_minutes),
-                self.timeframe.contract_type
-            );
-            if let Some(last_record) = market_data_batch.last() {
-                current_time = last_record.open_time.timestamp_millis() + 1;
-                inserted_count += market_data_batch.len();
-            }
-        }
+    const MINUTE_MS: i64 = 60_000;
+    // 2024-01-01T00:00:00Z
+    const FIRST_OPEN_TIME: i64 = 1_704_067_200_000;
 
-        if inserted_count == 0 {
-            return Err(MarketDataFetcherError::NoDataFound);
+    // 1m klines opened from FIRST_OPEN_TIME on, served like Binance does:
+    // opened from startTime to endTime, at most `limit` of them
+    struct Klines {
+        count: i64,
+    }
+
+    impl Respond for Klines {
+        fn respond(&self, request: &Request) -> ResponseTemplate {
+            let param = |name: &str| {
+                request
+                    .url
+                    .query_pairs()
+                    .find(|(key, _)| key == name)
+                    .and_then(|(_, value)| value.parse::<i64>().ok())
+            };
+            let start_time = param("startTime").unwrap_or(FIRST_OPEN_TIME);
+            let end_time = param("endTime").unwrap_or(i64::MAX);
+            let limit = param("limit").unwrap_or(500) as usize;
+
+            let klines: Vec<Value> = (0..self.count)
+                .map(|i| FIRST_OPEN_TIME + i * MINUTE_MS)
+                .filter(|open_time| (start_time..=end_time).contains(open_time))
+                .take(limit)
+                .map(|open_time| {
+                    serde_json::json!([
+                        open_time,
+                        "100.0",
+                        "101.0",
+                        "99.0",
+                        "100.5",
+                        "12.5",
+                        open_time + MINUTE_MS - 1,
+                        "1256.25",
+                        42,
+                        "6.0",
+                        "603.0",
+                        "0"
+                    ])
+                })
+                .collect();
+            ResponseTemplate::new(200).set_body_json(klines)
         }
-        tracing::info!(
-            "MarketData initalization done {} elements inserted for {} {} {}",
-            inserted_count,
-            self.symbol,
-            Helper::minutes_to_interval(self.timeframe.interval_minutes),
-            self.timeframe.contract_type
+    }
+
+    async fn kline_server(api_path: &str, count: i64) -> MockServer {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path(format!("/{}", api_path)))
+            .respond_with(Klines { count })
+            .mount(&server)
+            .await;
+        server
+    }
+
+    async fn requested(server: &MockServer, name: &str) -> Vec<i64> {
+        server
+            .received_requests()
+            .await
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|request| {
+                request
+                    .url
+                    .query_pairs()
+                    .find(|(key, _)| key == name)
+                    .and_then(|(_, value)| value.parse().ok())
+            })
+            .collect()
+    }
+
+    // Open times of every candle handed to `save`, page by page
+    async fn fetch_pages(
+        api: &BinanceApi,
+        contract_type: ContractType,
+        candles: i64,
+    ) -> Vec<Vec<i64>> {
+        let timeframe = TimeFrame::new("BTCUSDT".to_string(), contract_type, 1);
+        let start_time = DateTime::<Utc>::from_timestamp_millis(FIRST_OPEN_TIME).unwrap();
+        let end_time = start_time + DurationChrono::minutes(candles + 60);
+        let pages = Mutex::new(Vec::new());
+
+        let fetched = api
+            .fetch_klines(&timeframe, start_time, end_time, |batch| {
+                let open_times = batch
+                    .iter()
+                    .map(|candle| candle.open_time.timestamp_millis())
+                    .collect();
+                pages.lock().unwrap().push(open_times);
+                async move { Ok(batch.len()) }
+            })
+            .await
+            .unwrap();
+
+        let pages = pages.into_inner().unwrap();
+        assert_eq!(fetched, pages.iter().map(Vec::len).sum::<usize>());
+        pages
+    }
+
+    #[tokio::test]
+    async fn pages_start_after_the_close_of_the_previous_page() {
+        let server = kline_server(CONTINUOUS_KLINES_API_PATH, 25).await;
+        let api = BinanceApi::with_base_url(
+            format!("{}/", server.uri()),
+            ContractType::Perpetual,
+            Some(10),
         );
 
-        Ok(inserted_count)
+        let pages = fetch_pages(&api, ContractType::Perpetual, 25).await;
+
+        assert_eq!(
+            pages.iter().map(Vec::len).collect::<Vec<_>>(),
+            vec![10, 10, 5]
+        );
+        // Close time of the last candle of a page plus 1ms
+        assert_eq!(
+            requested(&server, "startTime").await,
+            vec![
+                FIRST_OPEN_TIME,
+                FIRST_OPEN_TIME + 10 * MINUTE_MS,
+                FIRST_OPEN_TIME + 20 * MINUTE_MS
+            ]
+        );
     }
 
-    pub async fn initialize_market_data(&self) -> Result<usize, MarketDataFetcherError> {
-        let end_time = Utc::now();
-        let start_time = end_time - DurationChrono::days(self.lookback_days.into());
+    #[tokio::test]
+    async fn short_page_ends_the_window() {
+        let server = kline_server(CONTINUOUS_KLINES_API_PATH, 25).await;
+        let api = BinanceApi::with_base_url(
+            format!("{}/", server.uri()),
+            ContractType::Perpetual,
+            Some(10),
+        );
+
+        fetch_pages(&api, ContractType::Perpetual, 25).await;
 
-        self.fetch_market_data(start_time, end_time).await
+        // No request after the page of 5
+        assert_eq!(requested(&server, "startTime").await.len(), 3);
     }
 
-    pub async fn fetch_recent_market_data(&self) -> Result<usize, MarketDataFetcherError> {
-        let latest_record = self
-            .market_data_repository
-            .find_latest_by_timeframe(&self.timeframe.id)
+    #[tokio::test]
+    async fn full_last_page_is_followed_by_an_empty_one() {
+        let server = kline_server(CONTINUOUS_KLINES_API_PATH, 20).await;
+        let api = BinanceApi::with_base_url(
+            format!("{}/", server.uri()),
+            ContractType::Perpetual,
+            Some(10),
+        );
+
+        let pages = fetch_pages(&api, ContractType::Perpetual, 20).await;
+
+        assert_eq!(pages.len(), 2);
+        assert_eq!(requested(&server, "startTime").await.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn pages_share_no_candle() {
+        let server = kline_server(CONTINUOUS_KLINES_API_PATH, 1000).await;
+        let api = BinanceApi::with_base_url(
+            format!("{}/", server.uri()),
+            ContractType::Perpetual,
+            Some(7),
+        );
+
+        let open_times: Vec<i64> = fetch_pages(&api, ContractType::Perpetual, 1000)
             .await
-            .map_err(|e| MarketDataFetcherError::Api {
-                status: StatusCode::INTERNAL_SERVER_ERROR,
-                body: e.to_string(),
-            })?;
+            .into_iter()
+            .flatten()
+            .collect();
 
-        let start_time = match latest_record {
-            Some(record) => record.open_time + DurationChrono::milliseconds(1),
-            None => Utc::now() - DurationChrono::hours(24),
-        };
+        let expected: Vec<i64> = (0..1000).map(|i| FIRST_OPEN_TIME + i * MINUTE_MS).collect();
+        assert_eq!(open_times, expected);
+    }
 
-        let end_time = Utc::now();
-        let mut retries = 0;
+    #[tokio::test]
+    async fn futures_pages_are_clamped_to_1500_candles() {
+        let server = kline_server(CONTINUOUS_KLINES_API_PATH, 2000).await;
+        let api = BinanceApi::with_base_url(
+            format!("{}/", server.uri()),
+            ContractType::Perpetual,
+            Some(5000),
+        );
 
-        loop {
-            match self.fetch_market_data(start_time, end_time).await {
-                Ok(count) => {
-                    tracing::info!(
-                        "Inserted {} elements for {} {} {}",
-                        count,
-                        self.symbol,
-                        Helper::minutes_to_interval(self.timeframe.interval_minutes),
-                        self.timeframe.contract_type
-                    );
-                    return Ok(count);
-                }
-                Err(MarketDataFetcherError::NoDataFound) if retries < RECENT_DATA_MAX_RETRIES => {
-                    retries += 1;
-                    tracing::warn!(
-                        "No recent data found, retry {} of {}",
-                        retries,
-                        RECENT_DATA_MAX_RETRIES
-                    );
-                    sleep(std::time::Duration::from_millis(RECENT_DATA_RETRY_DELAY)).await;
-                }
-                Err(e) => return Err(e),
-            }
+        let pages = fetch_pages(&api, ContractType::Perpetual, 2000).await;
+
+        assert_eq!(requested(&server, "limit").await, vec![1500, 1500]);
+        assert_eq!(
+            pages.iter().map(Vec::len).collect::<Vec<_>>(),
+            vec![1500, 500]
+        );
+    }
+
+    #[tokio::test]
+    async fn spot_pages_are_clamped_to_1000_candles() {
+        let server = kline_server(SPOT_KLINES_API_PATH, 1500).await;
+        let api =
+            BinanceApi::with_base_url(format!("{}/", server.uri()), ContractType::Spot, Some(5000));
+
+        let pages = fetch_pages(&api, ContractType::Spot, 1500).await;
+
+        assert_eq!(requested(&server, "limit").await, vec![1000, 1000]);
+        assert_eq!(
+            pages.iter().map(Vec::len).collect::<Vec<_>>(),
+            vec![1000, 500]
+        );
+    }
+
+    #[test]
+    fn rising_weight_below_the_high_watermark_keeps_the_pace() {
+        let mut pacing = FetchPacing::new(1000, FUTURES_MAX_WEIGHT);
+
+        for weight in [100, 400, 900, 1200, 1500, 1790] {
+            pacing.observe(weight);
         }
+
+        assert_eq!((pacing.limit, pacing.delay_ms), (1000, 0));
+    }
+
+    #[test]
+    fn weight_above_the_high_watermark_slows_down_until_it_falls() {
+        let mut pacing = FetchPacing::new(1000, FUTURES_MAX_WEIGHT);
+
+        pacing.observe(1800);
+        assert_eq!((pacing.limit, pacing.delay_ms), (500, 200));
+        pacing.observe(2000);
+        assert_eq!((pacing.limit, pacing.delay_ms), (250, 400));
+        pacing.observe(1500);
+        assert_eq!((pacing.limit, pacing.delay_ms), (250, 400));
+        pacing.observe(1000);
+        assert_eq!((pacing.limit, pacing.delay_ms), (375, 200));
+    }
+
+    #[test]
+    fn limits_depend_on_the_market() {
+        let futures = BinanceApi::with_base_url(String::new(), ContractType::Perpetual, None);
+        let spot = BinanceApi::with_base_url(String::new(), ContractType::Spot, None);
+
+        futures.pacing.lock().unwrap().observe(2000);
+        spot.pacing.lock().unwrap().observe(2000);
+
+        assert_eq!(futures.pacing.lock().unwrap().limit, 500);
+        assert_eq!(spot.pacing.lock().unwrap().limit, 1000);
     }
 }
@@ -0,0 +1,306 @@
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Duration as DurationChrono, NaiveDateTime, Utc};
+use rust_decimal::Decimal;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::str::FromStr;
+
+use crate::{
+    models::{
+        market_data::MarketData,
+        timeframe::{ContractType, TimeFrame},
+    },
+    repositories::{
+        market_data_repository::MarketDataRepository, timeframe_repository::TimeFrameRepository,
+    },
+    services::configuration_service::CsvImportConfig,
+};
+
+use super::database_service::DatabaseService;
+
+const IMPORT_CHUNK_SIZE: usize = 1000;
+
+// Loads OHLCV candles of one timeframe from CSV files, existing candles are
+// kept as they are
+pub struct MarketDataImporter {
+    timeframe: TimeFrame,
+    config: CsvImportConfig,
+    market_data_repository: MarketDataRepository,
+}
+
+impl MarketDataImporter {
+    pub async fn new(
+        symbol: String,
+        contract_type: ContractType,
+        interval: String,
+        config: CsvImportConfig,
+    ) -> Result<Self> {
+        let database = DatabaseService::new().await?;
+        let timeframe = TimeFrameRepository::new(database.client)
+            .find_or_create(symbol, contract_type, interval)
+            .await?;
+
+        let database = DatabaseService::new().await?;
+
+        Ok(MarketDataImporter {
+            timeframe,
+            config,
+            market_data_repository: MarketDataRepository::new(database.client),
+        })
+    }
+
+    pub async fn import(&self, path: &str) -> Result<usize> {
+        let file = File::open(path).with_context(|| format!("Cannot open {}", path))?;
+        self.import_lines(BufReader::new(file), path).await
+    }
+
+    // Returns the number of candles inserted, `source` names the input in
+    // errors
+    pub async fn import_lines<R: BufRead>(&self, reader: R, source: &str) -> Result<usize> {
+        let mut inserted = 0;
+        let mut chunk = Vec::with_capacity(IMPORT_CHUNK_SIZE);
+        for (index, line) in reader.lines().enumerate() {
+            let line = line?;
+            let Some(candle) = Self::parse_record(&self.timeframe, &self.config, index, &line)
+                .with_context(|| format!("{} line {}", source, index + 1))?
+            else {
+                continue;
+            };
+            chunk.push(candle);
+
+            if chunk.len() == IMPORT_CHUNK_SIZE {
+                inserted += self
+                    .market_data_repository
+                    .create_batch(&chunk)
+                    .await?
+                    .len();
+                chunk.clear();
+            }
+        }
+        if !chunk.is_empty() {
+            inserted += self
+                .market_data_repository
+                .create_batch(&chunk)
+                .await?
+                .len();
+        }
+
+        Ok(inserted)
+    }
+
+    // Candle of the line at `index`, none for a blank line or a header. A
+    // first line that does not parse is taken for a header.
+    fn parse_record(
+        timeframe: &TimeFrame,
+        config: &CsvImportConfig,
+        index: usize,
+        line: &str,
+    ) -> Result<Option<MarketData>> {
+        if line.trim().is_empty() {
+            return Ok(None);
+        }
+        match Self::parse_line(timeframe, config, line) {
+            Ok(candle) => Ok(Some(candle)),
+            Err(_) if index == 0 => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn parse_line(
+        timeframe: &TimeFrame,
+        config: &CsvImportConfig,
+        line: &str,
+    ) -> Result<MarketData> {
+        let columns: Vec<&str> = line
+            .split(config.delimiter)
+            .map(|column| column.trim().trim_matches('"'))
+            .collect();
+        let column = |index: usize, field: &str| -> Result<&str> {
+            columns
+                .get(index)
+                .copied()
+                .ok_or_else(|| anyhow!("Missing {} column {}", field, index))
+        };
+        let parse_decimal = |index: usize, field: &str| -> Result<Decimal> {
+            let value = column(index, field)?;
+            Decimal::from_str(value)
+                .or_else(|_| Decimal::from_scientific(value))
+                .map_err(|_| anyhow!("Invalid {} decimal {}", field, value))
+        };
+
+        let open_time = Self::parse_time(column(config.open_time, "open_time")?)?;
+        let close_time = open_time + DurationChrono::minutes(timeframe.interval_minutes.into())
+            - DurationChrono::milliseconds(1);
+        let trades = match config.trades {
+            Some(index) => {
+                let value = column(index, "trades")?;
+                value
+                    .parse::<i64>()
+                    .map_err(|_| anyhow!("Invalid trades count {}", value))?
+            }
+            None => 0,
+        };
+
+        let parse_optional = |index: Option<usize>, field: &str| -> Result<Option<Decimal>> {
+            index.map(|index| parse_decimal(index, field)).transpose()
+        };
+
+        let mut candle = MarketData::new(
+            timeframe.id,
+            timeframe.symbol.clone(),
+            timeframe.contract_type.to_string(),
+            open_time,
+            close_time,
+            parse_decimal(config.open, "open")?,
+            parse_decimal(config.close, "close")?,
+            parse_decimal(config.high, "high")?,
+            parse_decimal(config.low, "low")?,
+            parse_decimal(config.volume, "volume")?,
+            trades,
+        );
+        candle.taker_buy_volume = parse_optional(config.taker_buy_volume, "taker_buy_volume")?;
+        candle.taker_buy_quote_volume =
+            parse_optional(config.taker_buy_quote_volume, "taker_buy_quote_volume")?;
+        Ok(candle)
+    }
+
+    // Epoch timestamps in seconds, milliseconds or microseconds (told apart by
+    // magnitude), RFC 3339 or "YYYY-MM-DD HH:MM:SS" taken as UTC
+    fn parse_time(value: &str) -> Result<DateTime<Utc>> {
+        let time = match value.parse::<i64>() {
+            Ok(timestamp) if timestamp >= 100_000_000_000_000 => {
+                DateTime::<Utc>::from_timestamp_micros(timestamp)
+            }
+            Ok(timestamp) if timestamp >= 100_000_000_000 => {
+                DateTime::<Utc>::from_timestamp_millis(timestamp)
+            }
+            Ok(timestamp) => DateTime::<Utc>::from_timestamp(timestamp, 0),
+            Err(_) => DateTime::parse_from_rfc3339(value)
+                .map(|time| time.with_timezone(&Utc))
+                .or_else(|_| {
+                    NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S")
+                        .map(|time| time.and_utc())
+                })
+                .ok(),
+        };
+
+        time.ok_or_else(|| anyhow!("Invalid open time {}", value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use uuid::Uuid;
+
+    // Binance Vision futures kline export, newer files start with the header
+    const HEADER: &str = "open_time,open,high,low,close,volume,close_time,quote_volume,count,\
+                          taker_buy_volume,taker_buy_quote_volume,ignore";
+    const ROWS: [&str; 2] = [
+        "1704067200000,42314.00,42335.80,42289.60,42331.90,311.251,1704067259999,\
+         13170125.39170,3265,188.994,7996716.73330,0",
+        "1704067260000,42331.90,42370.00,42325.00,42358.20,269.774,1704067319999,\
+         11424436.15930,2822,173.069,7329260.36280,0",
+    ];
+
+    fn timeframe() -> TimeFrame {
+        TimeFrame {
+            id: Uuid::nil(),
+            symbol: "BTCUSDT".to_string(),
+            contract_type: ContractType::Perpetual,
+            interval_minutes: 1,
+            created_at: Utc::now(),
+        }
+    }
+
+    fn parse(lines: &[&str]) -> Result<Vec<MarketData>> {
+        let timeframe = timeframe();
+        let config = CsvImportConfig::default();
+        let mut candles = Vec::new();
+        for (index, line) in lines.iter().enumerate() {
+            candles.extend(MarketDataImporter::parse_record(
+                &timeframe, &config, index, line,
+            )?);
+        }
+        Ok(candles)
+    }
+
+    fn decimal(value: &str) -> Decimal {
+        Decimal::from_str(value).unwrap()
+    }
+
+    #[test]
+    fn binance_export_without_header() {
+        let candles = parse(&ROWS).unwrap();
+
+        assert_eq!(candles.len(), 2);
+        let candle = &candles[0];
+        let open_time = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        assert_eq!(candle.open_time, open_time);
+        assert_eq!(
+            candle.close_time,
+            open_time + DurationChrono::milliseconds(59_999)
+        );
+        assert_eq!(
+            (candle.open, candle.high, candle.low, candle.close),
+            (
+                decimal("42314.00"),
+                decimal("42335.80"),
+                decimal("42289.60"),
+                decimal("42331.90")
+            )
+        );
+        assert_eq!(candle.volume, decimal("311.251"));
+        assert_eq!(candle.trades, 3265);
+        assert_eq!(candle.taker_buy_volume, Some(decimal("188.994")));
+        assert_eq!(
+            candle.taker_buy_quote_volume,
+            Some(decimal("7996716.73330"))
+        );
+        assert_eq!(candles[1].open_time, open_time + DurationChrono::minutes(1));
+    }
+
+    #[test]
+    fn binance_export_with_header() {
+        let summary = |candles: Vec<MarketData>| -> Vec<(DateTime<Utc>, Decimal, i64)> {
+            candles
+                .iter()
+                .map(|candle| (candle.open_time, candle.close, candle.trades))
+                .collect()
+        };
+
+        let with_header = parse(&[HEADER, ROWS[0], "", ROWS[1]]).unwrap();
+
+        assert_eq!(summary(with_header), summary(parse(&ROWS).unwrap()));
+    }
+
+    #[test]
+    fn only_the_first_line_can_be_a_header() {
+        let error = parse(&[ROWS[0], HEADER]).unwrap_err();
+
+        assert!(error.to_string().contains("Invalid open time"), "{}", error);
+    }
+
+    #[test]
+    fn open_times_in_every_supported_format() {
+        let expected = Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap();
+
+        for value in [
+            "1735689600",
+            "1735689600000",
+            "1735689600000000",
+            "2025-01-01T00:00:00Z",
+            "2025-01-01T01:00:00+01:00",
+            "2025-01-01 00:00:00",
+        ] {
+            assert_eq!(
+                MarketDataImporter::parse_time(value).unwrap(),
+                expected,
+                "{}",
+                value
+            );
+        }
+        assert!(MarketDataImporter::parse_time("2025-01-01").is_err());
+    }
+}
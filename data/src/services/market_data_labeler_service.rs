@@ -0,0 +1,118 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use rust_decimal::prelude::{FromPrimitive, ToPrimitive};
+use rust_decimal::Decimal;
+use tokio::sync::Mutex;
+use uuid::Uuid;
+
+use crate::{
+    models::triple_barrier_label::TripleBarrierLabel,
+    repositories::{
+        training_feature_repository::TrainingFeatureRepository,
+        triple_barrier_label_repository::TripleBarrierLabelRepository,
+    },
+    services::configuration_service::LabelingConfig,
+    utils::labeling::{BarrierCandle, TripleBarrier},
+};
+
+use super::database_service::DatabaseService;
+
+const LABELING_BATCH_SIZE: usize = 5000;
+
+// Labels analyzed candles with the triple-barrier method once enough
+// candles have followed them to reach a barrier, then regenerates their
+// training features with the label
+pub struct MarketDataLabeler {
+    triple_barrier_label_repository: TripleBarrierLabelRepository,
+    training_feature_repository: TrainingFeatureRepository,
+    timeframe_id: Uuid,
+    config: LabelingConfig,
+}
+
+impl MarketDataLabeler {
+    pub async fn new(timeframe_id: Uuid, config: LabelingConfig) -> Result<Self> {
+        let database = DatabaseService::new().await?;
+        let client = Arc::new(Mutex::new(database.client));
+
+        Ok(MarketDataLabeler {
+            triple_barrier_label_repository: TripleBarrierLabelRepository::from_shared(
+                client.clone(),
+            ),
+            training_feature_repository: TrainingFeatureRepository::from_shared(client),
+            timeframe_id,
+            config,
+        })
+    }
+
+    pub async fn label_market_data(&self) -> Result<u64> {
+        let barrier = TripleBarrier {
+            profit_target: self.config.profit_target,
+            stop_loss: self.config.stop_loss,
+            max_holding_candles: self.config.max_holding_candles.max(1) as usize,
+        };
+
+        let mut total = 0;
+        loop {
+            let window = self
+                .triple_barrier_label_repository
+                .find_labeling_window(
+                    &self.timeframe_id,
+                    (LABELING_BATCH_SIZE + barrier.max_holding_candles) as i64,
+                )
+                .await?;
+            let future: Vec<BarrierCandle> = window
+                .iter()
+                .map(|c| BarrierCandle {
+                    high: c.high.to_f64().unwrap_or_default(),
+                    low: c.low.to_f64().unwrap_or_default(),
+                    close: c.close.to_f64().unwrap_or_default(),
+                })
+                .collect();
+
+            let labels: Vec<TripleBarrierLabel> = window
+                .iter()
+                .enumerate()
+                .filter(|(_, candle)| candle.pending)
+                .filter_map(|(i, candle)| {
+                    let label = barrier.label(future[i].close, &future[i + 1..])?;
+                    Some(TripleBarrierLabel {
+                        market_data_id: candle.market_data_id,
+                        timeframe_id: self.timeframe_id,
+                        open_time: candle.open_time,
+                        label: label.label.sign(),
+                        barrier: label.barrier.to_string(),
+                        realized_return: Decimal::from_f64(label.realized_return)
+                            .unwrap_or_default(),
+                        holding_candles: label.holding_candles as i32,
+                        profit_target: Decimal::from_f64(barrier.profit_target).unwrap_or_default(),
+                        stop_loss: Decimal::from_f64(barrier.stop_loss).unwrap_or_default(),
+                        max_holding_candles: barrier.max_holding_candles as i32,
+                    })
+                })
+                .collect();
+
+            if labels.is_empty() {
+                break;
+            }
+            total += self
+                .triple_barrier_label_repository
+                .create_batch(&labels)
+                .await?;
+
+            // The analyzer wrote these rows before the labels existed
+            let ids: Vec<Uuid> = labels.iter().map(|label| label.market_data_id).collect();
+            self.training_feature_repository.refresh(&ids).await?;
+        }
+
+        if total > 0 {
+            tracing::info!(
+                "Labeled {} candles for timeframe {}",
+                total,
+                self.timeframe_id
+            );
+        }
+
+        Ok(total)
+    }
+}
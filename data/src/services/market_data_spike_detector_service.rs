@@ -0,0 +1,275 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use rust_decimal::prelude::ToPrimitive;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use crate::{
+    models::{market_data::MarketData, timeframe::TimeFrame},
+    repositories::market_data_repository::MarketDataRepository,
+    services::configuration_service::AlertConfig,
+    utils::rolling::WindowStats,
+};
+
+use super::database_service::DatabaseService;
+
+const BASELINE_CANDLES: i32 = 100;
+const MIN_BASELINE_CANDLES: usize = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SpikeMetric {
+    Return,
+    Volume,
+    Spread,
+}
+
+impl fmt::Display for SpikeMetric {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Return => write!(f, "return"),
+            Self::Volume => write!(f, "volume"),
+            Self::Spread => write!(f, "spread"),
+        }
+    }
+}
+
+// Fires on new candles whose return, volume or high-low spread deviate from
+// the preceding candles by more than the configured number of sigmas
+pub struct MarketDataSpikeDetector {
+    market_data_repository: Arc<MarketDataRepository>,
+    timeframe: TimeFrame,
+    config: AlertConfig,
+    last_checked: Mutex<Option<DateTime<Utc>>>,
+}
+
+impl MarketDataSpikeDetector {
+    pub async fn new(timeframe: TimeFrame, config: AlertConfig) -> Result<Self> {
+        let database = DatabaseService::new().await?;
+        let market_data_repository = MarketDataRepository::new(database.client);
+
+        Ok(MarketDataSpikeDetector {
+            market_data_repository: Arc::new(market_data_repository),
+            timeframe,
+            config,
+            last_checked: Mutex::new(None),
+        })
+    }
+
+    pub async fn detect_spikes(&self) -> Result<usize> {
+        // Newest first
+        let candles = self
+            .market_data_repository
+            .get_historical_data(
+                self.timeframe.id,
+                &self.timeframe.symbol,
+                &self.timeframe.contract_type.to_string(),
+                Utc::now(),
+                BASELINE_CANDLES + 1,
+            )
+            .await?;
+        let Some(latest) = candles.first() else {
+            return Ok(0);
+        };
+
+        // Only the latest candle on the first run, every newer candle afterwards
+        let last_checked = self.last_checked.lock().unwrap().replace(latest.open_time);
+        let new_candles = match last_checked {
+            Some(last) => candles.iter().take_while(|d| d.open_time > last).count(),
+            None => 1,
+        };
+
+        let mut alert_count = 0;
+        for i in 0..new_candles {
+            // The candle under check followed by its baseline
+            let window = &candles[i..];
+            if window.len() <= MIN_BASELINE_CANDLES {
+                break;
+            }
+
+            for (metric, sigma, threshold) in Self::spikes(window, &self.config) {
+                alert_count += 1;
+                tracing::warn!(
+                    target: "alerts",
+                    "{} spike on {} {} {}m at {}: {:.2} sigma (threshold {:.2})",
+                    metric,
+                    self.timeframe.symbol,
+                    self.timeframe.contract_type,
+                    self.timeframe.interval_minutes,
+                    candles[i].open_time,
+                    sigma,
+                    threshold
+                );
+            }
+        }
+
+        Ok(alert_count)
+    }
+
+    // Metrics of the first candle of `window` deviating from the following
+    // candles by at least their threshold, with their sigma and threshold
+    fn spikes(window: &[MarketData], config: &AlertConfig) -> Vec<(SpikeMetric, f64, f64)> {
+        [
+            (
+                SpikeMetric::Return,
+                config.return_sigma,
+                Self::returns(window),
+            ),
+            (
+                SpikeMetric::Volume,
+                config.volume_sigma,
+                Self::volumes(window),
+            ),
+            (
+                SpikeMetric::Spread,
+                config.spread_sigma,
+                Self::spreads(window),
+            ),
+        ]
+        .into_iter()
+        .filter_map(|(metric, threshold, values)| {
+            let sigma = Self::sigma(&values)?;
+            (sigma.abs() >= threshold).then_some((metric, sigma, threshold))
+        })
+        .collect()
+    }
+
+    // Distance of the first value from the mean of the others, in standard
+    // deviations. Missing values are left out of the baseline, a missing
+    // first value is not checked.
+    fn sigma(values: &[Option<f64>]) -> Option<f64> {
+        let (current, baseline) = values.split_first()?;
+        let current = (*current)?;
+        let baseline: Vec<f64> = baseline.iter().flatten().copied().collect();
+        let window = WindowStats::from_values(baseline.len(), baseline.iter().copied());
+        let std_dev = window.std_dev();
+        (std_dev > 0.0).then(|| (current - window.mean()) / std_dev)
+    }
+
+    // Returns against the previous close, none after a zero close
+    fn returns(data: &[MarketData]) -> Vec<Option<f64>> {
+        data.windows(2)
+            .map(|w| {
+                (!w[1].close.is_zero())
+                    .then(|| ((w[0].close - w[1].close) / w[1].close).to_f64())
+                    .flatten()
+            })
+            .collect()
+    }
+
+    fn volumes(data: &[MarketData]) -> Vec<Option<f64>> {
+        data.iter().map(|d| d.volume.to_f64()).collect()
+    }
+
+    // Candles carry no bid/ask, the high-low range relative to the open stands
+    // in, none for a zero open
+    fn spreads(data: &[MarketData]) -> Vec<Option<f64>> {
+        data.iter()
+            .map(|d| {
+                (!d.open.is_zero())
+                    .then(|| ((d.high - d.low) / d.open).to_f64())
+                    .flatten()
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, TimeZone};
+    use rust_decimal::Decimal;
+    use uuid::Uuid;
+
+    const CONFIG: AlertConfig = AlertConfig {
+        return_sigma: 3.0,
+        volume_sigma: 3.0,
+        spread_sigma: 3.0,
+    };
+
+    fn candle(minutes_ago: i64, open: i64, close: i64, volume: i64) -> MarketData {
+        let open_time =
+            Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap() - Duration::minutes(minutes_ago);
+        MarketData::new(
+            Uuid::nil(),
+            "BTCUSDT".to_string(),
+            "PERPETUAL".to_string(),
+            open_time,
+            open_time + Duration::milliseconds(59_999),
+            Decimal::from(open),
+            Decimal::from(close),
+            Decimal::from(open.max(close) + 1),
+            Decimal::from(open.min(close) - 1),
+            Decimal::from(volume),
+            100,
+        )
+    }
+
+    // `latest` followed by 30 candles swinging between 100 and 101, newest
+    // first
+    fn window(latest: MarketData) -> Vec<MarketData> {
+        let mut window = vec![latest];
+        window.extend((1..=30).map(|i| {
+            let (open, close) = if i % 2 == 0 { (100, 101) } else { (101, 100) };
+            candle(i, open, close, 10 + i % 3)
+        }));
+        window
+    }
+
+    fn metrics(window: &[MarketData]) -> Vec<SpikeMetric> {
+        MarketDataSpikeDetector::spikes(window, &CONFIG)
+            .into_iter()
+            .map(|(metric, _, _)| metric)
+            .collect()
+    }
+
+    #[test]
+    fn candle_like_its_baseline_raises_nothing() {
+        assert!(metrics(&window(candle(0, 100, 101, 11))).is_empty());
+    }
+
+    #[test]
+    fn volume_spike_alone() {
+        let spikes = MarketDataSpikeDetector::spikes(&window(candle(0, 100, 101, 100)), &CONFIG);
+
+        assert_eq!(spikes.len(), 1);
+        let (metric, sigma, threshold) = spikes[0];
+        assert_eq!(metric, SpikeMetric::Volume);
+        assert!(sigma > 3.0);
+        assert_eq!(threshold, 3.0);
+    }
+
+    #[test]
+    fn price_jump_spikes_the_return_and_the_spread() {
+        let spikes = MarketDataSpikeDetector::spikes(&window(candle(0, 100, 90, 11)), &CONFIG);
+
+        let metrics: Vec<SpikeMetric> = spikes.iter().map(|(metric, _, _)| *metric).collect();
+        assert_eq!(metrics, vec![SpikeMetric::Return, SpikeMetric::Spread]);
+        // A drop is a negative deviation
+        assert!(spikes[0].1 < -3.0);
+    }
+
+    #[test]
+    fn sigma_leaves_missing_values_out() {
+        assert_eq!(
+            MarketDataSpikeDetector::sigma(&[Some(3.0), Some(1.0), None, Some(-1.0)]),
+            Some(3.0)
+        );
+        assert_eq!(
+            MarketDataSpikeDetector::sigma(&[None, Some(1.0), Some(-1.0)]),
+            None
+        );
+        // A flat baseline has no deviation to compare against
+        assert_eq!(
+            MarketDataSpikeDetector::sigma(&[Some(5.0), Some(1.0), Some(1.0)]),
+            None
+        );
+    }
+
+    #[test]
+    fn zero_prices_give_no_return_or_spread() {
+        let data = [candle(0, 100, 101, 10), candle(1, 0, 0, 10)];
+
+        assert_eq!(MarketDataSpikeDetector::returns(&data), vec![None]);
+        assert_eq!(MarketDataSpikeDetector::spreads(&data)[1], None);
+    }
+}
@@ -0,0 +1,163 @@
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use futures_util::StreamExt;
+use rust_decimal::Decimal;
+use serde_json::Value;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{broadcast, mpsc};
+use tokio::time::sleep;
+use tokio_tungstenite::tungstenite::Message;
+
+use crate::{
+    models::{market_data::MarketData, timeframe::ContractType},
+    repositories::market_data_repository::MarketDataRepository,
+    utils::{
+        helper::Helper,
+        http::{self, Endpoint},
+    },
+};
+
+use super::database_service::DatabaseService;
+use super::exchange_service::Exchange;
+use super::market_data_fetcher_service::{MarketDataFetcher, MarketDataFetcherError};
+
+const RECONNECT_DELAY: u64 = 1000; // 1 second in milliseconds
+const MAX_RECONNECT_DELAY: u64 = 60000; // 1 minute in milliseconds
+
+// Writes closed candles from the Binance continuousKline stream (kline
+// stream for spot pairs) as they close. Every (re)connection first backfills the gap over REST.
+// Each stored candle is signalled on `candle_closed` so it gets analyzed right away.
+pub struct MarketDataStreamer {
+    fetcher: Arc<MarketDataFetcher>,
+    market_data_repository: MarketDataRepository,
+    candle_closed: mpsc::Sender<()>,
+}
+
+impl MarketDataStreamer {
+    pub async fn new(
+        fetcher: Arc<MarketDataFetcher>,
+        candle_closed: mpsc::Sender<()>,
+    ) -> Result<Self> {
+        let database = DatabaseService::new().await?;
+
+        Ok(MarketDataStreamer {
+            fetcher,
+            market_data_repository: MarketDataRepository::new(database.client),
+            candle_closed,
+        })
+    }
+
+    fn stream_url(&self) -> String {
+        let interval = Helper::minutes_to_interval(self.fetcher.timeframe.interval_minutes);
+        match self.fetcher.contract_type {
+            ContractType::Spot => format!(
+                "{}{}@kline_{}",
+                Endpoint::BinanceSpotStream.url(),
+                self.fetcher.symbol.to_lowercase(),
+                interval
+            ),
+            _ => format!(
+                "{}{}_{}@continuousKline_{}",
+                Endpoint::BinanceFuturesStream.url(),
+                self.fetcher.symbol.to_lowercase(),
+                self.fetcher.contract_type.to_string().to_lowercase(),
+                interval
+            ),
+        }
+    }
+
+    pub async fn run(&self, mut shutdown: broadcast::Receiver<()>) {
+        let mut delay = RECONNECT_DELAY;
+        loop {
+            tokio::select! {
+                result = self.stream() => match result {
+                    Ok(()) => {
+                        tracing::warn!("Kline stream closed for {}", self.stream_url());
+                        delay = RECONNECT_DELAY;
+                    }
+                    Err(e) => {
+                        tracing::error!("Kline stream failed for {}: {}", self.stream_url(), e);
+                        delay = (delay * 2).min(MAX_RECONNECT_DELAY);
+                    }
+                },
+                _ = shutdown.recv() => return,
+            }
+
+            tokio::select! {
+                _ = sleep(Duration::from_millis(delay)) => {}
+                _ = shutdown.recv() => return,
+            }
+        }
+    }
+
+    async fn stream(&self) -> Result<()> {
+        let mut socket = http::connect_stream(&self.stream_url()).await?;
+        tracing::info!("Connected to {}", self.stream_url());
+
+        // Candles closed while disconnected
+        match self.fetcher.fetch_recent_market_data().await {
+            Ok(_) | Err(MarketDataFetcherError::NoDataFound) => {}
+            Err(e) => return Err(e.into()),
+        }
+
+        while let Some(message) = socket.next().await {
+            let text = match message? {
+                Message::Text(text) => text,
+                Message::Close(_) => break,
+                _ => continue,
+            };
+
+            let payload: Value = serde_json::from_str(&text)?;
+            if let Some(candle) = self.parse_closed_kline(&payload["k"])? {
+                let inserted = self.market_data_repository.create_batch(&[candle]).await?;
+                // A full channel already holds a pending analysis
+                if !inserted.is_empty() {
+                    let _ = self.candle_closed.try_send(());
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    // None while the candle is still open
+    fn parse_closed_kline(&self, kline: &Value) -> Result<Option<MarketData>> {
+        if !kline["x"].as_bool().unwrap_or(false) {
+            return Ok(None);
+        }
+
+        let parse_time = |field: &str| -> Result<DateTime<Utc>> {
+            kline[field]
+                .as_i64()
+                .and_then(DateTime::<Utc>::from_timestamp_millis)
+                .ok_or_else(|| anyhow!("Invalid kline {} timestamp", field))
+        };
+        let parse_decimal = |field: &str| -> Result<Decimal> {
+            kline[field]
+                .as_str()
+                .and_then(|s| Decimal::from_str(s).ok())
+                .ok_or_else(|| anyhow!("Invalid kline {} decimal", field))
+        };
+
+        Ok(Some(
+            MarketData::new(
+                self.fetcher.timeframe.id,
+                self.fetcher.symbol.clone(),
+                self.fetcher.contract_type.to_string(),
+                parse_time("t")?,
+                parse_time("T")?,
+                parse_decimal("o")?,
+                parse_decimal("c")?,
+                parse_decimal("h")?,
+                parse_decimal("l")?,
+                parse_decimal("v")?,
+                kline["n"]
+                    .as_i64()
+                    .ok_or_else(|| anyhow!("Invalid kline trades count"))?,
+            )
+            .with_taker_buy_volumes(parse_decimal("V")?, parse_decimal("Q")?),
+        ))
+    }
+}
@@ -2,3 +2,21 @@ pub mod database_service;
 pub mod market_data_fetcher_service;
 pub mod market_data_analyzer_service;
 pub mod configuration_service;
+pub mod market_data_archiver_service;
+pub mod market_data_spike_detector_service;
+pub mod market_data_aggregator_service;
+pub mod prediction_outcome_service;
+pub mod market_data_labeler_service;
+pub mod market_data_streamer_service;
+pub mod order_book_collector_service;
+pub mod liquidation_collector_service;
+pub mod okx_fetcher_service;
+pub mod coinbase_fetcher_service;
+pub mod binance_account_service;
+pub mod account_sync_service;
+pub mod market_data_importer_service;
+pub mod binance_vision_loader_service;
+pub mod clock_sync_service;
+pub mod exchange_info_service;
+pub mod storage_policy_service;
+pub mod exchange_service;
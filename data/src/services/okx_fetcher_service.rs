@@ -0,0 +1,278 @@
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Duration as DurationChrono, Utc};
+use reqwest::StatusCode;
+use rust_decimal::Decimal;
+use serde_json::Value;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use crate::{
+    models::{
+        market_data::MarketData,
+        timeframe::{ContractType, TimeFrame},
+    },
+    repositories::{
+        market_data_repository::MarketDataRepository, timeframe_repository::TimeFrameRepository,
+    },
+    utils::{
+        helper::Helper,
+        http::{self, Endpoint},
+        retry::RetryPolicy,
+    },
+};
+
+use super::database_service::DatabaseService;
+use super::exchange_service::Exchange;
+use super::market_data_fetcher_service::MarketDataFetcherError;
+
+const OKX_HISTORY_CANDLES_API_PATH: &str = "api/v5/market/history-candles";
+const OKX_FETCH_LIMIT: i32 = 100;
+
+// Candles of an OKX swap or spot instrument (symbol is the instId, e.g.
+// BTC-USDT-SWAP). OKX pages backwards from an `after` timestamp, newest
+// first, and does not report trade counts, so `trades` is stored as 0.
+pub struct OkxMarketDataFetcher {
+    client: reqwest::Client,
+    symbol: String,
+    contract_type: ContractType,
+    bar: &'static str,
+    pub timeframe: TimeFrame,
+    lookback_days: u32,
+    market_data_repository: Arc<MarketDataRepository>,
+    timeframe_repository: TimeFrameRepository,
+    dry_run: bool,
+}
+
+impl OkxMarketDataFetcher {
+    pub async fn new(
+        symbol: String,
+        contract_type: ContractType,
+        interval: String,
+        lookback_days: u32,
+        dry_run: bool,
+    ) -> Result<Self> {
+        if !matches!(contract_type, ContractType::Perpetual | ContractType::Spot) {
+            return Err(anyhow!("OKX only supports PERPETUAL and SPOT pairs"));
+        }
+        let minutes = Helper::interval_to_minutes(&interval)
+            .ok_or_else(|| anyhow!("Invalid interval {}", interval))?;
+        let bar = Self::bar(minutes).ok_or_else(|| anyhow!("OKX has no {} candles", interval))?;
+
+        let database = DatabaseService::new().await?;
+        let timeframe_repository = TimeFrameRepository::new(database.client);
+
+        let database = DatabaseService::new().await?;
+        let market_data_repository = MarketDataRepository::new(database.client);
+
+        let timeframe = if dry_run {
+            timeframe_repository
+                .find_or_new(symbol.clone(), contract_type.clone(), interval)
+                .await?
+        } else {
+            timeframe_repository
+                .find_or_create(symbol.clone(), contract_type.clone(), interval)
+                .await?
+        };
+
+        Ok(OkxMarketDataFetcher {
+            client: http::client(),
+            symbol,
+            contract_type,
+            bar,
+            timeframe,
+            lookback_days,
+            market_data_repository: Arc::new(market_data_repository),
+            timeframe_repository,
+            dry_run,
+        })
+    }
+
+    // Dry runs print the batch, which only holds confirmed candles, instead
+    // of inserting it
+    async fn save_batch(&self, batch: &[MarketData]) -> Result<usize, MarketDataFetcherError> {
+        if self.dry_run {
+            batch.iter().for_each(|candle| println!("{}", candle));
+            return Ok(batch.len());
+        }
+        self.market_data_repository
+            .create_batch(batch)
+            .await
+            .map(|ids| ids.len())
+            .map_err(|e| MarketDataFetcherError::Api {
+                status: StatusCode::INTERNAL_SERVER_ERROR,
+                body: e.to_string(),
+            })
+    }
+
+    // OKX bar names, hours and above in upper case with a utc suffix from 6H
+    // on, as their default candles follow Hong Kong time
+    fn bar(interval_minutes: i32) -> Option<&'static str> {
+        match interval_minutes {
+            1 => Some("1m"),
+            3 => Some("3m"),
+            5 => Some("5m"),
+            15 => Some("15m"),
+            30 => Some("30m"),
+            60 => Some("1H"),
+            120 => Some("2H"),
+            240 => Some("4H"),
+            360 => Some("6Hutc"),
+            720 => Some("12Hutc"),
+            1440 => Some("1Dutc"),
+            4320 => Some("3Dutc"),
+            10080 => Some("1Wutc"),
+            _ => None,
+        }
+    }
+
+    // Walks back from `end_time` page by page until `start_time`, keeping
+    // confirmed candles only
+    async fn fetch_market_data(
+        &self,
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+    ) -> Result<usize, MarketDataFetcherError> {
+        let mut after = end_time.timestamp_millis();
+        let mut inserted_count = 0;
+
+        while after > start_time.timestamp_millis() {
+            let params = [
+                ("instId", self.symbol.to_string()),
+                ("bar", self.bar.to_string()),
+                ("after", after.to_string()),
+                ("limit", OKX_FETCH_LIMIT.to_string()),
+            ];
+            let data = self.fetch_with_retry(&params).await?;
+            let candles = data["data"].as_array().ok_or(MarketDataFetcherError::Api {
+                status: StatusCode::BAD_REQUEST,
+                body: "Invalid OKX response format".to_string(),
+            })?;
+
+            let mut market_data_batch = Vec::with_capacity(candles.len());
+            for candle in candles {
+                let market_data = self.parse_candle(candle)?;
+                after = after.min(market_data.open_time.timestamp_millis());
+                if candle[8].as_str() == Some("1") && market_data.open_time >= start_time {
+                    market_data_batch.push(market_data);
+                }
+            }
+
+            if !market_data_batch.is_empty() {
+                self.save_batch(&market_data_batch).await?;
+                inserted_count += market_data_batch.len();
+            }
+            if (candles.len() as i32) < OKX_FETCH_LIMIT {
+                break;
+            }
+        }
+
+        if inserted_count == 0 {
+            return Err(MarketDataFetcherError::NoDataFound);
+        }
+        tracing::info!(
+            "Inserted {} OKX elements for {} {} {}",
+            inserted_count,
+            self.symbol,
+            self.bar,
+            self.contract_type
+        );
+        Ok(inserted_count)
+    }
+
+    // Errors come back as a non-zero `code` with the HTTP status mostly 200
+    async fn fetch_with_retry(
+        &self,
+        params: &[(&str, String)],
+    ) -> Result<Value, MarketDataFetcherError> {
+        let url = format!("{}{}", Endpoint::OkxApi.url(), OKX_HISTORY_CANDLES_API_PATH);
+        let response = RetryPolicy::DEFAULT
+            .send(|| self.client.get(&url).query(&params))
+            .await
+            .map_err(MarketDataFetcherError::Request)?;
+
+        let status = response.status();
+        let data: Value = response
+            .json()
+            .await
+            .map_err(MarketDataFetcherError::Json)?;
+        if data["code"].as_str() != Some("0") {
+            let body = data["msg"].as_str().unwrap_or_default().to_string();
+            tracing::error!(%status, %body, "OKX API request failed");
+            return Err(MarketDataFetcherError::Api { status, body });
+        }
+        Ok(data)
+    }
+
+    // [ts, o, h, l, c, vol, volCcy, volCcyQuote, confirm], swap vol counts
+    // contracts so the base currency volume is volCcy, spot vol is in base
+    fn parse_candle(&self, candle: &Value) -> Result<MarketData, MarketDataFetcherError> {
+        let parse_decimal =
+            |index: usize, field: &str| -> Result<Decimal, MarketDataFetcherError> {
+                candle[index]
+                    .as_str()
+                    .and_then(|s| Decimal::from_str(s).ok())
+                    .ok_or_else(|| MarketDataFetcherError::Api {
+                        status: StatusCode::BAD_REQUEST,
+                        body: format!("Invalid OKX {} decimal", field),
+                    })
+            };
+
+        let open_time = candle[0]
+            .as_str()
+            .and_then(|s| s.parse::<i64>().ok())
+            .and_then(DateTime::<Utc>::from_timestamp_millis)
+            .ok_or_else(|| MarketDataFetcherError::Api {
+                status: StatusCode::BAD_REQUEST,
+                body: "Invalid OKX candle timestamp".to_string(),
+            })?;
+        let close_time = open_time
+            + DurationChrono::minutes(self.timeframe.interval_minutes.into())
+            - DurationChrono::milliseconds(1);
+        let volume_index = match self.contract_type {
+            ContractType::Spot => 5,
+            _ => 6,
+        };
+
+        Ok(MarketData::new(
+            self.timeframe.id,
+            self.symbol.clone(),
+            self.contract_type.to_string(),
+            open_time,
+            close_time,
+            parse_decimal(1, "open")?,
+            parse_decimal(4, "close")?,
+            parse_decimal(2, "high")?,
+            parse_decimal(3, "low")?,
+            parse_decimal(volume_index, "volume")?,
+            0,
+        ))
+    }
+}
+
+impl Exchange for OkxMarketDataFetcher {
+    const NAME: &'static str = "OKX";
+
+    fn timeframe(&self) -> &TimeFrame {
+        &self.timeframe
+    }
+
+    fn lookback_days(&self) -> u32 {
+        self.lookback_days
+    }
+
+    fn market_data_repository(&self) -> &MarketDataRepository {
+        &self.market_data_repository
+    }
+
+    fn timeframe_repository(&self) -> &TimeFrameRepository {
+        &self.timeframe_repository
+    }
+
+    async fn fetch_window(
+        &self,
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+    ) -> Result<usize, MarketDataFetcherError> {
+        self.fetch_market_data(start_time, end_time).await
+    }
+}
@@ -0,0 +1,202 @@
+use anyhow::{anyhow, Result};
+use chrono::Utc;
+use futures_util::StreamExt;
+use rust_decimal::Decimal;
+use serde_json::Value;
+use std::str::FromStr;
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tokio::time::{interval, sleep};
+use tokio_tungstenite::tungstenite::Message;
+use uuid::Uuid;
+
+use crate::{
+    models::{order_book_snapshot::OrderBookSnapshot, timeframe::ContractType},
+    repositories::order_book_repository::OrderBookRepository,
+    services::configuration_service::OrderBookConfig,
+    utils::{
+        http::{self, Endpoint},
+        order_book::{DepthUpdate, DepthUpdateOutcome, OrderBook},
+        retry::RetryPolicy,
+    },
+};
+
+use super::database_service::DatabaseService;
+
+const DEPTH_API_PATH: &str = "fapi/v1/depth";
+const SNAPSHOT_DEPTH_LIMIT: u32 = 1000;
+const RECONNECT_DELAY: u64 = 1000; // 1 second in milliseconds
+const MAX_RECONNECT_DELAY: u64 = 60000; // 1 minute in milliseconds
+
+// Keeps a local order book of a pair from the REST depth snapshot and the
+// diff-depth stream, and stores its best levels at a fixed interval
+pub struct OrderBookCollector {
+    client: reqwest::Client,
+    symbol: String,
+    contract_type: ContractType,
+    config: OrderBookConfig,
+    order_book_repository: OrderBookRepository,
+}
+
+impl OrderBookCollector {
+    pub async fn new(
+        symbol: String,
+        contract_type: ContractType,
+        config: OrderBookConfig,
+    ) -> Result<Self> {
+        let database = DatabaseService::new().await?;
+
+        Ok(OrderBookCollector {
+            client: http::client(),
+            symbol,
+            contract_type,
+            config,
+            order_book_repository: OrderBookRepository::new(database.client),
+        })
+    }
+
+    fn stream_url(&self) -> String {
+        format!(
+            "{}{}@depth@100ms",
+            Endpoint::BinanceFuturesStream.url(),
+            self.symbol.to_lowercase()
+        )
+    }
+
+    pub async fn run(&self, mut shutdown: broadcast::Receiver<()>) {
+        let mut delay = RECONNECT_DELAY;
+        loop {
+            tokio::select! {
+                result = self.collect() => match result {
+                    Ok(()) => {
+                        tracing::warn!("Depth stream closed for {}", self.stream_url());
+                        delay = RECONNECT_DELAY;
+                    }
+                    Err(e) => {
+                        tracing::error!("Depth stream failed for {}: {}", self.stream_url(), e);
+                        delay = (delay * 2).min(MAX_RECONNECT_DELAY);
+                    }
+                },
+                _ = shutdown.recv() => return,
+            }
+
+            tokio::select! {
+                _ = sleep(Duration::from_millis(delay)) => {}
+                _ = shutdown.recv() => return,
+            }
+        }
+    }
+
+    async fn collect(&self) -> Result<()> {
+        // Connect first, the socket buffers the updates while the snapshot loads
+        let mut socket = http::connect_stream(&self.stream_url()).await?;
+        tracing::info!("Connected to {}", self.stream_url());
+
+        let mut book = self.fetch_snapshot().await?;
+        let mut ticker = interval(Duration::from_secs(
+            self.config.snapshot_interval_seconds.max(1),
+        ));
+
+        loop {
+            tokio::select! {
+                message = socket.next() => {
+                    let text = match message {
+                        Some(message) => match message? {
+                            Message::Text(text) => text,
+                            Message::Close(_) => return Ok(()),
+                            _ => continue,
+                        },
+                        None => return Ok(()),
+                    };
+
+                    let payload: Value = serde_json::from_str(&text)?;
+                    if book.apply(Self::parse_update(&payload)?) == DepthUpdateOutcome::Gap {
+                        tracing::warn!("Depth update gap for {}, reloading the book", self.symbol);
+                        book = self.fetch_snapshot().await?;
+                    }
+                }
+                _ = ticker.tick() => {
+                    self.save_snapshot(&book).await?;
+                }
+            }
+        }
+    }
+
+    async fn fetch_snapshot(&self) -> Result<OrderBook> {
+        let url = format!("{}{}", Endpoint::BinanceFuturesApi.url(), DEPTH_API_PATH);
+        let payload: Value = RetryPolicy::DEFAULT
+            .send(|| {
+                self.client.get(&url).query(&[
+                    ("symbol", self.symbol.clone()),
+                    ("limit", SNAPSHOT_DEPTH_LIMIT.to_string()),
+                ])
+            })
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        Ok(OrderBook::from_snapshot(
+            payload["lastUpdateId"]
+                .as_i64()
+                .ok_or_else(|| anyhow!("Invalid depth lastUpdateId"))?,
+            Self::parse_levels(&payload["bids"])?,
+            Self::parse_levels(&payload["asks"])?,
+        ))
+    }
+
+    async fn save_snapshot(&self, book: &OrderBook) -> Result<u64> {
+        let (bid_prices, bid_quantities) =
+            book.top_bids(self.config.depth_levels).into_iter().unzip();
+        let (ask_prices, ask_quantities) =
+            book.top_asks(self.config.depth_levels).into_iter().unzip();
+
+        self.order_book_repository
+            .create(&OrderBookSnapshot {
+                id: Uuid::new_v4(),
+                symbol: self.symbol.clone(),
+                contract_type: self.contract_type.to_string(),
+                snapshot_time: Utc::now(),
+                last_update_id: book.last_update_id(),
+                bid_prices,
+                bid_quantities,
+                ask_prices,
+                ask_quantities,
+                imbalance: book.imbalance(self.config.depth_levels),
+            })
+            .await
+    }
+
+    fn parse_update(payload: &Value) -> Result<DepthUpdate> {
+        let parse_id = |field: &str| -> Result<i64> {
+            payload[field]
+                .as_i64()
+                .ok_or_else(|| anyhow!("Invalid depth update {} id", field))
+        };
+
+        Ok(DepthUpdate {
+            first_update_id: parse_id("U")?,
+            final_update_id: parse_id("u")?,
+            previous_final_update_id: parse_id("pu")?,
+            bids: Self::parse_levels(&payload["b"])?,
+            asks: Self::parse_levels(&payload["a"])?,
+        })
+    }
+
+    // [["price", "quantity"], ...]
+    fn parse_levels(levels: &Value) -> Result<Vec<(Decimal, Decimal)>> {
+        let parse_decimal = |value: &Value| -> Result<Decimal> {
+            value
+                .as_str()
+                .and_then(|s| Decimal::from_str(s).ok())
+                .ok_or_else(|| anyhow!("Invalid depth level {}", value))
+        };
+
+        levels
+            .as_array()
+            .ok_or_else(|| anyhow!("Invalid depth levels"))?
+            .iter()
+            .map(|level| Ok((parse_decimal(&level[0])?, parse_decimal(&level[1])?)))
+            .collect()
+    }
+}
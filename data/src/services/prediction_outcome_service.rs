@@ -0,0 +1,46 @@
+use anyhow::Result;
+use uuid::Uuid;
+
+use crate::repositories::model_prediction_repository::ModelPredictionRepository;
+
+use super::database_service::DatabaseService;
+
+const SCOREBOARD_WINDOW: i32 = 500;
+
+pub struct PredictionOutcomeTracker {
+    model_prediction_repository: ModelPredictionRepository,
+    timeframe_id: Uuid,
+    horizon_candles: i32,
+}
+
+impl PredictionOutcomeTracker {
+    pub async fn new(timeframe_id: Uuid, horizon_candles: u32) -> Result<Self> {
+        let database = DatabaseService::new().await?;
+
+        Ok(PredictionOutcomeTracker {
+            model_prediction_repository: ModelPredictionRepository::new(database.client),
+            timeframe_id,
+            horizon_candles: horizon_candles.max(1) as i32,
+        })
+    }
+
+    pub async fn track_outcomes(&self) -> Result<u64> {
+        let evaluated = self
+            .model_prediction_repository
+            .evaluate_outcomes(&self.timeframe_id, self.horizon_candles)
+            .await?;
+
+        if evaluated > 0 {
+            self.model_prediction_repository
+                .refresh_scoreboard(&self.timeframe_id, SCOREBOARD_WINDOW)
+                .await?;
+            tracing::info!(
+                "Evaluated {} predictions for timeframe {}",
+                evaluated,
+                self.timeframe_id
+            );
+        }
+
+        Ok(evaluated)
+    }
+}
@@ -0,0 +1,65 @@
+use anyhow::Result;
+use chrono::{Duration as DurationChrono, Utc};
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tokio::time::interval;
+
+use crate::repositories::storage_repository::StorageRepository;
+
+use super::configuration_service::StorageConfig;
+use super::database_service::DatabaseService;
+
+const RETENTION_INTERVAL: u64 = 86400; // 1 day in seconds
+
+// Keeps the candle storage bounded: the 1m candles past the retention are
+// deleted daily, the higher timeframes are kept.
+pub struct StoragePolicyService {
+    config: StorageConfig,
+    storage_repository: StorageRepository,
+}
+
+impl StoragePolicyService {
+    pub async fn new(config: StorageConfig) -> Result<Self> {
+        let database = DatabaseService::new().await?;
+
+        Ok(StoragePolicyService {
+            config,
+            storage_repository: StorageRepository::new(database.client),
+        })
+    }
+
+    pub async fn run(&self, mut shutdown: broadcast::Receiver<()>) {
+        let mut ticker = interval(Duration::from_secs(RETENTION_INTERVAL));
+        loop {
+            tokio::select! {
+                _ = ticker.tick() => {
+                    if let Err(e) = self.enforce_retention().await {
+                        tracing::error!("Enforcing the retention failed: {}", e);
+                    }
+                }
+                _ = shutdown.recv() => return,
+            }
+        }
+    }
+
+    // Returns the number of 1m candles deleted
+    pub async fn enforce_retention(&self) -> Result<u64> {
+        let Some(days) = self.config.raw_retention_days else {
+            return Ok(0);
+        };
+        let cutoff = Utc::now() - DurationChrono::days(days.into());
+
+        let candles = self.storage_repository.delete_raw_candles(cutoff).await?;
+        let archives = self.storage_repository.delete_raw_archives(cutoff).await?;
+        if candles > 0 || archives > 0 {
+            tracing::info!(
+                "Deleted {} 1m candles and {} archives older than {}",
+                candles,
+                archives,
+                cutoff
+            );
+        }
+
+        Ok(candles)
+    }
+}
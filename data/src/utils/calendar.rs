@@ -0,0 +1,236 @@
+use chrono::{DateTime, Datelike, Duration, NaiveDate, TimeZone, Timelike, Utc, Weekday};
+use chrono_tz::America::New_York;
+use chrono_tz::Europe::London;
+
+use crate::models::market_data::TradingSession;
+
+/// Calendar context of a candle, derived from its open time only.
+pub struct CalendarFeatures {
+    pub trading_session: TradingSession,
+    pub day_of_week: i16, // 0 for Monday through 6 for Sunday
+    pub hours_to_weekly_close: f64,
+    pub hours_to_monthly_close: f64,
+    pub is_holiday: bool,
+}
+
+impl CalendarFeatures {
+    pub fn from_time(time: DateTime<Utc>) -> Self {
+        Self {
+            trading_session: Self::trading_session(time),
+            day_of_week: time.weekday().num_days_from_monday() as i16,
+            hours_to_weekly_close: Self::hours_until(time, Self::weekly_close(time)),
+            hours_to_monthly_close: Self::hours_until(time, Self::monthly_close(time)),
+            is_holiday: Self::is_low_liquidity_holiday(time.date_naive()),
+        }
+    }
+
+    // Sessions by local hour, so they shift with daylight saving time: Europe
+    // from the 08:00 London open, US from 09:00 in New York before the open
+    // until 17:00 after the close, and Asia from the Sydney/Tokyo open in
+    // between
+    pub fn trading_session(time: DateTime<Utc>) -> TradingSession {
+        let new_york_hour = time.with_timezone(&New_York).hour();
+        if (9..17).contains(&new_york_hour) {
+            TradingSession::Us
+        } else if time.with_timezone(&London).hour() >= 8 && new_york_hour < 9 {
+            TradingSession::Europe
+        } else {
+            TradingSession::Asia
+        }
+    }
+
+    // 0 for Monday 00:00 UTC through 167 for Sunday 23:00 UTC
+    pub fn hour_of_week(time: DateTime<Utc>) -> u32 {
+        time.weekday().num_days_from_monday() * 24 + time.hour()
+    }
+
+    // Weekly candles close on Monday 00:00 UTC
+    fn weekly_close(time: DateTime<Utc>) -> DateTime<Utc> {
+        let days_until_monday = 7 - time.weekday().num_days_from_monday() as i64;
+        let next_monday = time.date_naive() + Duration::days(days_until_monday);
+        Utc.from_utc_datetime(&next_monday.and_hms_opt(0, 0, 0).unwrap())
+    }
+
+    fn monthly_close(time: DateTime<Utc>) -> DateTime<Utc> {
+        let (year, month) = if time.month() == 12 {
+            (time.year() + 1, 1)
+        } else {
+            (time.year(), time.month() + 1)
+        };
+        let first_of_month = NaiveDate::from_ymd_opt(year, month, 1).unwrap();
+        Utc.from_utc_datetime(&first_of_month.and_hms_opt(0, 0, 0).unwrap())
+    }
+
+    fn hours_until(from: DateTime<Utc>, to: DateTime<Utc>) -> f64 {
+        (to - from).num_seconds() as f64 / 3600.0
+    }
+
+    // Holidays with historically thin volume: Christmas/New Year, Easter and
+    // the US market holidays around July 4th and Thanksgiving
+    pub fn is_low_liquidity_holiday(date: NaiveDate) -> bool {
+        let fixed = matches!(
+            (date.month(), date.day()),
+            (1, 1) | (7, 4) | (12, 24) | (12, 25) | (12, 26) | (12, 31)
+        );
+
+        let easter = Self::easter_sunday(date.year());
+        let around_easter =
+            date >= easter - Duration::days(2) && date <= easter + Duration::days(1);
+
+        let thanksgiving = NaiveDate::from_weekday_of_month_opt(date.year(), 11, Weekday::Thu, 4);
+        let is_thanksgiving =
+            thanksgiving.is_some_and(|t| date == t || date == t + Duration::days(1));
+
+        fixed || around_easter || is_thanksgiving
+    }
+
+    // Anonymous Gregorian algorithm
+    fn easter_sunday(year: i32) -> NaiveDate {
+        let a = year % 19;
+        let b = year / 100;
+        let c = year % 100;
+        let d = b / 4;
+        let e = b % 4;
+        let f = (b + 8) / 25;
+        let g = (b - f + 1) / 3;
+        let h = (19 * a + b - d - g + 15) % 30;
+        let i = c / 4;
+        let k = c % 4;
+        let l = (32 + 2 * e + 2 * i - h - k) % 7;
+        let m = (a + 11 * h + 22 * l) / 451;
+        let month = (h + l - 7 * m + 114) / 31;
+        let day = (h + l - 7 * m + 114) % 31 + 1;
+        NaiveDate::from_ymd_opt(year, month as u32, day as u32).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(year: i32, month: u32, day: u32, hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(year, month, day, hour, minute, 0)
+            .unwrap()
+    }
+
+    fn sessions(times: &[DateTime<Utc>]) -> Vec<TradingSession> {
+        times
+            .iter()
+            .map(|time| CalendarFeatures::trading_session(*time))
+            .collect()
+    }
+
+    #[test]
+    fn winter_sessions() {
+        let day = |hour, minute| utc(2024, 1, 15, hour, minute);
+
+        assert_eq!(
+            sessions(&[
+                day(7, 59),
+                day(8, 0),
+                day(13, 59),
+                day(14, 0),
+                day(21, 59),
+                day(22, 0)
+            ]),
+            vec![
+                TradingSession::Asia,
+                TradingSession::Europe,
+                TradingSession::Europe,
+                TradingSession::Us,
+                TradingSession::Us,
+                TradingSession::Asia,
+            ]
+        );
+    }
+
+    #[test]
+    fn summer_sessions_open_an_hour_earlier() {
+        let day = |hour, minute| utc(2024, 7, 15, hour, minute);
+
+        assert_eq!(
+            sessions(&[
+                day(6, 59),
+                day(7, 0),
+                day(12, 59),
+                day(13, 0),
+                day(20, 59),
+                day(21, 0)
+            ]),
+            vec![
+                TradingSession::Asia,
+                TradingSession::Europe,
+                TradingSession::Europe,
+                TradingSession::Us,
+                TradingSession::Us,
+                TradingSession::Asia,
+            ]
+        );
+    }
+
+    #[test]
+    fn sessions_between_the_us_and_uk_clock_changes() {
+        // New York moved to summer time on March 10 2024, London on March 31
+        let day = |hour, minute| utc(2024, 3, 20, hour, minute);
+        assert_eq!(
+            sessions(&[day(7, 59), day(8, 0), day(12, 59), day(13, 0), day(21, 0)]),
+            vec![
+                TradingSession::Asia,
+                TradingSession::Europe,
+                TradingSession::Europe,
+                TradingSession::Us,
+                TradingSession::Asia,
+            ]
+        );
+
+        // London changes at 01:00 UTC
+        assert_eq!(
+            sessions(&[utc(2024, 3, 30, 7, 0), utc(2024, 4, 1, 7, 0)]),
+            vec![TradingSession::Asia, TradingSession::Europe]
+        );
+    }
+
+    #[test]
+    fn week_turns_on_monday_midnight() {
+        let sunday = CalendarFeatures::from_time(utc(2024, 1, 14, 23, 59));
+        let monday = CalendarFeatures::from_time(utc(2024, 1, 15, 0, 0));
+
+        assert_eq!(sunday.day_of_week, 6);
+        assert_eq!(monday.day_of_week, 0);
+        assert!((sunday.hours_to_weekly_close - 1.0 / 60.0).abs() < 1e-9);
+        assert_eq!(monday.hours_to_weekly_close, 168.0);
+        assert_eq!(CalendarFeatures::hour_of_week(utc(2024, 1, 14, 23, 0)), 167);
+        assert_eq!(CalendarFeatures::hour_of_week(utc(2024, 1, 15, 0, 0)), 0);
+    }
+
+    #[test]
+    fn month_closes_across_the_year_end() {
+        let features = CalendarFeatures::from_time(utc(2024, 12, 31, 23, 0));
+
+        assert_eq!(features.hours_to_monthly_close, 1.0);
+        assert!(features.is_holiday);
+    }
+
+    #[test]
+    fn holidays_around_easter_and_thanksgiving() {
+        // Easter Sunday fell on March 31 2024, Thanksgiving on November 28
+        let holidays: Vec<bool> = [
+            (3, 28),
+            (3, 29),
+            (4, 1),
+            (4, 2),
+            (11, 28),
+            (11, 29),
+            (11, 30),
+        ]
+        .iter()
+        .map(|(month, day)| {
+            CalendarFeatures::is_low_liquidity_holiday(
+                NaiveDate::from_ymd_opt(2024, *month, *day).unwrap(),
+            )
+        })
+        .collect();
+
+        assert_eq!(holidays, vec![false, true, true, false, true, true, false]);
+    }
+}
@@ -0,0 +1,333 @@
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use thiserror::Error;
+use uuid::Uuid;
+
+use crate::models::market_data::MarketData;
+
+// Version 2 adds the taker buy volumes, version 1 archives decode without them
+pub const ARCHIVE_FORMAT_VERSION: u8 = 2;
+pub const ARCHIVE_PRICE_SCALE: u32 = 8;
+
+#[derive(Debug, Error)]
+pub enum CandleCodecError {
+    #[error("Unsupported archive format version {0}")]
+    UnsupportedVersion(u8),
+    #[error("Archive payload is truncated")]
+    Truncated,
+    #[error("Value out of range for {0}")]
+    OutOfRange(&'static str),
+}
+
+type Result<T> = std::result::Result<T, CandleCodecError>;
+
+/// Compact encoding of OHLCV candles ordered by ascending open_time.
+///
+/// Open times are stored as delta-of-delta milliseconds, close times as the
+/// change in candle span, and prices/volumes as integers scaled by
+/// 10^ARCHIVE_PRICE_SCALE relative to the previous close or the candle open.
+/// Every value is zigzag encoded as a LEB128 varint of up to 128 bits, so
+/// any Decimal holding eight decimals fits. The optional taker buy volumes
+/// are written as the varint plus one, zero standing for a missing value.
+pub struct CandleCodec;
+
+impl CandleCodec {
+    pub fn encode(candles: &[MarketData]) -> Result<Vec<u8>> {
+        let mut buffer = Vec::with_capacity(candles.len() * 16 + 8);
+        buffer.push(ARCHIVE_FORMAT_VERSION);
+        write_varint(&mut buffer, candles.len() as u128);
+
+        let mut prev_time = 0_i64;
+        let mut prev_delta = 0_i64;
+        let mut prev_span = 0_i64;
+        let mut prev_close = 0_i128;
+
+        for (i, candle) in candles.iter().enumerate() {
+            let open_time = candle.open_time.timestamp_millis();
+            let span = candle.close_time.timestamp_millis() - open_time;
+            match i {
+                0 => write_signed(&mut buffer, open_time.into()),
+                1 => write_signed(&mut buffer, (open_time - prev_time).into()),
+                _ => write_signed(&mut buffer, (open_time - prev_time - prev_delta).into()),
+            }
+            if i > 0 {
+                prev_delta = open_time - prev_time;
+            }
+            prev_time = open_time;
+            write_signed(&mut buffer, (span - prev_span).into());
+            prev_span = span;
+
+            let open = to_scaled(candle.open, "open")?;
+            let high = to_scaled(candle.high, "high")?;
+            let low = to_scaled(candle.low, "low")?;
+            let close = to_scaled(candle.close, "close")?;
+            write_signed(&mut buffer, open - prev_close);
+            write_signed(&mut buffer, high - open);
+            write_signed(&mut buffer, low - open);
+            write_signed(&mut buffer, close - open);
+            prev_close = close;
+
+            write_signed(&mut buffer, to_scaled(candle.volume, "volume")?);
+            write_signed(&mut buffer, candle.trades.into());
+            write_optional(&mut buffer, candle.taker_buy_volume, "taker_buy_volume")?;
+            write_optional(
+                &mut buffer,
+                candle.taker_buy_quote_volume,
+                "taker_buy_quote_volume",
+            )?;
+        }
+
+        Ok(buffer)
+    }
+
+    pub fn decode(
+        payload: &[u8],
+        timeframe_id: Uuid,
+        symbol: &str,
+        contract_type: &str,
+    ) -> Result<Vec<MarketData>> {
+        let mut reader = Reader {
+            payload,
+            position: 0,
+        };
+
+        let version = reader.byte()?;
+        if !(1..=ARCHIVE_FORMAT_VERSION).contains(&version) {
+            return Err(CandleCodecError::UnsupportedVersion(version));
+        }
+        let count =
+            usize::try_from(reader.varint()?).map_err(|_| CandleCodecError::OutOfRange("count"))?;
+        let mut candles = Vec::with_capacity(count);
+
+        let mut prev_time = 0_i64;
+        let mut prev_delta = 0_i64;
+        let mut prev_span = 0_i64;
+        let mut prev_close = 0_i128;
+
+        for i in 0..count {
+            let encoded_time = reader.signed_i64("timestamp")?;
+            let open_time = match i {
+                0 => encoded_time,
+                1 => prev_time + encoded_time,
+                _ => prev_time + prev_delta + encoded_time,
+            };
+            if i > 0 {
+                prev_delta = open_time - prev_time;
+            }
+            prev_time = open_time;
+            let span = prev_span + reader.signed_i64("timestamp")?;
+            prev_span = span;
+
+            let open = prev_close + reader.signed()?;
+            let high = open + reader.signed()?;
+            let low = open + reader.signed()?;
+            let close = open + reader.signed()?;
+            prev_close = close;
+            let volume = reader.signed()?;
+            let trades = reader.signed_i64("trades")?;
+            let (taker_buy_volume, taker_buy_quote_volume) = if version >= 2 {
+                (
+                    reader.optional("taker_buy_volume")?,
+                    reader.optional("taker_buy_quote_volume")?,
+                )
+            } else {
+                (None, None)
+            };
+
+            let mut candle = MarketData::new(
+                timeframe_id,
+                symbol.to_string(),
+                contract_type.to_string(),
+                to_time(open_time)?,
+                to_time(open_time + span)?,
+                from_scaled(open, "open")?,
+                from_scaled(close, "close")?,
+                from_scaled(high, "high")?,
+                from_scaled(low, "low")?,
+                from_scaled(volume, "volume")?,
+                trades,
+            );
+            candle.taker_buy_volume = taker_buy_volume;
+            candle.taker_buy_quote_volume = taker_buy_quote_volume;
+            candle.analyzed = true;
+            candles.push(candle);
+        }
+
+        Ok(candles)
+    }
+}
+
+struct Reader<'a> {
+    payload: &'a [u8],
+    position: usize,
+}
+
+impl Reader<'_> {
+    fn byte(&mut self) -> Result<u8> {
+        let byte = *self
+            .payload
+            .get(self.position)
+            .ok_or(CandleCodecError::Truncated)?;
+        self.position += 1;
+        Ok(byte)
+    }
+
+    fn varint(&mut self) -> Result<u128> {
+        let mut value = 0_u128;
+        let mut shift = 0;
+        loop {
+            let byte = self.byte()?;
+            if shift >= 128 {
+                return Err(CandleCodecError::OutOfRange("varint"));
+            }
+            value |= ((byte & 0x7f) as u128) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+            shift += 7;
+        }
+    }
+
+    fn signed(&mut self) -> Result<i128> {
+        Ok(unzigzag(self.varint()?))
+    }
+
+    fn optional(&mut self, field: &'static str) -> Result<Option<Decimal>> {
+        match self.varint()? {
+            0 => Ok(None),
+            value => from_scaled(unzigzag(value - 1), field).map(Some),
+        }
+    }
+
+    fn signed_i64(&mut self, field: &'static str) -> Result<i64> {
+        i64::try_from(self.signed()?).map_err(|_| CandleCodecError::OutOfRange(field))
+    }
+}
+
+fn write_varint(buffer: &mut Vec<u8>, mut value: u128) {
+    while value >= 0x80 {
+        buffer.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    buffer.push(value as u8);
+}
+
+fn write_signed(buffer: &mut Vec<u8>, value: i128) {
+    write_varint(buffer, zigzag(value));
+}
+
+fn write_optional(buffer: &mut Vec<u8>, value: Option<Decimal>, field: &'static str) -> Result<()> {
+    match value {
+        Some(value) => write_varint(buffer, zigzag(to_scaled(value, field)?) + 1),
+        None => write_varint(buffer, 0),
+    }
+    Ok(())
+}
+
+fn zigzag(value: i128) -> u128 {
+    ((value << 1) ^ (value >> 127)) as u128
+}
+
+fn unzigzag(value: u128) -> i128 {
+    ((value >> 1) as i128) ^ -((value & 1) as i128)
+}
+
+// Values too large to keep eight decimals are rescaled to fewer by
+// rust_decimal, they are rejected instead
+fn to_scaled(value: Decimal, field: &'static str) -> Result<i128> {
+    let mut scaled = value;
+    scaled.rescale(ARCHIVE_PRICE_SCALE);
+    if scaled.scale() != ARCHIVE_PRICE_SCALE {
+        return Err(CandleCodecError::OutOfRange(field));
+    }
+    Ok(scaled.mantissa())
+}
+
+fn from_scaled(value: i128, field: &'static str) -> Result<Decimal> {
+    Decimal::try_from_i128_with_scale(value, ARCHIVE_PRICE_SCALE)
+        .map_err(|_| CandleCodecError::OutOfRange(field))
+}
+
+fn to_time(millis: i64) -> Result<DateTime<Utc>> {
+    DateTime::<Utc>::from_timestamp_millis(millis).ok_or(CandleCodecError::OutOfRange("timestamp"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, TimeZone};
+    use std::str::FromStr;
+
+    fn candle(open_time: DateTime<Utc>, close: &str, volume: &str) -> MarketData {
+        let close = Decimal::from_str(close).unwrap();
+        MarketData::new(
+            Uuid::nil(),
+            "BTCUSDT".to_string(),
+            "PERPETUAL".to_string(),
+            open_time,
+            open_time + Duration::minutes(1) - Duration::milliseconds(1),
+            close,
+            close,
+            close,
+            close,
+            Decimal::from_str(volume).unwrap(),
+            12,
+        )
+    }
+
+    #[test]
+    fn round_trips_volumes_beyond_the_i64_mantissa() {
+        let start = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let candles = vec![
+            candle(start, "0.00000123", "987654321012.12345678").with_taker_buy_volumes(
+                Decimal::from_str("123456789012.5").unwrap(),
+                Decimal::from_str("0.15").unwrap(),
+            ),
+            candle(start + Duration::minutes(1), "0.00000124", "1.5"),
+        ];
+
+        let payload = CandleCodec::encode(&candles).unwrap();
+        let decoded = CandleCodec::decode(&payload, Uuid::nil(), "BTCUSDT", "PERPETUAL").unwrap();
+
+        assert_eq!(decoded.len(), 2);
+        for (decoded, original) in decoded.iter().zip(&candles) {
+            assert_eq!(decoded.open_time, original.open_time);
+            assert_eq!(decoded.close_time, original.close_time);
+            assert_eq!(decoded.close, original.close);
+            assert_eq!(decoded.volume, original.volume);
+            assert_eq!(decoded.trades, original.trades);
+            assert_eq!(decoded.taker_buy_volume, original.taker_buy_volume);
+            assert_eq!(
+                decoded.taker_buy_quote_volume,
+                original.taker_buy_quote_volume
+            );
+        }
+    }
+
+    #[test]
+    fn decodes_version_1_archives_without_taker_volumes() {
+        let start = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let candles = vec![candle(start, "42000.5", "10")];
+
+        // Version 1 had no taker volumes after the trades
+        let mut payload = CandleCodec::encode(&candles).unwrap();
+        payload[0] = 1;
+        payload.truncate(payload.len() - 2);
+        let decoded = CandleCodec::decode(&payload, Uuid::nil(), "BTCUSDT", "PERPETUAL").unwrap();
+
+        assert_eq!(decoded[0].close, candles[0].close);
+        assert_eq!(decoded[0].volume, candles[0].volume);
+        assert_eq!(decoded[0].taker_buy_volume, None);
+    }
+
+    #[test]
+    fn rejects_values_without_eight_decimals() {
+        let start = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let candles = vec![candle(start, "1", "79228162514264337593543950")];
+
+        assert!(matches!(
+            CandleCodec::encode(&candles),
+            Err(CandleCodecError::OutOfRange("volume"))
+        ));
+    }
+}
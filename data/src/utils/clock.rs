@@ -0,0 +1,29 @@
+use std::sync::atomic::{AtomicI64, Ordering};
+
+use chrono::{DateTime, Duration, Utc};
+
+use crate::services::configuration_service::Exchange;
+
+// Exchange server time minus local time, in milliseconds. Only the Binance
+// offset is measured, the other exchanges keep the local clock.
+static BINANCE_OFFSET_MS: AtomicI64 = AtomicI64::new(0);
+static OKX_OFFSET_MS: AtomicI64 = AtomicI64::new(0);
+static COINBASE_OFFSET_MS: AtomicI64 = AtomicI64::new(0);
+
+fn offset(exchange: Exchange) -> &'static AtomicI64 {
+    match exchange {
+        Exchange::Binance => &BINANCE_OFFSET_MS,
+        Exchange::Okx => &OKX_OFFSET_MS,
+        Exchange::Coinbase => &COINBASE_OFFSET_MS,
+    }
+}
+
+/// Current time on the clock of `exchange`, the local clock corrected by the
+/// last offset measured on that exchange
+pub fn now(exchange: Exchange) -> DateTime<Utc> {
+    Utc::now() + Duration::milliseconds(offset(exchange).load(Ordering::Relaxed))
+}
+
+pub fn set_offset_ms(exchange: Exchange, offset_ms: i64) {
+    offset(exchange).store(offset_ms, Ordering::Relaxed);
+}
@@ -0,0 +1,316 @@
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PositionClass {
+    Long,
+    Short,
+    None,
+}
+
+impl PositionClass {
+    pub const ALL: [PositionClass; 3] = [Self::Long, Self::Short, Self::None];
+
+    fn index(self) -> usize {
+        match self {
+            Self::Long => 0,
+            Self::Short => 1,
+            Self::None => 2,
+        }
+    }
+
+    // Class of a signed value, values within `neutral_band` of zero are None
+    pub fn from_signed(value: f64, neutral_band: f64) -> Self {
+        if value > neutral_band {
+            Self::Long
+        } else if value < -neutral_band {
+            Self::Short
+        } else {
+            Self::None
+        }
+    }
+
+    // 1 long, -1 short, 0 none, as stored in the database
+    pub fn sign(self) -> i16 {
+        match self {
+            Self::Long => 1,
+            Self::Short => -1,
+            Self::None => 0,
+        }
+    }
+
+    // One-vs-rest ranking score of this class for a signed prediction
+    fn score(self, prediction: f64) -> f64 {
+        match self {
+            Self::Long => prediction,
+            Self::Short => -prediction,
+            Self::None => -prediction.abs(),
+        }
+    }
+}
+
+impl fmt::Display for PositionClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Long => write!(f, "Long"),
+            Self::Short => write!(f, "Short"),
+            Self::None => write!(f, "None"),
+        }
+    }
+}
+
+pub struct LabeledPrediction {
+    pub prediction: f64, // Signed, > 0 long and < 0 short
+    pub predicted: PositionClass,
+    pub actual: PositionClass,
+}
+
+impl LabeledPrediction {
+    pub fn new(prediction: f64, realized_return: f64, neutral_band: f64) -> Self {
+        Self {
+            prediction,
+            predicted: PositionClass::from_signed(prediction, 0.0),
+            actual: PositionClass::from_signed(realized_return, neutral_band),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ClassMetrics {
+    pub precision: f64,
+    pub recall: f64,
+    pub f1: f64,
+    pub support: usize,
+    pub auc: Option<f64>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ClassificationReport {
+    pub samples: usize,
+    pub accuracy: f64,
+    pub confusion: [[usize; 3]; 3], // [actual][predicted] in PositionClass::ALL order
+    pub classes: [ClassMetrics; 3],
+    pub macro_auc: Option<f64>,
+}
+
+impl ClassificationReport {
+    pub fn evaluate(samples: &[LabeledPrediction]) -> Self {
+        let mut confusion = [[0_usize; 3]; 3];
+        for sample in samples {
+            confusion[sample.actual.index()][sample.predicted.index()] += 1;
+        }
+
+        let correct: usize = (0..3).map(|i| confusion[i][i]).sum();
+        let accuracy = ratio(correct as f64, samples.len() as f64);
+
+        let classes = PositionClass::ALL.map(|class| {
+            let i = class.index();
+            let true_positives = confusion[i][i] as f64;
+            let predicted: usize = (0..3).map(|actual| confusion[actual][i]).sum();
+            let support: usize = confusion[i].iter().sum();
+
+            let precision = ratio(true_positives, predicted as f64);
+            let recall = ratio(true_positives, support as f64);
+            ClassMetrics {
+                precision,
+                recall,
+                f1: ratio(2.0 * precision * recall, precision + recall),
+                support,
+                auc: Self::auc(samples, class),
+            }
+        });
+
+        let aucs: Vec<f64> = classes.iter().filter_map(|c| c.auc).collect();
+        let macro_auc = (!aucs.is_empty()).then(|| aucs.iter().sum::<f64>() / aucs.len() as f64);
+
+        Self {
+            samples: samples.len(),
+            accuracy,
+            confusion,
+            classes,
+            macro_auc,
+        }
+    }
+
+    // One-vs-rest ROC AUC from the Mann-Whitney U statistic, ties share ranks
+    fn auc(samples: &[LabeledPrediction], class: PositionClass) -> Option<f64> {
+        let mut scored: Vec<(f64, bool)> = samples
+            .iter()
+            .map(|s| (class.score(s.prediction), s.actual == class))
+            .collect();
+        let positives = scored.iter().filter(|(_, p)| *p).count();
+        let negatives = scored.len() - positives;
+        if positives == 0 || negatives == 0 {
+            return None;
+        }
+
+        scored.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal));
+        let mut positive_rank_sum = 0.0;
+        let mut start = 0;
+        while start < scored.len() {
+            let mut end = start;
+            while end + 1 < scored.len() && scored[end + 1].0 == scored[start].0 {
+                end += 1;
+            }
+            let rank = (start + end) as f64 / 2.0 + 1.0;
+            positive_rank_sum +=
+                rank * scored[start..=end].iter().filter(|(_, p)| *p).count() as f64;
+            start = end + 1;
+        }
+
+        let positives = positives as f64;
+        Some(
+            (positive_rank_sum - positives * (positives + 1.0) / 2.0)
+                / (positives * negatives as f64),
+        )
+    }
+}
+
+impl fmt::Display for ClassificationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "samples {}  accuracy {:.2}%  macro AUC {}",
+            self.samples,
+            self.accuracy * 100.0,
+            format_auc(self.macro_auc)
+        )?;
+
+        writeln!(
+            f,
+            "{:<8} {:>9} {:>9} {:>9} {:>9} {:>9}",
+            "class", "precision", "recall", "f1", "support", "auc"
+        )?;
+        for (class, metrics) in PositionClass::ALL.iter().zip(&self.classes) {
+            writeln!(
+                f,
+                "{:<8} {:>9.4} {:>9.4} {:>9.4} {:>9} {:>9}",
+                class.to_string(),
+                metrics.precision,
+                metrics.recall,
+                metrics.f1,
+                metrics.support,
+                format_auc(metrics.auc)
+            )?;
+        }
+
+        writeln!(f, "confusion (rows actual, columns predicted)")?;
+        write!(f, "{:<8}", "")?;
+        for class in PositionClass::ALL {
+            write!(f, " {:>9}", class.to_string())?;
+        }
+        for class in PositionClass::ALL {
+            write!(f, "\n{:<8}", class.to_string())?;
+            for count in self.confusion[class.index()] {
+                write!(f, " {:>9}", count)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn ratio(numerator: f64, denominator: f64) -> f64 {
+    if denominator == 0.0 {
+        0.0
+    } else {
+        numerator / denominator
+    }
+}
+
+fn format_auc(auc: Option<f64>) -> String {
+    auc.map(|a| format!("{:.4}", a))
+        .unwrap_or_else(|| "n/a".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BAND: f64 = 0.1;
+
+    fn sample(prediction: f64, realized_return: f64) -> LabeledPrediction {
+        LabeledPrediction::new(prediction, realized_return, BAND)
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() < 1e-9,
+            "{} != {}",
+            actual,
+            expected
+        );
+    }
+
+    // Long/Long twice, Long/Short, Short/Short, Short/None and None/None
+    // as predicted/actual
+    fn samples() -> Vec<LabeledPrediction> {
+        vec![
+            sample(0.5, 1.0),
+            sample(0.3, -1.0),
+            sample(-0.4, -1.0),
+            sample(-0.2, 0.05),
+            sample(0.0, 0.0),
+            sample(0.7, 2.0),
+        ]
+    }
+
+    #[test]
+    fn counts_the_confusion_matrix_by_actual_then_predicted() {
+        let report = ClassificationReport::evaluate(&samples());
+
+        assert_eq!(report.samples, 6);
+        assert_eq!(report.confusion, [[2, 0, 0], [1, 1, 0], [0, 1, 1]]);
+        assert_close(report.accuracy, 4.0 / 6.0);
+    }
+
+    #[test]
+    fn derives_precision_recall_and_f1_per_class() {
+        let report = ClassificationReport::evaluate(&samples());
+        let [long, short, none] = &report.classes;
+
+        assert_close(long.precision, 2.0 / 3.0);
+        assert_close(long.recall, 1.0);
+        assert_close(long.f1, 0.8);
+        assert_eq!(long.support, 2);
+        assert_close(short.precision, 0.5);
+        assert_close(short.recall, 0.5);
+        assert_close(short.f1, 0.5);
+        assert_close(none.precision, 1.0);
+        assert_close(none.recall, 0.5);
+        assert_close(none.f1, 2.0 / 3.0);
+    }
+
+    #[test]
+    fn computes_one_vs_rest_auc_from_the_ranks() {
+        let report = ClassificationReport::evaluate(&samples());
+
+        // Short scores -0.3 and 0.4 beat 2 and 4 of the 4 other scores
+        assert_close(report.classes[0].auc.unwrap(), 1.0);
+        assert_close(report.classes[1].auc.unwrap(), 0.75);
+        assert_close(report.classes[2].auc.unwrap(), 1.0);
+        assert_close(report.macro_auc.unwrap(), 2.75 / 3.0);
+    }
+
+    #[test]
+    fn counts_tied_scores_as_half_a_pair() {
+        // Long scores 1.0 and 0.5 against 0.5 and 0.0: 3.5 of 4 pairs
+        let samples = [
+            sample(1.0, 1.0),
+            sample(0.5, 1.0),
+            sample(0.5, -1.0),
+            sample(0.0, 0.0),
+        ];
+
+        let report = ClassificationReport::evaluate(&samples);
+
+        assert_close(report.classes[0].auc.unwrap(), 0.875);
+    }
+
+    #[test]
+    fn has_no_auc_for_a_class_without_negatives() {
+        let report = ClassificationReport::evaluate(&[sample(0.5, 1.0), sample(-0.5, 1.0)]);
+
+        assert_eq!(report.classes[0].auc, None);
+        assert_eq!(report.classes[1].auc, None);
+        assert_eq!(report.macro_auc, None);
+    }
+}
@@ -1,11 +1,13 @@
-use chrono::Duration;
+use chrono::{DateTime, Duration, Utc};
 use rust_decimal::{
     prelude::{FromPrimitive, ToPrimitive},
     Decimal,
 };
 use thiserror::Error;
 
-use crate::models::market_data::{MarketData, MarketRegime, PricePattern};
+use crate::models::market_data::{HourlyActivity, MarketData, MarketRegime, PricePattern};
+
+use super::rolling::{RollingEma, WindowStats};
 
 pub struct Helper {}
 
@@ -70,15 +72,12 @@ impl Helper {
         let slow_period = 26;
         let signal_period = 9;
 
-        let mut fast_emas = Vec::with_capacity(closes.len());
-        let mut slow_emas = Vec::with_capacity(closes.len());
+        let mut fast_ema = RollingEma::new(fast_period);
+        let mut slow_ema = RollingEma::new(slow_period);
         let mut macd_lines = Vec::with_capacity(closes.len());
 
-        for i in 0..closes.len() {
-            let slice = &closes[0..=i];
-            fast_emas.push(Helper::exponential_ma(slice, fast_period));
-            slow_emas.push(Helper::exponential_ma(slice, slow_period));
-            macd_lines.push(fast_emas[i] - slow_emas[i]);
+        for &close in closes {
+            macd_lines.push(fast_ema.push(close) - slow_ema.push(close));
         }
 
         // Calculate signal line from MACD values
@@ -94,8 +93,9 @@ impl Helper {
         period: usize,
         std_dev: f64,
     ) -> (f64, f64, f64) {
-        let sma = Helper::simple_ma(closes, period);
-        let std = Helper::standard_deviation(closes, period);
+        let window = WindowStats::from_values(period, closes.iter().take(period).copied());
+        let sma = window.mean();
+        let std = window.period_std_dev();
 
         let upper = sma + std_dev * std;
         let lower = sma - std_dev * std;
@@ -104,7 +104,7 @@ impl Helper {
     }
 
     pub fn calculate_atr(data: &[MarketData], period: usize) -> f64 {
-        let mut tr = Vec::with_capacity(data.len());
+        let mut atr = RollingEma::new(period);
 
         for i in 1..data.len() {
             let high = data[i].high.to_f64().unwrap();
@@ -115,22 +115,20 @@ impl Helper {
             let tr_2 = (high - prev_close).abs();
             let tr_3 = (low - prev_close).abs();
 
-            tr.push(tr_1.max(tr_2).max(tr_3));
+            atr.push(tr_1.max(tr_2).max(tr_3));
         }
 
-        Helper::exponential_ma(&tr, period)
+        atr.value()
     }
 
     pub fn calculate_volatility(closes: &[f64], hours: i32) -> f64 {
-        let returns: Vec<f64> = closes.windows(2).map(|w| (w[1] - w[0]) / w[0]).collect();
-
-        let period = if returns.len() >= (hours * 60) as usize {
-            (hours * 60) as usize
-        } else {
-            returns.len()
-        };
+        let period = ((hours * 60) as usize).min(closes.len().saturating_sub(1));
+        let returns = WindowStats::from_values(
+            period,
+            closes.windows(2).take(period).map(|w| (w[1] - w[0]) / w[0]),
+        );
 
-        Helper::standard_deviation(&returns, period) * (252_f64 * 24.0 / hours as f64).sqrt()
+        returns.period_std_dev() * (252_f64 * 24.0 / hours as f64).sqrt()
     }
 
     pub fn calculate_price_change(data: &[MarketData], hours: i64) -> Decimal {
@@ -151,18 +149,18 @@ impl Helper {
             return 0.0;
         }
 
-        let high = data
-            .iter()
-            .map(|d| d.high.to_f64().unwrap())
-            .fold(f64::MIN, f64::max);
-
-        let low = data
-            .iter()
-            .map(|d| d.low.to_f64().unwrap())
-            .fold(f64::MAX, f64::min);
+        let mut highs = WindowStats::new(data.len());
+        let mut lows = WindowStats::new(data.len());
+        let mut closes = WindowStats::new(data.len());
+        for d in data {
+            highs.push(d.high.to_f64().unwrap());
+            lows.push(d.low.to_f64().unwrap());
+            closes.push(d.close.to_f64().unwrap());
+        }
 
-        let avg_price =
-            data.iter().map(|d| d.close.to_f64().unwrap()).sum::<f64>() / data.len() as f64;
+        let high = highs.max().unwrap_or_default();
+        let low = lows.min().unwrap_or_default();
+        let avg_price = closes.mean();
 
         let basic_range = (high - low) / avg_price;
 
@@ -185,23 +183,16 @@ impl Helper {
             return 0.0;
         }
 
-        let returns: Vec<f64> = data
-            .windows(2)
-            .map(|window| {
+        let returns = WindowStats::from_values(
+            data.len() - 1,
+            data.windows(2).map(|window| {
                 let current = window[0].close.to_f64().unwrap();
                 let previous = window[1].close.to_f64().unwrap();
                 (current - previous) / previous
-            })
-            .collect();
-
-        let mean_return = returns.iter().sum::<f64>() / returns.len() as f64;
-        let variance = returns
-            .iter()
-            .map(|r| (r - mean_return).powi(2))
-            .sum::<f64>()
-            / returns.len() as f64;
+            }),
+        );
 
-        variance.sqrt()
+        returns.std_dev()
     }
 
     fn calculate_time_factor(data: &[MarketData]) -> f64 {
@@ -245,6 +236,41 @@ impl Helper {
 
         vol_ma * price_std
     }
+
+    // Ratio of the candle's volume and range to their per-candle average over
+    // the hours of `history` in the same bucket (session, hour of week, ...).
+    // Candles opened at a zero price have no range.
+    pub fn calculate_baseline_deviation(
+        current: &MarketData,
+        history: &[HourlyActivity],
+        in_bucket: impl Fn(DateTime<Utc>) -> bool,
+    ) -> (Option<f64>, Option<f64>) {
+        let (mut candles, mut volume, mut priced_candles, mut range) = (0, 0.0, 0, 0.0);
+        for hour in history.iter().filter(|h| in_bucket(h.hour)) {
+            candles += hour.candles;
+            volume += hour.volume;
+            priced_candles += hour.priced_candles;
+            range += hour.range;
+        }
+
+        let ratio = |value: Option<f64>, total: f64, count: i64| {
+            let baseline = total / count.max(1) as f64;
+            value
+                .filter(|_| baseline > 0.0)
+                .map(|value| value / baseline)
+        };
+        (
+            ratio(current.volume.to_f64(), volume, candles),
+            ratio(Self::candle_range(current), range, priced_candles),
+        )
+    }
+
+    // (high - low) / open, None for a zero open
+    fn candle_range(d: &MarketData) -> Option<f64> {
+        let open = d.open.to_f64().filter(|open| *open != 0.0)?;
+        Some((d.high - d.low).to_f64()? / open)
+    }
+
     pub fn exponential_ma(values: &[f64], period: usize) -> f64 {
         let alpha = 2.0 / (period + 1) as f64;
         let mut ema = values[0];
@@ -260,19 +286,11 @@ impl Helper {
         if values.is_empty() || period == 0 {
             return 0.0;
         }
-        let period = period.min(values.len());
-        values.iter().take(period).sum::<f64>() / period as f64
+        WindowStats::from_values(period, values.iter().take(period).copied()).mean()
     }
 
     pub fn standard_deviation(values: &[f64], period: usize) -> f64 {
-        let mean = Helper::simple_ma(values, period);
-        let variance = values
-            .iter()
-            .take(period)
-            .map(|x| (x - mean).powi(2))
-            .sum::<f64>()
-            / period as f64;
-        variance.sqrt()
+        WindowStats::from_values(period, values.iter().take(period).copied()).period_std_dev()
     }
 
     pub fn identify_market_regime(
@@ -951,7 +969,7 @@ impl Helper {
                     None
                 }
             }
-            PricePattern::None => None
+            PricePattern::None => None,
         };
 
         base_strength.map(|strength| {
@@ -0,0 +1,370 @@
+use std::env;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, Result};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use percent_encoding::percent_decode_str;
+use reqwest::Url;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{lookup_host, TcpStream};
+use tokio_socks::tcp::Socks5Stream;
+use tokio_tungstenite::{client_async_tls, connect_async, MaybeTlsStream, WebSocketStream};
+
+use crate::services::configuration_service::HttpConfig;
+
+const DEFAULT_TIMEOUT_SECONDS: u64 = 30;
+// Longest proxy answer to a CONNECT request
+const MAX_PROXY_RESPONSE_BYTES: usize = 8192;
+
+static CONFIG: OnceLock<HttpConfig> = OnceLock::new();
+
+/// Exchange base URLs, ending with a slash. Each one is read from its
+/// environment variable first, then from the `http` section of the
+/// configuration, and falls back to the public endpoint.
+#[derive(Debug, Clone, Copy)]
+pub enum Endpoint {
+    BinanceFuturesApi,
+    BinanceSpotApi,
+    BinanceFuturesStream,
+    BinanceSpotStream,
+    OkxApi,
+    CoinbaseApi,
+}
+
+impl Endpoint {
+    pub fn url(self) -> String {
+        let (env_var, configured, default) = match self {
+            Self::BinanceFuturesApi => (
+                "BINANCE_FUTURES_API_URL",
+                config().binance_futures_api_url.as_ref(),
+                "https://fapi.binance.com/",
+            ),
+            Self::BinanceSpotApi => (
+                "BINANCE_SPOT_API_URL",
+                config().binance_spot_api_url.as_ref(),
+                "https://api.binance.com/",
+            ),
+            Self::BinanceFuturesStream => (
+                "BINANCE_FUTURES_STREAM_URL",
+                config().binance_futures_stream_url.as_ref(),
+                "wss://fstream.binance.com/ws/",
+            ),
+            Self::BinanceSpotStream => (
+                "BINANCE_SPOT_STREAM_URL",
+                config().binance_spot_stream_url.as_ref(),
+                "wss://stream.binance.com:9443/ws/",
+            ),
+            Self::OkxApi => (
+                "OKX_API_URL",
+                config().okx_api_url.as_ref(),
+                "https://www.okx.com/",
+            ),
+            Self::CoinbaseApi => (
+                "COINBASE_API_URL",
+                config().coinbase_api_url.as_ref(),
+                "https://api.exchange.coinbase.com/",
+            ),
+        };
+
+        let url = env::var(env_var)
+            .ok()
+            .or_else(|| configured.cloned())
+            .unwrap_or_else(|| default.to_string());
+        if url.ends_with('/') {
+            url
+        } else {
+            format!("{}/", url)
+        }
+    }
+}
+
+/// Stores the `http` section of the configuration, to be called once at
+/// startup before any client is built
+pub fn init(config: HttpConfig) {
+    if CONFIG.set(config).is_err() {
+        tracing::warn!("HTTP settings already initialized");
+    }
+}
+
+fn config() -> &'static HttpConfig {
+    CONFIG.get_or_init(HttpConfig::default)
+}
+
+/// Client builder with the configured timeout and proxy. HTTP_TIMEOUT_SECONDS
+/// and HTTP_PROXY_URL override the configuration, without a proxy set the
+/// standard HTTP_PROXY, HTTPS_PROXY and ALL_PROXY variables still apply.
+pub fn client_builder() -> reqwest::ClientBuilder {
+    let timeout_seconds = env::var("HTTP_TIMEOUT_SECONDS")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .or(config().timeout_seconds)
+        .unwrap_or(DEFAULT_TIMEOUT_SECONDS);
+    let builder = reqwest::Client::builder().timeout(Duration::from_secs(timeout_seconds));
+
+    match configured_proxy().map(|url| reqwest::Proxy::all(&url).map_err(|e| (url, e))) {
+        Some(Ok(proxy)) => builder.proxy(proxy),
+        Some(Err((url, e))) => {
+            tracing::error!("Ignoring invalid proxy {}: {}", url, e);
+            builder
+        }
+        None => builder,
+    }
+}
+
+fn configured_proxy() -> Option<String> {
+    env::var("HTTP_PROXY_URL")
+        .ok()
+        .or_else(|| config().proxy.clone())
+}
+
+pub type WebSocket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// Opens a WebSocket stream through the same proxy as the HTTP clients. An
+/// http:// proxy is asked for a CONNECT tunnel and a socks5:// or socks5h://
+/// one opens the connection, the TLS handshake then runs through it.
+/// https:// proxies are refused for the streams.
+pub async fn connect_stream(url: &str) -> Result<WebSocket> {
+    let target = Url::parse(url)?;
+    connect_stream_through(&target, stream_proxy(&target)).await
+}
+
+async fn connect_stream_through(target: &Url, proxy: Option<Url>) -> Result<WebSocket> {
+    let url = target.as_str();
+    let Some(proxy) = proxy else {
+        let (socket, _) = connect_async(url).await?;
+        return Ok(socket);
+    };
+
+    let host = target
+        .host_str()
+        .ok_or_else(|| anyhow!("Stream URL {} has no host", url))?;
+    let port = target
+        .port_or_known_default()
+        .ok_or_else(|| anyhow!("Stream URL {} has no port", url))?;
+    let address = format!("{}:{}", host, port);
+    let proxy_address = format!(
+        "{}:{}",
+        proxy
+            .host_str()
+            .ok_or_else(|| anyhow!("Proxy {} has no host", proxy))?,
+        proxy
+            .port_or_known_default()
+            .ok_or_else(|| anyhow!("Proxy {} has no port", proxy))?
+    );
+    let username = percent_decode_str(proxy.username()).decode_utf8()?;
+    let password = percent_decode_str(proxy.password().unwrap_or_default()).decode_utf8()?;
+
+    let stream = match proxy.scheme() {
+        "http" => {
+            let credentials =
+                (!username.is_empty()).then(|| BASE64.encode(format!("{}:{}", username, password)));
+            connect_tunnel(&proxy_address, &address, credentials.as_deref()).await?
+        }
+        scheme @ ("socks5" | "socks5h") => {
+            // socks5 resolves the host locally, socks5h leaves it to the proxy
+            let target = if scheme == "socks5" {
+                lookup_host(&address)
+                    .await?
+                    .next()
+                    .ok_or_else(|| anyhow!("No address for {}", address))?
+                    .to_string()
+            } else {
+                address
+            };
+            let stream = if username.is_empty() {
+                Socks5Stream::connect(proxy_address.as_str(), target.as_str()).await?
+            } else {
+                Socks5Stream::connect_with_password(
+                    proxy_address.as_str(),
+                    target.as_str(),
+                    &username,
+                    &password,
+                )
+                .await?
+            };
+            stream.into_inner()
+        }
+        scheme => bail!("{}:// proxies are not supported for the streams", scheme),
+    };
+
+    let (socket, _) = client_async_tls(url, stream).await?;
+    Ok(socket)
+}
+
+// Proxy of a stream URL: the configured one, else the standard variables
+// unless NO_PROXY lists the host
+fn stream_proxy(target: &Url) -> Option<Url> {
+    let proxy = configured_proxy().or_else(|| {
+        let variables = match target.scheme() {
+            "wss" => ["HTTPS_PROXY", "https_proxy", "ALL_PROXY", "all_proxy"],
+            _ => ["HTTP_PROXY", "http_proxy", "ALL_PROXY", "all_proxy"],
+        };
+        let proxy = variables
+            .iter()
+            .find_map(|name| env::var(name).ok().filter(|v| !v.is_empty()))?;
+        let no_proxy = env::var("NO_PROXY")
+            .or_else(|_| env::var("no_proxy"))
+            .unwrap_or_default();
+        let host = target.host_str()?;
+        let bypassed = no_proxy
+            .split(',')
+            .map(|entry| entry.trim().trim_start_matches('.'))
+            .filter(|entry| !entry.is_empty())
+            .any(|entry| entry == "*" || host == entry || host.ends_with(&format!(".{}", entry)));
+        (!bypassed).then_some(proxy)
+    })?;
+
+    // Like reqwest, a proxy without a scheme is an HTTP one
+    let proxy = if proxy.contains("://") {
+        proxy
+    } else {
+        format!("http://{}", proxy)
+    };
+    match Url::parse(&proxy) {
+        Ok(url) => Some(url),
+        Err(e) => {
+            tracing::error!("Ignoring invalid proxy {}: {}", proxy, e);
+            None
+        }
+    }
+}
+
+// Asks an HTTP proxy to open a tunnel to `address`, the stream is returned
+// once the proxy answered with a 2xx status and the end of its headers
+async fn connect_tunnel(
+    proxy_address: &str,
+    address: &str,
+    credentials: Option<&str>,
+) -> Result<TcpStream> {
+    let mut stream = TcpStream::connect(proxy_address).await?;
+    let mut request = format!("CONNECT {0} HTTP/1.1\r\nHost: {0}\r\n", address);
+    if let Some(credentials) = credentials {
+        request.push_str(&format!("Proxy-Authorization: Basic {}\r\n", credentials));
+    }
+    request.push_str("\r\n");
+    stream.write_all(request.as_bytes()).await?;
+
+    // Byte by byte, nothing past the headers may be consumed
+    let mut response = Vec::new();
+    while !response.ends_with(b"\r\n\r\n") {
+        if response.len() >= MAX_PROXY_RESPONSE_BYTES {
+            bail!("Proxy {} sent no end of headers", proxy_address);
+        }
+        response.push(stream.read_u8().await?);
+    }
+
+    let response = String::from_utf8_lossy(&response);
+    let status_line = response.lines().next().unwrap_or_default();
+    match status_line.split_whitespace().nth(1) {
+        Some(status) if status.starts_with('2') => Ok(stream),
+        _ => bail!(
+            "Proxy {} refused the tunnel to {}: {}",
+            proxy_address,
+            address,
+            status_line
+        ),
+    }
+}
+
+/// Client with the configured timeout and proxy
+pub fn client() -> reqwest::Client {
+    client_builder()
+        .build()
+        .expect("HTTP client settings are valid")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_util::StreamExt;
+    use std::sync::Arc;
+    use tokio::net::TcpListener;
+    use tokio::sync::Mutex;
+    use tokio_tungstenite::{accept_async, tungstenite::Message};
+
+    // WebSocket server sending one text message to each client
+    async fn websocket_server() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let mut socket = accept_async(stream).await.unwrap();
+                futures_util::SinkExt::send(&mut socket, Message::Text("hello".into()))
+                    .await
+                    .unwrap();
+            }
+        });
+        format!("ws://{}/ws/btcusdt@kline_1m", address)
+    }
+
+    // HTTP proxy answering CONNECT with `status`, tunneling on a 200, the
+    // received requests are kept
+    async fn connect_proxy(status: &'static str) -> (Url, Arc<Mutex<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let received = requests.clone();
+        tokio::spawn(async move {
+            while let Ok((mut client, _)) = listener.accept().await {
+                let mut request = Vec::new();
+                while !request.ends_with(b"\r\n\r\n") {
+                    request.push(client.read_u8().await.unwrap());
+                }
+                let request = String::from_utf8(request).unwrap();
+                let target = request.split_whitespace().nth(1).unwrap().to_string();
+                received.lock().await.push(request);
+
+                client
+                    .write_all(format!("HTTP/1.1 {}\r\n\r\n", status).as_bytes())
+                    .await
+                    .unwrap();
+                if status.starts_with("200") {
+                    let mut server = TcpStream::connect(target).await.unwrap();
+                    tokio::spawn(async move {
+                        let _ = tokio::io::copy_bidirectional(&mut client, &mut server).await;
+                    });
+                }
+            }
+        });
+        let proxy = Url::parse(&format!("http://user:p%40ss@{}", address)).unwrap();
+        (proxy, requests)
+    }
+
+    #[tokio::test]
+    async fn streams_through_the_connect_tunnel() {
+        let url = Url::parse(&websocket_server().await).unwrap();
+        let (proxy, requests) = connect_proxy("200 Connection established").await;
+
+        let mut socket = connect_stream_through(&url, Some(proxy)).await.unwrap();
+        let message = socket.next().await.unwrap().unwrap();
+
+        assert_eq!(message, Message::Text("hello".into()));
+        let requests = requests.lock().await;
+        assert_eq!(requests.len(), 1);
+        let address = format!("{}:{}", url.host_str().unwrap(), url.port().unwrap());
+        assert!(requests[0].starts_with(&format!("CONNECT {} HTTP/1.1\r\n", address)));
+        // user:p@ss
+        assert!(requests[0].contains("Proxy-Authorization: Basic dXNlcjpwQHNz\r\n"));
+    }
+
+    #[tokio::test]
+    async fn fails_when_the_proxy_refuses_the_tunnel() {
+        let url = Url::parse(&websocket_server().await).unwrap();
+        let (proxy, _) = connect_proxy("407 Proxy Authentication Required").await;
+
+        let error = connect_stream_through(&url, Some(proxy)).await.unwrap_err();
+
+        assert!(error.to_string().contains("407"), "{}", error);
+    }
+
+    #[tokio::test]
+    async fn refuses_https_proxies() {
+        let url = Url::parse(&websocket_server().await).unwrap();
+        let proxy = Url::parse("https://127.0.0.1:1").unwrap();
+
+        let error = connect_stream_through(&url, Some(proxy)).await.unwrap_err();
+
+        assert!(error.to_string().contains("https:// proxies"), "{}", error);
+    }
+}
@@ -0,0 +1,162 @@
+use std::fmt;
+
+use super::evaluation::PositionClass;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Barrier {
+    Upper,
+    Lower,
+    Time,
+}
+
+impl fmt::Display for Barrier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Upper => write!(f, "upper"),
+            Self::Lower => write!(f, "lower"),
+            Self::Time => write!(f, "time"),
+        }
+    }
+}
+
+// High, low and close of a candle following the labeled one
+#[derive(Debug, Clone, Copy)]
+pub struct BarrierCandle {
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct BarrierLabel {
+    pub label: PositionClass,
+    pub barrier: Barrier,
+    pub realized_return: f64, // % from the entry close to the barrier exit
+    pub holding_candles: usize,
+}
+
+// Triple-barrier method: the upper barrier sits `profit_target` % above the
+// entry close, the lower one `stop_loss` % below it and the vertical one
+// `max_holding_candles` later. Touching the upper barrier first labels Long,
+// the lower one Short and running out of time None.
+#[derive(Debug, Clone, Copy)]
+pub struct TripleBarrier {
+    pub profit_target: f64,
+    pub stop_loss: f64,
+    pub max_holding_candles: usize,
+}
+
+impl TripleBarrier {
+    // `future` holds the candles after the entry, oldest first. None when no
+    // barrier was touched and the time limit lies beyond the known candles.
+    pub fn label(&self, entry_close: f64, future: &[BarrierCandle]) -> Option<BarrierLabel> {
+        if entry_close <= 0.0 {
+            return None;
+        }
+        let upper = entry_close * (1.0 + self.profit_target / 100.0);
+        let lower = entry_close * (1.0 - self.stop_loss / 100.0);
+
+        for (i, candle) in future.iter().take(self.max_holding_candles).enumerate() {
+            // The order within a candle is unknown, so a candle spanning both
+            // barriers counts as stopped out
+            if candle.low <= lower {
+                return Some(BarrierLabel {
+                    label: PositionClass::Short,
+                    barrier: Barrier::Lower,
+                    realized_return: -self.stop_loss,
+                    holding_candles: i + 1,
+                });
+            }
+            if candle.high >= upper {
+                return Some(BarrierLabel {
+                    label: PositionClass::Long,
+                    barrier: Barrier::Upper,
+                    realized_return: self.profit_target,
+                    holding_candles: i + 1,
+                });
+            }
+        }
+
+        if self.max_holding_candles == 0 || future.len() < self.max_holding_candles {
+            return None;
+        }
+        let exit = future[self.max_holding_candles - 1].close;
+        Some(BarrierLabel {
+            label: PositionClass::None,
+            barrier: Barrier::Time,
+            realized_return: (exit - entry_close) / entry_close * 100.0,
+            holding_candles: self.max_holding_candles,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BARRIER: TripleBarrier = TripleBarrier {
+        profit_target: 2.0,
+        stop_loss: 1.0,
+        max_holding_candles: 3,
+    };
+
+    fn candle(high: f64, low: f64, close: f64) -> BarrierCandle {
+        BarrierCandle { high, low, close }
+    }
+
+    fn outcome(label: &BarrierLabel) -> (PositionClass, Barrier, usize) {
+        (label.label, label.barrier, label.holding_candles)
+    }
+
+    #[test]
+    fn upper_barrier_hit_labels_long() {
+        let future = [candle(101.0, 99.5, 100.5), candle(102.0, 100.0, 101.5)];
+
+        let label = BARRIER.label(100.0, &future).unwrap();
+
+        assert_eq!(outcome(&label), (PositionClass::Long, Barrier::Upper, 2));
+        assert_eq!(label.realized_return, 2.0);
+    }
+
+    #[test]
+    fn lower_barrier_hit_labels_short() {
+        let future = [candle(100.5, 99.0, 99.2)];
+
+        let label = BARRIER.label(100.0, &future).unwrap();
+
+        assert_eq!(outcome(&label), (PositionClass::Short, Barrier::Lower, 1));
+        assert_eq!(label.realized_return, -1.0);
+    }
+
+    #[test]
+    fn time_barrier_labels_none_with_the_last_close() {
+        let future = [
+            candle(101.0, 99.5, 100.0),
+            candle(101.5, 99.5, 101.0),
+            candle(101.9, 99.1, 100.5),
+            candle(110.0, 90.0, 105.0),
+        ];
+
+        let label = BARRIER.label(100.0, &future).unwrap();
+
+        assert_eq!(outcome(&label), (PositionClass::None, Barrier::Time, 3));
+        assert!((label.realized_return - 0.5).abs() < 1e-12);
+    }
+
+    #[test]
+    fn candle_spanning_both_barriers_counts_as_stopped_out() {
+        let future = [candle(101.0, 99.5, 100.0), candle(103.0, 98.0, 102.5)];
+
+        let label = BARRIER.label(100.0, &future).unwrap();
+
+        assert_eq!(outcome(&label), (PositionClass::Short, Barrier::Lower, 2));
+    }
+
+    #[test]
+    fn unlabeled_until_the_time_barrier_is_known() {
+        let future = [candle(101.0, 99.5, 100.0), candle(101.5, 99.5, 101.0)];
+
+        assert!(BARRIER.label(100.0, &future).is_none());
+        assert!(BARRIER.label(0.0, &[candle(1.0, 0.0, 1.0); 3]).is_none());
+    }
+}
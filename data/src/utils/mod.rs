@@ -1 +1,13 @@
+pub mod calendar;
+pub mod candle_codec;
+pub mod evaluation;
 pub mod helper;
+pub mod labeling;
+pub mod order_book;
+pub mod rolling;
+pub mod timing;
+pub mod signing;
+pub mod retry;
+pub mod http;
+pub mod clock;
+pub mod rate_limit;
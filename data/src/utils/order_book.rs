@@ -0,0 +1,116 @@
+use std::collections::BTreeMap;
+
+use rust_decimal::Decimal;
+
+// Local copy of an exchange order book, kept in sync from a REST snapshot
+// and the diff-depth updates following it
+#[derive(Debug)]
+pub struct OrderBook {
+    bids: BTreeMap<Decimal, Decimal>,
+    asks: BTreeMap<Decimal, Decimal>,
+    last_update_id: i64,
+    synced: bool, // At least one update applied on top of the snapshot
+}
+
+// One diff-depth event: first (U), final (u) and previous final (pu) update ids
+#[derive(Debug, Clone)]
+pub struct DepthUpdate {
+    pub first_update_id: i64,
+    pub final_update_id: i64,
+    pub previous_final_update_id: i64,
+    pub bids: Vec<(Decimal, Decimal)>,
+    pub asks: Vec<(Decimal, Decimal)>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum DepthUpdateOutcome {
+    Applied,
+    Stale,
+    // An update was missed, the book must be rebuilt from a new snapshot
+    Gap,
+}
+
+impl OrderBook {
+    pub fn from_snapshot(
+        last_update_id: i64,
+        bids: Vec<(Decimal, Decimal)>,
+        asks: Vec<(Decimal, Decimal)>,
+    ) -> Self {
+        let mut book = Self {
+            bids: BTreeMap::new(),
+            asks: BTreeMap::new(),
+            last_update_id,
+            synced: false,
+        };
+        Self::apply_levels(&mut book.bids, bids);
+        Self::apply_levels(&mut book.asks, asks);
+        book
+    }
+
+    pub fn last_update_id(&self) -> i64 {
+        self.last_update_id
+    }
+
+    // Follows the Binance futures sync rules: updates ending before the
+    // snapshot are stale, the first one applied must straddle it and every
+    // later one must continue from the previous one
+    pub fn apply(&mut self, update: DepthUpdate) -> DepthUpdateOutcome {
+        if update.final_update_id < self.last_update_id
+            || (self.synced && update.final_update_id == self.last_update_id)
+        {
+            return DepthUpdateOutcome::Stale;
+        }
+
+        let in_sequence = if self.synced {
+            update.previous_final_update_id == self.last_update_id
+        } else {
+            update.first_update_id <= self.last_update_id
+        };
+        if !in_sequence {
+            return DepthUpdateOutcome::Gap;
+        }
+
+        Self::apply_levels(&mut self.bids, update.bids);
+        Self::apply_levels(&mut self.asks, update.asks);
+        self.last_update_id = update.final_update_id;
+        self.synced = true;
+        DepthUpdateOutcome::Applied
+    }
+
+    fn apply_levels(side: &mut BTreeMap<Decimal, Decimal>, levels: Vec<(Decimal, Decimal)>) {
+        for (price, quantity) in levels {
+            if quantity.is_zero() {
+                side.remove(&price);
+            } else {
+                side.insert(price, quantity);
+            }
+        }
+    }
+
+    // Best `levels` bids, highest first
+    pub fn top_bids(&self, levels: usize) -> Vec<(Decimal, Decimal)> {
+        self.bids
+            .iter()
+            .rev()
+            .take(levels)
+            .map(|(p, q)| (*p, *q))
+            .collect()
+    }
+
+    // Best `levels` asks, lowest first
+    pub fn top_asks(&self, levels: usize) -> Vec<(Decimal, Decimal)> {
+        self.asks
+            .iter()
+            .take(levels)
+            .map(|(p, q)| (*p, *q))
+            .collect()
+    }
+
+    // (bid - ask) / (bid + ask) quantity over the best `levels` of each side
+    pub fn imbalance(&self, levels: usize) -> Option<Decimal> {
+        let bid: Decimal = self.top_bids(levels).iter().map(|(_, q)| q).sum();
+        let ask: Decimal = self.top_asks(levels).iter().map(|(_, q)| q).sum();
+        let total = bid + ask;
+        (!total.is_zero()).then(|| (bid - ask) / total)
+    }
+}
@@ -0,0 +1,169 @@
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use reqwest::Url;
+use tokio::time::sleep_until;
+
+use crate::services::configuration_service::{Exchange, LimitsConfig};
+
+use super::http::Endpoint;
+
+const API_ENDPOINTS: [(Endpoint, Exchange); 4] = [
+    (Endpoint::BinanceFuturesApi, Exchange::Binance),
+    (Endpoint::BinanceSpotApi, Exchange::Binance),
+    (Endpoint::OkxApi, Exchange::Okx),
+    (Endpoint::CoinbaseApi, Exchange::Coinbase),
+];
+
+static CONFIG: OnceLock<LimitsConfig> = OnceLock::new();
+// Earliest start of the next request, by exchange and by exchange endpoint
+static NEXT_SLOTS: OnceLock<Mutex<HashMap<String, Instant>>> = OnceLock::new();
+
+/// Stores the `limits` section of the configuration, to be called once at
+/// startup before any request is sent
+pub fn init(config: LimitsConfig) {
+    if CONFIG.set(config).is_err() {
+        tracing::warn!("Request limits already initialized");
+    }
+}
+
+/// Waits until a request to `url` fits the requests per second configured
+/// for its exchange and endpoint. URLs outside the exchange APIs, such as
+/// the Binance Vision archives, are not paced.
+pub async fn pace(url: &Url) {
+    let Some((exchange, path)) = route(url) else {
+        return;
+    };
+    let Some(limits) = CONFIG.get().and_then(|c| c.exchanges.get(&exchange)) else {
+        return;
+    };
+
+    let mut start = Instant::now();
+    {
+        let mut slots = NEXT_SLOTS.get_or_init(Default::default).lock().unwrap();
+        if let Some(rate) = limits.requests_per_second {
+            start = reserve(&mut slots, format!("{:?}", exchange), rate, start);
+        }
+        if let Some(rate) = limits.endpoints.get(&path) {
+            start = reserve(&mut slots, format!("{:?} {}", exchange, path), *rate, start);
+        }
+    }
+    sleep_until(start.into()).await;
+}
+
+// Exchange and path relative to its base URL, without the query string
+fn route(url: &Url) -> Option<(Exchange, String)> {
+    API_ENDPOINTS.iter().find_map(|(endpoint, exchange)| {
+        let rest = url.as_str().strip_prefix(endpoint.url().as_str())?;
+        let path = rest.split('?').next().unwrap_or_default();
+        Some((*exchange, path.to_string()))
+    })
+}
+
+// First free slot of `key` from `earliest` on, the next one is booked a
+// period later
+fn reserve(
+    slots: &mut HashMap<String, Instant>,
+    key: String,
+    rate: f64,
+    earliest: Instant,
+) -> Instant {
+    if !rate.is_finite() || rate <= 0.0 {
+        return earliest;
+    }
+    let slot = slots
+        .get(&key)
+        .map_or(earliest, |next| (*next).max(earliest));
+    slots.insert(key, slot + Duration::from_secs_f64(1.0 / rate));
+    slot
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: f64 = 4.0;
+    const PERIOD: Duration = Duration::from_millis(250);
+
+    #[test]
+    fn requests_up_to_the_rate_fit_in_one_second() {
+        let mut slots = HashMap::new();
+        let now = Instant::now();
+
+        let starts: Vec<Duration> = (0..RATE as usize)
+            .map(|_| reserve(&mut slots, "key".to_string(), RATE, now) - now)
+            .collect();
+
+        assert_eq!(starts, vec![Duration::ZERO, PERIOD, PERIOD * 2, PERIOD * 3]);
+    }
+
+    #[test]
+    fn requests_above_the_rate_wait_for_the_next_second() {
+        let mut slots = HashMap::new();
+        let now = Instant::now();
+        for _ in 0..RATE as usize {
+            reserve(&mut slots, "key".to_string(), RATE, now);
+        }
+
+        let start = reserve(&mut slots, "key".to_string(), RATE, now);
+
+        assert_eq!(start - now, Duration::from_secs(1));
+    }
+
+    #[test]
+    fn idle_key_starts_again_right_away() {
+        let mut slots = HashMap::new();
+        let now = Instant::now();
+        for _ in 0..RATE as usize {
+            reserve(&mut slots, "key".to_string(), RATE, now);
+        }
+
+        // The booked slots have passed, none carries over
+        let later = now + Duration::from_secs(5);
+        let start = reserve(&mut slots, "key".to_string(), RATE, later);
+
+        assert_eq!(start, later);
+        assert_eq!(slots["key"], later + PERIOD);
+    }
+
+    #[test]
+    fn keys_are_paced_apart() {
+        let mut slots = HashMap::new();
+        let now = Instant::now();
+        reserve(&mut slots, "Binance".to_string(), RATE, now);
+
+        let start = reserve(&mut slots, "Okx".to_string(), RATE, now);
+
+        assert_eq!(start, now);
+    }
+
+    #[test]
+    fn invalid_rates_are_not_paced() {
+        let mut slots = HashMap::new();
+        let now = Instant::now();
+
+        for rate in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+            assert_eq!(reserve(&mut slots, "key".to_string(), rate, now), now);
+        }
+        assert!(slots.is_empty());
+    }
+
+    #[test]
+    fn urls_are_routed_to_their_exchange_path() {
+        let url = Url::parse(&format!(
+            "{}fapi/v1/aggTrades?symbol=BTCUSDT",
+            Endpoint::BinanceFuturesApi.url()
+        ))
+        .unwrap();
+
+        assert_eq!(
+            route(&url),
+            Some((Exchange::Binance, "fapi/v1/aggTrades".to_string()))
+        );
+        assert_eq!(
+            route(&Url::parse("https://data.binance.vision/data/futures").unwrap()),
+            None
+        );
+    }
+}
@@ -0,0 +1,209 @@
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use rand::Rng;
+use reqwest::{header::RETRY_AFTER, RequestBuilder, Response, StatusCode};
+use tokio::time::sleep;
+
+use super::rate_limit;
+
+/// Retry policy shared by every HTTP call: rate limits (429, and 418 once
+/// Binance bans the IP), server errors, timeouts and connection failures are
+/// retried with exponential backoff and jitter, or after the Retry-After
+/// delay when the response carries one, until `max_elapsed` would be
+/// exceeded. The last response or error is then handed back to the caller.
+/// Every attempt waits for the configured request pacing first.
+pub struct RetryPolicy {
+    pub initial_delay: Duration,
+    pub max_delay: Duration,
+    pub max_elapsed: Duration,
+}
+
+impl RetryPolicy {
+    pub const DEFAULT: RetryPolicy = RetryPolicy {
+        initial_delay: Duration::from_millis(100),
+        max_delay: Duration::from_secs(10),
+        max_elapsed: Duration::from_secs(60),
+    };
+
+    /// Sends the request built by `build`, which is called again on every
+    /// attempt so signed requests get a fresh timestamp
+    pub async fn send<F>(&self, build: F) -> reqwest::Result<Response>
+    where
+        F: Fn() -> RequestBuilder,
+    {
+        let started = Instant::now();
+        let mut attempt = 0;
+
+        loop {
+            let (client, request) = build().build_split();
+            let result = match request {
+                Ok(request) => {
+                    rate_limit::pace(request.url()).await;
+                    client.execute(request).await
+                }
+                Err(e) => Err(e),
+            };
+            let (reason, retry_after) = match &result {
+                Ok(response) if Self::is_retryable(response.status()) => {
+                    (response.status().to_string(), Self::retry_after(response))
+                }
+                Err(e) if e.is_timeout() || e.is_connect() => (e.to_string(), None),
+                _ => return result,
+            };
+
+            let delay = retry_after.unwrap_or_else(|| self.backoff(attempt));
+            if started.elapsed() + delay > self.max_elapsed {
+                return result;
+            }
+            attempt += 1;
+            tracing::warn!(
+                "Request failed with {}, retry {} in {}ms",
+                reason,
+                attempt,
+                delay.as_millis()
+            );
+            sleep(delay).await;
+        }
+    }
+
+    fn is_retryable(status: StatusCode) -> bool {
+        status == StatusCode::TOO_MANY_REQUESTS
+            || status == StatusCode::IM_A_TEAPOT
+            || status.is_server_error()
+    }
+
+    fn retry_after(response: &Response) -> Option<Duration> {
+        response
+            .headers()
+            .get(RETRY_AFTER)
+            .and_then(|h| h.to_str().ok())
+            .and_then(|value| Self::parse_retry_after(value, Utc::now()))
+    }
+
+    // Retry-After in seconds or as an HTTP date, a date already past means
+    // no wait
+    fn parse_retry_after(value: &str, now: DateTime<Utc>) -> Option<Duration> {
+        let value = value.trim();
+        if let Ok(seconds) = value.parse::<u64>() {
+            return Some(Duration::from_secs(seconds));
+        }
+        let date = DateTime::parse_from_rfc2822(value).ok()?;
+        Some(
+            (date.with_timezone(&Utc) - now)
+                .to_std()
+                .unwrap_or_default(),
+        )
+    }
+
+    // Random delay between half and all of the capped exponential one, so
+    // workers rate limited together do not retry in lockstep
+    fn backoff(&self, attempt: u32) -> Duration {
+        self.ceiling(attempt)
+            .mul_f64(rand::thread_rng().gen_range(0.5..=1.0))
+    }
+
+    fn ceiling(&self, attempt: u32) -> Duration {
+        self.initial_delay
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max_delay)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use wiremock::matchers::method;
+    use wiremock::{Mock, MockServer, Request, Respond, ResponseTemplate};
+
+    #[test]
+    fn backoff_doubles_up_to_the_max_delay() {
+        let ceilings: Vec<u128> = (0..10)
+            .map(|attempt| RetryPolicy::DEFAULT.ceiling(attempt).as_millis())
+            .collect();
+
+        assert_eq!(
+            ceilings,
+            vec![100, 200, 400, 800, 1600, 3200, 6400, 10000, 10000, 10000]
+        );
+        assert_eq!(
+            RetryPolicy::DEFAULT.ceiling(u32::MAX),
+            RetryPolicy::DEFAULT.max_delay
+        );
+    }
+
+    #[test]
+    fn jitter_stays_between_half_and_all_of_the_ceiling() {
+        for attempt in [0, 3, 8] {
+            let ceiling = RetryPolicy::DEFAULT.ceiling(attempt);
+            for _ in 0..1000 {
+                let delay = RetryPolicy::DEFAULT.backoff(attempt);
+                assert!(delay >= ceiling / 2 && delay <= ceiling, "{:?}", delay);
+            }
+        }
+    }
+
+    #[test]
+    fn retry_after_in_seconds() {
+        let now = Utc::now();
+
+        assert_eq!(
+            RetryPolicy::parse_retry_after("120", now),
+            Some(Duration::from_secs(120))
+        );
+        assert_eq!(
+            RetryPolicy::parse_retry_after(" 0 ", now),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(RetryPolicy::parse_retry_after("-1", now), None);
+        assert_eq!(RetryPolicy::parse_retry_after("soon", now), None);
+    }
+
+    #[test]
+    fn retry_after_as_an_http_date() {
+        let now = Utc.with_ymd_and_hms(2015, 10, 21, 7, 27, 30).unwrap();
+
+        assert_eq!(
+            RetryPolicy::parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT", now),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(
+            RetryPolicy::parse_retry_after("Wed, 21 Oct 2015 07:00:00 GMT", now),
+            Some(Duration::ZERO)
+        );
+    }
+
+    // Answers 429 with Retry-After: 0 to the first request, then 200
+    struct RateLimitedOnce(Arc<AtomicUsize>);
+
+    impl Respond for RateLimitedOnce {
+        fn respond(&self, _: &Request) -> ResponseTemplate {
+            match self.0.fetch_add(1, Ordering::SeqCst) {
+                0 => ResponseTemplate::new(429).insert_header("Retry-After", "0"),
+                _ => ResponseTemplate::new(200),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn rate_limited_request_is_sent_again() {
+        let server = MockServer::start().await;
+        let requests = Arc::new(AtomicUsize::new(0));
+        Mock::given(method("GET"))
+            .respond_with(RateLimitedOnce(requests.clone()))
+            .mount(&server)
+            .await;
+        let client = reqwest::Client::new();
+
+        let response = RetryPolicy::DEFAULT
+            .send(|| client.get(server.uri()))
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
+    }
+}
@@ -0,0 +1,189 @@
+/// Mean, variance and extremes of the values of one window, updated on every
+/// push with Welford's algorithm so the variance stays accurate on large
+/// prices. Values are never evicted, a window is built from at most `period`
+/// values and read once.
+pub struct WindowStats {
+    period: usize,
+    count: usize,
+    mean: f64,
+    // Sum of the squared deviations from the mean
+    m2: f64,
+    min: Option<f64>,
+    max: Option<f64>,
+}
+
+impl WindowStats {
+    pub fn new(period: usize) -> Self {
+        Self {
+            period: period.max(1),
+            count: 0,
+            mean: 0.0,
+            m2: 0.0,
+            min: None,
+            max: None,
+        }
+    }
+
+    pub fn from_values<I>(period: usize, values: I) -> Self
+    where
+        I: IntoIterator<Item = f64>,
+    {
+        let mut window = Self::new(period);
+        for value in values {
+            window.push(value);
+        }
+        window
+    }
+
+    pub fn push(&mut self, value: f64) {
+        self.count += 1;
+        let delta = value - self.mean;
+        self.mean += delta / self.count as f64;
+        self.m2 += delta * (value - self.mean);
+
+        self.min = Some(self.min.map_or(value, |min| min.min(value)));
+        self.max = Some(self.max.map_or(value, |max| max.max(value)));
+    }
+
+    pub fn mean(&self) -> f64 {
+        self.mean
+    }
+
+    // Population variance, divided by `divisor` elements
+    fn variance_over(&self, divisor: usize) -> f64 {
+        if self.count == 0 || divisor == 0 {
+            return 0.0;
+        }
+        (self.m2 / divisor as f64).max(0.0)
+    }
+
+    pub fn variance(&self) -> f64 {
+        self.variance_over(self.count)
+    }
+
+    pub fn std_dev(&self) -> f64 {
+        self.variance().sqrt()
+    }
+
+    /// Standard deviation divided by the full window period even if fewer
+    /// values were pushed, matching `Helper::standard_deviation`.
+    pub fn period_std_dev(&self) -> f64 {
+        self.variance_over(self.period).sqrt()
+    }
+
+    pub fn min(&self) -> Option<f64> {
+        self.min
+    }
+
+    pub fn max(&self) -> Option<f64> {
+        self.max
+    }
+}
+
+/// Incrementally updated exponential moving average, seeded with the first
+/// value like `Helper::exponential_ma`.
+pub struct RollingEma {
+    alpha: f64,
+    value: Option<f64>,
+}
+
+impl RollingEma {
+    pub fn new(period: usize) -> Self {
+        Self {
+            alpha: 2.0 / (period + 1) as f64,
+            value: None,
+        }
+    }
+
+    pub fn push(&mut self, value: f64) -> f64 {
+        let ema = match self.value {
+            Some(ema) => value * self.alpha + ema * (1.0 - self.alpha),
+            None => value,
+        };
+        self.value = Some(ema);
+        ema
+    }
+
+    pub fn value(&self) -> f64 {
+        self.value.unwrap_or(0.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn naive_variance(values: &[f64], divisor: usize) -> f64 {
+        let mean = values.iter().sum::<f64>() / values.len() as f64;
+        values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / divisor as f64
+    }
+
+    // Relative to the expected value, absolute below 1
+    fn assert_close(actual: f64, expected: f64, tolerance: f64) {
+        let tolerance = tolerance * expected.abs().max(1.0);
+        assert!(
+            (actual - expected).abs() <= tolerance,
+            "{} != {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn matches_the_naive_statistics() {
+        let values = [3.5, -1.25, 8.0, 0.0, 2.75, 2.75, 10.5, -4.0];
+        let window = WindowStats::from_values(values.len(), values);
+
+        assert_close(
+            window.mean(),
+            values.iter().sum::<f64>() / values.len() as f64,
+            1e-12,
+        );
+        assert_close(
+            window.variance(),
+            naive_variance(&values, values.len()),
+            1e-12,
+        );
+        assert_eq!(window.min(), Some(-4.0));
+        assert_eq!(window.max(), Some(10.5));
+    }
+
+    #[test]
+    fn stays_accurate_on_large_prices() {
+        // A sum of squares loses every digit of the spread at this scale
+        let values: Vec<f64> = (0..500)
+            .map(|i| 1e9 + ((i * 37) % 101) as f64 * 0.01)
+            .collect();
+        let window = WindowStats::from_values(values.len(), values.iter().copied());
+
+        // The inputs themselves are rounded to 1.2e-7 at this scale
+        assert_close(
+            window.variance(),
+            naive_variance(&values, values.len()),
+            1e-6,
+        );
+        assert!(window.variance() > 0.0);
+    }
+
+    #[test]
+    fn period_std_dev_divides_by_the_period() {
+        let values = [1.0, 2.0, 4.0];
+        let window = WindowStats::from_values(5, values);
+
+        assert_close(
+            window.period_std_dev(),
+            naive_variance(&values, 5).sqrt(),
+            1e-12,
+        );
+        assert_close(window.std_dev(), naive_variance(&values, 3).sqrt(), 1e-12);
+    }
+
+    #[test]
+    fn empty_window_is_zero() {
+        let window = WindowStats::new(10);
+
+        assert_eq!(window.mean(), 0.0);
+        assert_eq!(window.variance(), 0.0);
+        assert_eq!(window.min(), None);
+    }
+}
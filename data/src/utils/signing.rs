@@ -0,0 +1,11 @@
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+// Hex encoded HMAC-SHA256 of `payload`, as Binance expects in the
+// `signature` parameter of signed endpoints
+pub fn hmac_sha256_hex(secret: &str, payload: &str) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any size");
+    mac.update(payload.as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
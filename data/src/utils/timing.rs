@@ -0,0 +1,68 @@
+use std::time::{Duration, Instant};
+
+/// Accumulates wall-clock time spent in named stages across a number of
+/// processed items and reports the per-item breakdown.
+#[derive(Default)]
+pub struct StageTimings {
+    stages: Vec<(&'static str, Duration)>,
+    samples: u32,
+}
+
+impl StageTimings {
+    pub fn record(&mut self, stage: &'static str, started: Instant) {
+        let elapsed = started.elapsed();
+        match self.stages.iter_mut().find(|(name, _)| *name == stage) {
+            Some((_, total)) => *total += elapsed,
+            None => self.stages.push((stage, elapsed)),
+        }
+    }
+
+    pub fn time<T>(&mut self, stage: &'static str, f: impl FnOnce() -> T) -> T {
+        let started = Instant::now();
+        let result = f();
+        self.record(stage, started);
+        result
+    }
+
+    pub fn tick(&mut self) {
+        self.samples += 1;
+    }
+
+    pub fn samples(&self) -> u32 {
+        self.samples
+    }
+
+    pub fn report(&self, label: &str) {
+        if self.samples == 0 {
+            return;
+        }
+
+        let total: Duration = self.stages.iter().map(|(_, d)| *d).sum();
+        let breakdown = self
+            .stages
+            .iter()
+            .map(|(name, d)| {
+                let share = if total.is_zero() {
+                    0.0
+                } else {
+                    d.as_secs_f64() / total.as_secs_f64() * 100.0
+                };
+                format!("{} {:?} ({:.1}%)", name, *d / self.samples, share)
+            })
+            .collect::<Vec<String>>()
+            .join(", ");
+
+        tracing::info!(
+            "{} timing over {} candles, {:?} per candle: {}",
+            label,
+            self.samples,
+            total / self.samples,
+            breakdown
+        );
+    }
+
+    pub fn reset(&mut self) {
+        self.stages.clear();
+        self.samples = 0;
+    }
+}